@@ -1,7 +1,7 @@
 //! Parsing of macro attributes.
 
 use proc_macro2::TokenStream;
-use proc_macro_error::{abort, abort_call_site, ResultExt};
+use proc_macro_error::{abort, ResultExt};
 use quote::quote;
 use syn::{
     self,
@@ -19,12 +19,27 @@ pub(crate) enum ChoicesAttribute {
     HideGet(Ident),
     HidePut(Ident),
     RwLock(Ident),
+    Nested(Ident),
+    FromStr(Ident),
+    SerdeJson(Ident),
+    AuthGet(Ident),
     // ident = "string literal"
     RootPath(Ident, LitStr),
     RootMessage(Ident, LitStr),
+    Rename(Ident, LitStr),
+    Delimiter(Ident, LitStr),
+    ReadRole(Ident, LitStr),
+    WriteRole(Ident, LitStr),
+    Persist(Ident, LitStr),
+    EnvPrefix(Ident, LitStr),
     // ident = arbitrary_expr
     OnSet(Ident, Expr),
     Validator(Ident, Expr),
+    ItemValidator(Ident, Expr),
+    Auth(Ident, Expr),
+    Identity(Ident, Expr),
+    Signature(Ident, Expr),
+    Cors(Ident, Expr),
 }
 
 impl Parse for ChoicesAttribute {
@@ -54,6 +69,30 @@ impl Parse for ChoicesAttribute {
                         Ok(RootPath(name, lit))
                     }
                     "message" => Ok(RootMessage(name, lit)),
+                    "rename" => {
+                        check_empty_lit("rename");
+                        Ok(Rename(name, lit))
+                    }
+                    "delimiter" => {
+                        check_empty_lit("delimiter");
+                        Ok(Delimiter(name, lit))
+                    }
+                    "read_role" => {
+                        check_empty_lit("read_role");
+                        Ok(ReadRole(name, lit))
+                    }
+                    "write_role" => {
+                        check_empty_lit("write_role");
+                        Ok(WriteRole(name, lit))
+                    }
+                    "persist" => {
+                        check_empty_lit("persist");
+                        Ok(Persist(name, lit))
+                    }
+                    "env_prefix" => {
+                        check_empty_lit("env_prefix");
+                        Ok(EnvPrefix(name, lit))
+                    }
                     _ => abort!(name, "unexpected attribute: {}", name_str),
                 }
             } else {
@@ -61,6 +100,11 @@ impl Parse for ChoicesAttribute {
                     Ok(expr) => match name_str.as_ref() {
                         "on_set" => Ok(OnSet(name, expr)),
                         "validator" => Ok(Validator(name, expr)),
+                        "item_validator" => Ok(ItemValidator(name, expr)),
+                        "auth" => Ok(Auth(name, expr)),
+                        "identity" => Ok(Identity(name, expr)),
+                        "signature" => Ok(Signature(name, expr)),
+                        "cors" => Ok(Cors(name, expr)),
                         _ => abort!(name, "unexpected attribute: {}", name_str),
                     },
                     Err(_) => abort! {
@@ -80,13 +124,17 @@ impl Parse for ChoicesAttribute {
                 "hide_get" => Ok(HideGet(name)),
                 "hide_put" => Ok(HidePut(name)),
                 "rw_lock" => Ok(RwLock(name)),
+                "nested" => Ok(Nested(name)),
+                "from_str" => Ok(FromStr(name)),
+                "serde_json" => Ok(SerdeJson(name)),
+                "auth_get" => Ok(AuthGet(name)),
                 _ => abort!(name, "unexpected attribute: {}", name_str),
             }
         }
     }
 }
 
-fn parse_choices_attributes(attrs: &[Attribute]) -> Vec<ChoicesAttribute> {
+pub(crate) fn parse_choices_attributes(attrs: &[Attribute]) -> Vec<ChoicesAttribute> {
     attrs
         .iter()
         .filter(|attr| attr.path.is_ident("choices"))
@@ -107,6 +155,17 @@ pub(crate) struct Attributes {
     pub(crate) hide_put: bool,
     pub(crate) rw_lock: bool,
     pub(crate) validator: Option<Expr>,
+    pub(crate) item_validator: Option<Expr>,
+    pub(crate) nested: bool,
+    pub(crate) auth: Option<Expr>,
+    pub(crate) auth_get: Option<Ident>,
+    pub(crate) identity: Option<Expr>,
+    pub(crate) read_role: Option<LitStr>,
+    pub(crate) write_role: Option<LitStr>,
+    pub(crate) signature: Option<Expr>,
+    pub(crate) cors: Option<Expr>,
+    pub(crate) persist: Option<LitStr>,
+    pub(crate) env_prefix: Option<LitStr>,
 }
 
 impl Attributes {
@@ -121,6 +180,17 @@ impl Attributes {
             hide_put: false,
             rw_lock: false,
             validator: None,
+            item_validator: None,
+            nested: false,
+            auth: None,
+            auth_get: None,
+            identity: None,
+            read_role: None,
+            write_role: None,
+            signature: None,
+            cors: None,
+            persist: None,
+            env_prefix: None,
         }
     }
 
@@ -198,11 +268,111 @@ impl Attributes {
                     }
                     self.validator = Some(expr);
                 }
+                ItemValidator(ident, expr) => {
+                    if from_struct {
+                        abort!(
+                            ident,
+                            "#[choices(item_validator)] can be used only on field level"
+                        );
+                    }
+                    self.item_validator = Some(expr);
+                }
+                Rename(ident, _) => {
+                    abort!(
+                        ident,
+                        "#[choices(rename)] can be used only on enum variants"
+                    );
+                }
+                FromStr(ident) | SerdeJson(ident) | Delimiter(ident, _) => {
+                    abort!(
+                        ident,
+                        "#[choices({})] can be used only with ChoicesValue",
+                        ident
+                    );
+                }
+                Nested(ident) => {
+                    if from_struct {
+                        abort!(ident, "#[choices(nested)] can be used only on field level");
+                    }
+                    self.nested = true;
+                }
+                Auth(ident, expr) => {
+                    if !from_struct {
+                        abort!(ident, "#[choices(auth)] can be used only on struct level");
+                    }
+                    self.auth = Some(expr);
+                }
+                AuthGet(ident) => {
+                    if !from_struct {
+                        abort!(
+                            ident,
+                            "#[choices(auth_get)] can be used only on struct level"
+                        );
+                    }
+                    self.auth_get = Some(ident);
+                }
+                Identity(ident, expr) => {
+                    if !from_struct {
+                        abort!(
+                            ident,
+                            "#[choices(identity)] can be used only on struct level"
+                        );
+                    }
+                    self.identity = Some(expr);
+                }
+                ReadRole(ident, role) => {
+                    if from_struct {
+                        abort!(
+                            ident,
+                            "#[choices(read_role)] can be used only on field level"
+                        );
+                    }
+                    self.read_role = Some(role);
+                }
+                WriteRole(ident, role) => {
+                    if from_struct {
+                        abort!(
+                            ident,
+                            "#[choices(write_role)] can be used only on field level"
+                        );
+                    }
+                    self.write_role = Some(role);
+                }
+                Signature(ident, expr) => {
+                    if !from_struct {
+                        abort!(
+                            ident,
+                            "#[choices(signature)] can be used only on struct level"
+                        );
+                    }
+                    self.signature = Some(expr);
+                }
+                Cors(ident, expr) => {
+                    if !from_struct {
+                        abort!(ident, "#[choices(cors)] can be used only on struct level");
+                    }
+                    self.cors = Some(expr);
+                }
+                Persist(ident, path) => {
+                    if !from_struct {
+                        abort!(
+                            ident,
+                            "#[choices(persist)] can be used only on struct level"
+                        );
+                    }
+                    self.persist = Some(path);
+                }
+                EnvPrefix(ident, prefix) => {
+                    if !from_struct {
+                        abort!(
+                            ident,
+                            "#[choices(env_prefix)] can be used only on struct level"
+                        );
+                    }
+                    self.env_prefix = Some(prefix);
+                }
             }
         }
-        if self.json && self.root_message.is_some() {
-            abort_call_site!("#[choices(message)] and #[choices(json)] can't be used together!");
-        }
     }
 
     pub(crate) fn from_struct(attrs: &[Attribute]) -> Self {
@@ -0,0 +1,220 @@
+//! Generation of the filters authenticating requests and checking the roles of their callers.
+
+use crate::attributes::Attributes;
+use crate::constants::{ALL_PATH, EVENTS_PATH};
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::quote;
+use syn::{punctuated::Punctuated, token::Comma, *};
+
+/// Filters guarding the resources of a configuration, which reply to the requests they don't
+/// allow and reject the other ones.
+pub(crate) struct Gates {
+    /// Statement binding `identify`, the identity extractor of the configuration, if any.
+    pub(crate) identify: Option<TokenStream>,
+    /// Filters checking the roles of the callers.
+    pub(crate) roles: Vec<TokenStream>,
+    /// Filter authenticating the requests, if any.
+    pub(crate) auth: Option<TokenStream>,
+    /// Whether requests reading the configuration are authenticated.
+    pub(crate) auth_get: bool,
+}
+
+/// Generates the filters guarding the resources of the configuration with the fields `fields`
+/// and the struct attributes `attrs`.
+pub(crate) fn gen_gates(fields: &Punctuated<Field, Comma>, attrs: &Attributes) -> Gates {
+    Gates {
+        identify: gen_identify(attrs),
+        roles: gen_roles_gates(fields, attrs),
+        auth: gen_auth_gate(attrs),
+        auth_get: attrs.auth_get.is_some(),
+    }
+}
+
+/// Generates the filter replying to the requests which fail the authentication of
+/// `#[choices(auth)]`, if any, matching every path below the configuration root filter `$root`.
+fn gen_auth_gate(attrs: &Attributes) -> Option<TokenStream> {
+    let auth = match (&attrs.auth, &attrs.auth_get) {
+        (Some(auth), _) => auth,
+        (None, Some(auth_get)) => {
+            abort!(auth_get, "#[choices(auth_get)] requires #[choices(auth)]")
+        }
+        (None, None) => return None,
+    };
+    let reads = attrs.auth_get.is_some();
+    let json = attrs.json;
+    Some(quote! {
+        $root.clone().and(choices::auth::gate(std::sync::Arc::new(#auth), #reads, #json))
+    })
+}
+
+/// Generates the statement binding `identify` to the identity extractor of
+/// `#[choices(identity)]`, if any.
+fn gen_identify(attrs: &Attributes) -> Option<TokenStream> {
+    attrs.identity.as_ref().map(|identity| {
+        quote! {
+            let identify: std::sync::Arc<dyn choices::auth::Identify> =
+                std::sync::Arc::new(#identity);
+        }
+    })
+}
+
+/// Generates the filters replying `403 Forbidden` to the callers lacking the roles of the
+/// fields: one for each field with roles, matching the field and its elements, and one for each
+/// bulk resource, which requires all the roles of the fields it accesses, including the ones of
+/// the nested configurations.
+///
+/// The filters of the fields use the identity extractor `identify`, which must be set by
+/// `#[choices(identity)]` if any field has roles, and the ones of the bulk resources the one of
+/// `__identify()`.
+fn gen_roles_gates(fields: &Punctuated<Field, Comma>, attrs: &Attributes) -> Vec<TokenStream> {
+    let json = attrs.json;
+    let mut gates = vec![];
+    let mut nested = false;
+    for field in fields {
+        let field_attr = Attributes::from_field(field);
+        if field_attr.skip {
+            continue;
+        }
+        nested |= field_attr.nested;
+        if field_attr.read_role.is_none() && field_attr.write_role.is_none() {
+            continue;
+        }
+        if attrs.identity.is_none() {
+            let role = field_attr
+                .read_role
+                .as_ref()
+                .or(field_attr.write_role.as_ref())
+                .unwrap();
+            abort!(
+                role,
+                "roles of fields require #[choices(identity)] on the struct"
+            );
+        }
+        let name = field.ident.as_ref().unwrap().to_string();
+        let read_roles: Vec<_> = field_attr.read_role.iter().collect();
+        let write_roles: Vec<_> = field_attr.write_role.iter().collect();
+        gates.push(quote! {
+            $root.clone()
+                .and(choices::warp::path(#name))
+                .and(choices::auth::roles_gate(
+                    Some(identify.clone()),
+                    vec![#( #read_roles ),*],
+                    vec![#( #write_roles ),*],
+                    Some(#name),
+                    #json,
+                ))
+        });
+    }
+    // The roles of the nested configurations are known only once they're compiled.
+    if gates.is_empty() && !nested {
+        return gates;
+    }
+
+    let read_gate = |path: &str| {
+        quote! {
+            $root.clone()
+                .and(choices::warp::path(#path))
+                .and(choices::auth::roles_gate(
+                    Self::__identify(),
+                    Self::__read_roles(),
+                    vec![],
+                    None,
+                    #json,
+                ))
+        }
+    };
+    gates.push(read_gate(ALL_PATH));
+    gates.push(read_gate(EVENTS_PATH));
+    // The WebSocket both reads and modifies the fields.
+    #[cfg(feature = "json")]
+    {
+        use crate::constants::WS_PATH;
+        gates.push(quote! {
+            $root.clone()
+                .and(choices::warp::path(#WS_PATH))
+                .and(choices::auth::roles_gate(
+                    Self::__identify(),
+                    Self::__read_roles(),
+                    [Self::__read_roles(), Self::__write_roles()].concat(),
+                    None,
+                    #json,
+                ))
+        });
+    }
+    // PATCH requests share the root path with the index, which is filtered instead.
+    gates.push(quote! {
+        $root.clone()
+            .and(choices::warp::path::end())
+            .and(choices::auth::roles_gate(
+                Self::__identify(),
+                vec![],
+                Self::__write_roles(),
+                None,
+                #json,
+            ))
+    });
+    gates
+}
+
+/// Generates the hidden fns returning the roles required to read and to modify all the fields of
+/// the configuration, including the nested ones, and the identity extractor checking them: the
+/// one of `#[choices(identity)]`, or else the one of the first nested configuration having any.
+pub(crate) fn gen_roles(fields: &Punctuated<Field, Comma>, attrs: &Attributes) -> TokenStream {
+    let mut read_roles = vec![];
+    let mut write_roles = vec![];
+    let mut nested = vec![];
+    for field in fields {
+        let field_attr = Attributes::from_field(field);
+        if field_attr.skip {
+            continue;
+        }
+        if field_attr.nested {
+            nested.push(&field.ty);
+        }
+        read_roles.extend(field_attr.read_role);
+        write_roles.extend(field_attr.write_role);
+    }
+    let identify = match &attrs.identity {
+        Some(identity) => quote! { Some(std::sync::Arc::new(#identity)) },
+        None => quote! { None #( .or_else(<#nested>::__identify) )* },
+    };
+    quote! {
+        /// Returns the roles required to read all the fields of this configuration, including the
+        /// nested ones.
+        #[doc(hidden)]
+        pub fn __read_roles() -> Vec<&'static str> {
+            let mut roles = vec![#( #read_roles ),*];
+            #( roles.extend(<#nested>::__read_roles()); )*
+            roles
+        }
+
+        /// Returns the roles required to modify all the fields of this configuration, including
+        /// the nested ones.
+        #[doc(hidden)]
+        pub fn __write_roles() -> Vec<&'static str> {
+            let mut roles = vec![#( #write_roles ),*];
+            #( roles.extend(<#nested>::__write_roles()); )*
+            roles
+        }
+
+        /// Returns the identity extractor checking the roles of the whole configuration, if any.
+        #[doc(hidden)]
+        pub fn __identify() -> Option<std::sync::Arc<dyn choices::auth::Identify>> {
+            #identify
+        }
+    }
+}
+
+/// Returns `tokens`, executed only if the caller `identity` holds the read role of the field
+/// whose attributes are `field_attr`.
+pub(crate) fn if_readable(field_attr: &Attributes, tokens: TokenStream) -> TokenStream {
+    match &field_attr.read_role {
+        Some(role) => quote! {
+            if identity.has_role(#role) {
+                #tokens
+            }
+        },
+        None => tokens,
+    }
+}
@@ -0,0 +1,371 @@
+//! Generation of the bulk resources, which access all configuration fields at once.
+//!
+//! Updates of multiple fields are performed in two steps: each field's new value is parsed and
+//! validated into a `choices::Patch`, then all patches are applied if no error occurred.
+
+use crate::attributes::Attributes;
+use crate::constants::ALL_PATH;
+use crate::negotiate::{
+    accept_filter, content_type_filter, empty_reply, error_reply, internal_error_reply, json_input,
+    json_output, negotiate, text_input, text_output, value_reply, with_json,
+};
+use crate::revision::{check_if_match, config_revision, if_match_filter, reply_with_etag};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{punctuated::Punctuated, token::Comma, *};
+
+/// Fields with their names and attributes.
+pub(crate) type NamedFields<'a> = Vec<(&'a Field, String, Attributes)>;
+
+/// Returns the fields which aren't skipped and whose attributes satisfy `keep`, with their names
+/// and attributes.
+fn fields_where(
+    fields: &Punctuated<Field, Comma>,
+    keep: impl Fn(&Attributes) -> bool,
+) -> NamedFields<'_> {
+    fields
+        .iter()
+        .filter_map(|field| {
+            let field_attr = Attributes::from_field(field);
+            if field_attr.skip || !keep(&field_attr) {
+                None
+            } else {
+                let field_ident = field
+                    .ident
+                    .as_ref()
+                    .expect("unnamed fields are not supported!");
+                Some((field, field_ident.to_string(), field_attr))
+            }
+        })
+        .collect()
+}
+
+/// Returns the fields exposed by the bulk resources, with their names and attributes.
+pub(crate) fn readable_fields(fields: &Punctuated<Field, Comma>) -> NamedFields<'_> {
+    fields_where(fields, |field_attr| !field_attr.hide_get)
+}
+
+/// Returns the fields which can be modified by the bulk resources, with their names and
+/// attributes.
+fn writable_fields(fields: &Punctuated<Field, Comma>) -> NamedFields<'_> {
+    fields_where(fields, |field_attr| !field_attr.hide_put)
+}
+
+/// Returns the fields saved to and loaded from files, i.e. all fields which aren't skipped, with
+/// their names and attributes.
+pub(crate) fn stored_fields(fields: &Punctuated<Field, Comma>) -> NamedFields<'_> {
+    fields_where(fields, |_| true)
+}
+
+/// Generates the hidden methods collecting the values of all readable fields, `__values_*`, and
+/// of all stored fields, `__stored_*`.
+///
+/// As text values are written as `name=value` lines, prefixed by the path of nested
+/// configurations (e.g. `db.port=5432`), while as json they're collected in an object. The
+/// stored text values have their line breaks escaped, so that each of them fits on its line of
+/// the file. The json methods are generated only with the feature `json`. `json` tells the
+/// format of the configuration.
+pub(crate) fn gen_values(fields: &Punctuated<Field, Comma>, json: bool) -> TokenStream {
+    let values_text = gen_values_text(readable_fields(fields), "__values_text", false, json);
+    let stored_text = gen_values_text(stored_fields(fields), "__stored_text", true, json);
+    let json = with_json(|| {
+        let values_json = gen_values_json(readable_fields(fields), "__values_json", json);
+        let stored_json = gen_values_json(stored_fields(fields), "__stored_json", json);
+        quote! {
+            #values_json
+            #stored_json
+        }
+    });
+    quote! {
+        #values_text
+        #stored_text
+        #json
+    }
+}
+
+fn gen_values_text(fields: NamedFields, method: &str, escape: bool, json: bool) -> TokenStream {
+    let method = format_ident!("{}", method);
+    let values = fields.into_iter().map(|(field, name, field_attr)| {
+        let field_ident = &field.ident;
+        if field_attr.nested {
+            quote! {
+                self.#field_ident.#method(&format!("{}{}.", prefix, #name), values);
+            }
+        } else {
+            let ty = &field.ty;
+            let mut value = text_output(&quote! { #ty }, &quote! { &self.#field_ident }, json);
+            if escape {
+                value = quote! { choices::persist::escape_value(&#value) };
+            }
+            quote! {
+                values.push_str(&format!("{}{}={}\n", prefix, #name, #value));
+            }
+        }
+    });
+    quote! {
+        /// Writes the values of all fields in `values`, as `name=value` lines whose names are
+        /// preceded by `prefix`.
+        #[doc(hidden)]
+        pub fn #method(&self, prefix: &str, values: &mut String) {
+            #( #values )*
+        }
+    }
+}
+
+fn gen_values_json(fields: NamedFields, method: &str, json: bool) -> TokenStream {
+    let method = format_ident!("{}", method);
+    let values = fields.into_iter().map(|(field, name, field_attr)| {
+        let field_ident = &field.ident;
+        let value = if field_attr.nested {
+            quote! { self.#field_ident.#method()? }
+        } else {
+            let ty = &field.ty;
+            let value = json_output(&quote! { #ty }, &quote! { &self.#field_ident }, json);
+            quote! { #value? }
+        };
+        quote! {
+            values.insert(#name.to_string(), #value);
+        }
+    });
+    quote! {
+        /// Returns the values of all fields as a json object.
+        #[doc(hidden)]
+        pub fn #method(
+            &self,
+        ) -> choices::serde_json::Result<choices::serde_json::Value> {
+            #[allow(unused_mut)]
+            let mut values = choices::serde_json::Map::new();
+            #( #values )*
+            Ok(choices::serde_json::Value::Object(values))
+        }
+    }
+}
+
+/// Generates the GET resource returning the values of all fields, read with a single access to
+/// the configuration, with the `ETag` of the configuration's revision.
+///
+/// `access_pattern` represents the way the configuration can be accessed.
+pub(crate) fn gen_all_resource(json: bool, access_pattern: TokenStream) -> TokenStream {
+    let reply = value_reply(
+        quote! {{
+            let mut values = String::new();
+            config.__values_text("", &mut values);
+            values
+        }},
+        || quote! { config.__values_json().map(|values| values.to_string()) },
+    );
+    let error_reply = internal_error_reply(quote! { err });
+    let reply = reply_with_etag(
+        &access_pattern,
+        &config_revision(),
+        quote! {
+            match access {
+                Ok(config) => #reply,
+                Err(err) => #error_reply,
+            }
+        },
+    );
+    let accept_filter = accept_filter(json);
+    quote! {
+        $root.clone()
+            .and(choices::warp::path(#ALL_PATH))
+            .and(choices::warp::path::end())
+            .and(choices::warp::get())
+            .and(#accept_filter)
+            .map(move |format: choices::negotiate::Format| {
+                use choices::warp::http::StatusCode;
+                #reply
+            })
+    }
+}
+
+/// Generates the hidden methods preparing the patch of a field from its name and new value,
+/// `__prepare_*` for the writable fields and `__prepare_stored_*` for the stored fields.
+///
+/// As text fields of nested configurations are named `nested.name`, while as json their new
+/// values are objects. The json methods are generated only with the feature `json`. `json`
+/// tells the format of the configuration.
+pub(crate) fn gen_prepare(fields: &Punctuated<Field, Comma>, json: bool) -> TokenStream {
+    let prepare_text = gen_prepare_text(writable_fields(fields), "__prepare_text", json);
+    let stored_text = gen_prepare_text(stored_fields(fields), "__prepare_stored_text", json);
+    let json = with_json(|| {
+        let prepare_json = gen_prepare_json(writable_fields(fields), "__prepare_json", json);
+        let stored_json = gen_prepare_json(stored_fields(fields), "__prepare_stored_json", json);
+        quote! {
+            #prepare_json
+            #stored_json
+        }
+    });
+    quote! {
+        #prepare_text
+        #stored_text
+        #json
+    }
+}
+
+fn gen_prepare_text(fields: NamedFields, method: &str, json: bool) -> TokenStream {
+    let method = format_ident!("{}", method);
+    let (nested, scalar): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .partition(|(_, _, field_attr)| field_attr.nested);
+    let scalar_arms = scalar.into_iter().map(|(field, name, _)| {
+        let ty = &field.ty;
+        let field_ident = field.ident.as_ref().unwrap();
+        let check_ident = format_ident!("__check_{}", field_ident);
+        let apply_ident = format_ident!("__apply_{}", field_ident);
+        let parse = text_input(&quote! { #ty }, &quote! { value }, json);
+        quote! {
+            #name => {
+                let value: #ty = #parse?;
+                self.#check_ident(&value)?;
+                Ok(Box::new(move |config: &mut Self| config.#apply_ident(value)))
+            }
+        }
+    });
+    let nested_arms = nested.into_iter().map(|(field, name, _)| {
+        let field_ident = &field.ident;
+        quote! {
+            Some((#name, name)) => {
+                let patch = self.#field_ident.#method(name, value)?;
+                Ok(Box::new(move |config: &mut Self| patch(&mut config.#field_ident)))
+            }
+        }
+    });
+    quote! {
+        /// Parses and validates the new value of the field `name`.
+        #[doc(hidden)]
+        pub fn #method(
+            &self,
+            name: &str,
+            value: &choices::bytes::Bytes,
+        ) -> choices::ChoicesResult<choices::Patch<Self>> {
+            match name {
+                #( #scalar_arms )*
+                _ => match name.split_once('.') {
+                    #( #nested_arms )*
+                    _ => Err(choices::patch::unknown_field(name)),
+                },
+            }
+        }
+    }
+}
+
+fn gen_prepare_json(fields: NamedFields, method: &str, json: bool) -> TokenStream {
+    let method = format_ident!("{}", method);
+    let arms = fields.into_iter().map(|(field, name, field_attr)| {
+        let ty = &field.ty;
+        let field_ident = field.ident.as_ref().unwrap();
+        if field_attr.nested {
+            quote! {
+                #name => {
+                    let patches = choices::patch::prepare_json(value, |name, value| {
+                        self.#field_ident.#method(name, value)
+                    })?;
+                    Ok(Box::new(move |config: &mut Self| {
+                        for patch in patches {
+                            patch(&mut config.#field_ident);
+                        }
+                    }))
+                }
+            }
+        } else {
+            let check_ident = format_ident!("__check_{}", field_ident);
+            let apply_ident = format_ident!("__apply_{}", field_ident);
+            let parse = json_input(&quote! { #ty }, &quote! { value }, json);
+            quote! {
+                #name => {
+                    let value: #ty = #parse?;
+                    self.#check_ident(&value)?;
+                    Ok(Box::new(move |config: &mut Self| config.#apply_ident(value)))
+                }
+            }
+        }
+    });
+    quote! {
+        /// Parses and validates the new value of the field `name`.
+        #[doc(hidden)]
+        pub fn #method(
+            &self,
+            name: &str,
+            value: choices::serde_json::Value,
+        ) -> choices::ChoicesResult<choices::Patch<Self>> {
+            match name {
+                #( #arms )*
+                _ => Err(choices::patch::unknown_field(name)),
+            }
+        }
+    }
+}
+
+/// Generates the PATCH resource updating multiple fields at once.
+///
+/// The patches of all fields are prepared and then applied with a single write access to the
+/// configuration, so that either all fields are updated or none. The `If-Match` header is
+/// checked against the revision of the whole configuration.
+pub(crate) fn gen_patch_resource(json: bool, access_pattern: TokenStream) -> TokenStream {
+    let prepare = negotiate(
+        quote! { body_format },
+        quote! {
+            choices::patch::prepare_text(&bytes, |name, value| config.__prepare_text(name, value))
+        },
+        || {
+            quote! {
+                choices::serde_json::from_slice(&bytes)
+                    .map_err(|err| choices::ChoicesError::ParseError(err.to_string()))
+                    .and_then(|value| {
+                        choices::patch::prepare_json(value, |name, value| {
+                            config.__prepare_json(name, value)
+                        })
+                    })
+            }
+        },
+    );
+    let if_match_filter = if_match_filter();
+    let content_type_filter = content_type_filter(json);
+    let empty_reply = empty_reply();
+    let error_reply = error_reply(
+        quote! { err },
+        None,
+        true,
+        quote! { StatusCode::BAD_REQUEST },
+    );
+    let internal_error_reply = internal_error_reply(quote! { err });
+    let apply = check_if_match(
+        &config_revision(),
+        None,
+        quote! {{
+            let patches: choices::ChoicesResult<Vec<choices::Patch<Self>>> = #prepare;
+            match patches {
+                Ok(patches) => {
+                    let config: &mut Self = &mut config;
+                    for patch in patches {
+                        patch(config);
+                    }
+                    #empty_reply
+                }
+                Err(err) => #error_reply,
+            }
+        }},
+    );
+    quote! {
+        $root.clone()
+            .and(choices::warp::path::end())
+            .and(choices::warp::patch())
+            .and(choices::warp::body::content_length_limit(1024 * 16))
+            .and(#if_match_filter)
+            .and(#content_type_filter)
+            .and(choices::signature::body(signature.clone(), #json))
+            .map(move |
+                if_match: Option<String>,
+                body_format: choices::negotiate::Format,
+                format: choices::negotiate::Format,
+                bytes: choices::bytes::Bytes,
+            | {
+                use choices::warp::http::StatusCode;
+                match #access_pattern {
+                    Ok(mut config) => #apply,
+                    Err(err) => #internal_error_reply,
+                }
+            })
+    }
+}
@@ -1,6 +1,14 @@
-//! Collection of various constants.
-
-pub(crate) const CONTENT_TYPE_HEADER: &str = "Content-Type";
-pub(crate) const CONTENT_TYPE_TEXT: &str = "text/plain; charset=utf-8";
-#[cfg(feature = "json")]
-pub(crate) const CONTENT_TYPE_JSON: &str = "application/json";
+//! Collection of various constants.
+
+/// Path segment of the resource returning the values of all fields.
+pub(crate) const ALL_PATH: &str = "_all";
+/// Path segment of the resource streaming the changes of the fields.
+pub(crate) const EVENTS_PATH: &str = "_events";
+/// Path segment of the WebSocket resource.
+#[cfg(feature = "json")]
+pub(crate) const WS_PATH: &str = "_ws";
+
+/// Header carrying the revision of the configuration or field returned.
+pub(crate) const ETAG_HEADER: &str = "ETag";
+/// Header carrying the revisions a modification is conditioned to.
+pub(crate) const IF_MATCH_HEADER: &str = "if-match";
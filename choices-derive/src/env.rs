@@ -0,0 +1,89 @@
+//! Generation of the methods loading configurations from environment variables.
+
+use crate::bulk::stored_fields;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{punctuated::Punctuated, token::Comma, *};
+
+/// Generates the methods loading the configuration from environment variables, where `json`
+/// tells the format of the configuration and `prefix` is the prefix of
+/// `#[choices(env_prefix)]`, if any, or else the name of the configuration type.
+pub(crate) fn gen_env(
+    fields: &Punctuated<Field, Comma>,
+    json: bool,
+    prefix: Option<&LitStr>,
+) -> TokenStream {
+    let prepare = if json {
+        quote! {
+            choices::env::prepare_json(name, value, |name, value| {
+                self.__prepare_stored_json(name, value)
+            })
+        }
+    } else {
+        quote! {
+            self.__prepare_stored_text(name, &choices::bytes::Bytes::from(value))
+        }
+    };
+    let prefix = prefix.map_or(
+        quote! { choices::env::default_prefix(std::any::type_name::<Self>()) },
+        |prefix| quote! { #prefix.to_string() },
+    );
+    let names = stored_fields(fields)
+        .into_iter()
+        .map(|(field, name, field_attr)| {
+            let field_ident = &field.ident;
+            if field_attr.nested {
+                quote! {
+                    self.#field_ident.__stored_names(&format!("{}{}.", prefix, #name), names);
+                }
+            } else {
+                quote! {
+                    names.push(format!("{}{}", prefix, #name));
+                }
+            }
+        });
+    quote! {
+        /// Pushes in `names` the names of all fields saved to and loaded from files, preceded
+        /// by `prefix`.
+        #[doc(hidden)]
+        pub fn __stored_names(&self, prefix: &str, names: &mut Vec<String>) {
+            #( #names )*
+        }
+
+        /// Loads the values of the fields which aren't skipped from the environment variables
+        /// `PREFIX_FIELD`, e.g. `APP_PORT` for the prefix `APP`, running the validators of the
+        /// fields. The prefix can't be empty. In json mode values are parsed as json, or else
+        /// taken as strings. Either all values are loaded or none, and the errors of all
+        /// variables are returned at once. Fields whose variables aren't set are left unchanged.
+        pub fn load_env(&mut self, prefix: &str) -> choices::ChoicesResult<()> {
+            let mut names = Vec::new();
+            self.__stored_names("", &mut names);
+            let patches = choices::env::prepare_env(prefix, &names, |name, value| {
+                #prepare
+            })?;
+            for patch in patches {
+                patch(self);
+            }
+            Ok(())
+        }
+
+        /// Returns the prefix of the environment variables loaded by `from_env()`: the prefix
+        /// of `#[choices(env_prefix)]`, or else the name of the type in uppercase, with its
+        /// words separated by underscores, e.g. `APP_CONFIG` for `AppConfig`.
+        pub fn env_prefix() -> String {
+            #prefix
+        }
+
+        /// Creates the default configuration and loads the values of its fields from the
+        /// environment variables with the prefix `env_prefix()`, as `load_env()`.
+        // The bound is higher-ranked so that it's checked only where the function is called.
+        pub fn from_env() -> choices::ChoicesResult<Self>
+        where
+            for<'a> Self: Default,
+        {
+            let mut config = Self::default();
+            config.load_env(&Self::env_prefix())?;
+            Ok(config)
+        }
+    }
+}
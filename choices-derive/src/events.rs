@@ -0,0 +1,221 @@
+//! Generation of the notifications sent when configuration fields are set, of the resource
+//! streaming them as Server-Sent Events and of the in-process subscriptions.
+
+use crate::attributes::Attributes;
+use crate::constants::EVENTS_PATH;
+use crate::negotiate::{accept_filter, internal_error_reply};
+use crate::revision::field_revision;
+use proc_macro2::{Ident, TokenStream};
+#[cfg(not(feature = "json"))]
+use proc_macro_error::abort_call_site;
+use quote::{format_ident, quote};
+use syn::{punctuated::Punctuated, token::Comma, *};
+
+/// Generates the hidden fns returning the events channel of the configuration and subscribing
+/// to it and to the ones of the nested configurations, and the one returning the watch channels
+/// of the fields.
+pub(crate) fn gen_events(fields: &Punctuated<Field, Comma>) -> TokenStream {
+    let fields_count = fields.len();
+    let nested = fields.iter().filter_map(|field| {
+        let field_attr = Attributes::from_field(field);
+        if field_attr.nested && !field_attr.skip && !field_attr.hide_get {
+            let field_ident = &field.ident;
+            let name = field.ident.as_ref().unwrap().to_string();
+            Some(quote! {
+                self.#field_ident.__event_receivers(&format!("{}{}.", prefix, #name), receivers);
+            })
+        } else {
+            None
+        }
+    });
+    quote! {
+        /// Returns the watch channels of the fields of this configuration object.
+        #[doc(hidden)]
+        pub fn __watches(&self) -> std::sync::Arc<choices::watch::Watches> {
+            self.__state().watches(#fields_count)
+        }
+
+        /// Returns the channel of the events of this configuration object.
+        #[doc(hidden)]
+        pub fn __events(&self) -> std::sync::Arc<choices::events::Events> {
+            self.__state().events()
+        }
+
+        /// Subscribes to the events of this configuration object and of the nested ones, whose
+        /// fields are preceded by `prefix`.
+        #[doc(hidden)]
+        pub fn __event_receivers(
+            &self,
+            prefix: &str,
+            receivers: &mut Vec<(String, choices::tokio::sync::broadcast::Receiver<choices::events::Event>)>,
+        ) {
+            receivers.push((prefix.to_string(), self.__events().subscribe()));
+            #( #nested )*
+        }
+
+        /// Returns a subscription to the changes of the fields of this configuration, including
+        /// the nested ones, whether they're set through HTTP or through the setters.
+        pub fn subscribe(&self) -> choices::events::Subscription {
+            let mut receivers = Vec::new();
+            self.__event_receivers("", &mut receivers);
+            choices::events::Subscription::new(receivers)
+        }
+    }
+}
+
+/// Generates the method subscribing to the values of the field `field_ident`, of type `ty`, at
+/// `index`.
+///
+/// The field's type must be `Clone` only if the method is used. Nested configurations can't be
+/// watched as a whole, since the changes of their fields aren't seen by the parent.
+pub(crate) fn gen_field_subscription(
+    field_ident: &Ident,
+    ty: &Type,
+    field_attr: &Attributes,
+    index: usize,
+) -> TokenStream {
+    if field_attr.nested {
+        return quote! {};
+    }
+    let subscribe_ident = format_ident!("subscribe_{}", field_ident);
+    quote! {
+        /// Returns a receiver of the new values of the field, whether it's set through HTTP or
+        /// through the setters.
+        pub fn #subscribe_ident(&self) -> choices::tokio::sync::watch::Receiver<#ty>
+        where
+            for<'a> #ty: Clone + Send + Sync + 'static,
+        {
+            self.__watches()
+                .field(#index)
+                .subscribe(self, |config: &Self| &config.#field_ident)
+        }
+    }
+}
+
+/// Generates the statement sending the new value of the field `field_ident`, at `index`, to its
+/// watch channel.
+pub(crate) fn notify_watch(field_attr: &Attributes, index: usize) -> TokenStream {
+    if field_attr.nested {
+        return quote! {};
+    }
+    quote! { self.__watches().field(#index).notify(self); }
+}
+
+/// Generates the statements evaluating `revision`, the new revision of the field `field_ident`
+/// whose new value has already been assigned, and sending its event.
+///
+/// Fields with `hide_get` don't send events, so that their values aren't disclosed.
+pub(crate) fn send_event(
+    field_ident: &Ident,
+    field_attr: &Attributes,
+    json: bool,
+    revision: TokenStream,
+) -> TokenStream {
+    if field_attr.hide_get {
+        return quote! { #revision; };
+    }
+    let name = field_ident.to_string();
+    let value = if json {
+        event_value_json(field_ident, field_attr.nested)
+    } else if field_attr.nested {
+        quote! {{
+            let mut values = String::new();
+            self.#field_ident.__values_text("", &mut values);
+            values.trim_end().to_string()
+        }}
+    } else {
+        quote! { choices::ChoicesOutput::body_string(&self.#field_ident) }
+    };
+    quote! {
+        let revision: u64 = #revision;
+        let events = self.__events();
+        if events.is_observed() {
+            events.send(choices::events::Event {
+                field: #name.to_string(),
+                value: #value,
+                revision,
+            });
+        }
+    }
+}
+
+/// Generates the expression formatting the new value of the field `_field_ident` as json.
+fn event_value_json(_field_ident: &Ident, _nested: bool) -> TokenStream {
+    #[cfg(not(feature = "json"))]
+    abort_call_site!("you must enable the choices feature `json` in order to use it in a macro");
+
+    #[cfg(feature = "json")]
+    {
+        let value = if _nested {
+            quote! { self.#_field_ident.__values_json() }
+        } else {
+            quote! { choices::serde_json::to_value(&self.#_field_ident) }
+        };
+        quote! {
+            match #value {
+                Ok(value) => value.to_string(),
+                Err(err) => format!("\"{}\"", err),
+            }
+        }
+    }
+}
+
+/// Generates the GET resource streaming the events of the configuration as Server-Sent Events.
+///
+/// `json` tells the format of the errors, unless another one is accepted, and `access_pattern`
+/// represents the way the configuration can be accessed.
+pub(crate) fn gen_events_resource(json: bool, access_pattern: TokenStream) -> TokenStream {
+    let accept_filter = accept_filter(json);
+    let error_reply = internal_error_reply(quote! { err });
+    quote! {{
+        let choices = $choices.clone();
+        $root.clone()
+            .and(choices::warp::path(#EVENTS_PATH))
+            .and(choices::warp::path::end())
+            .and(choices::warp::get())
+            .and(#accept_filter)
+            .map(move |format: choices::negotiate::Format| {
+                use choices::warp::Reply;
+                match #access_pattern {
+                    Ok(config) => choices::events::sse_reply(config.subscribe()).into_response(),
+                    Err(err) => #error_reply.into_response(),
+                }
+            })
+    }}
+}
+
+/// Returns the TokenStream of the warp filter making a GET of the field `field_ident` wait until
+/// the field is set past the revision in the query parameter `wait`, where the configuration is
+/// accessed through `choices`.
+///
+/// Requests with invalid parameters are rejected, and must be recovered with
+/// `choices::wait::recover`, which replies the error in the format accepted by the request, where
+/// `json` tells the format of the configuration.
+pub(crate) fn wait_filter(field_ident: &Ident, json: bool) -> TokenStream {
+    let name = field_ident.to_string();
+    let revision = field_revision(field_ident);
+    let accept_filter = accept_filter(json);
+    quote! {{
+        let choices = choices.clone();
+        choices::warp::query::<std::collections::HashMap<String, String>>()
+            .and(#accept_filter)
+            .and_then(move |query, format: choices::negotiate::Format| {
+                let choices = choices.clone();
+                async move {
+                    let events = match choices::ChoicesAccess::read_access(&choices) {
+                        Ok(config) => config.__events(),
+                        // The request isn't delayed, since it fails accessing the configuration.
+                        Err(_) => return Ok(()),
+                    };
+                    choices::wait::wait_for_query(query, format, &events, #name, || {
+                        match choices::ChoicesAccess::read_access(&choices) {
+                            Ok(config) => #revision,
+                            Err(_) => 0,
+                        }
+                    })
+                    .await
+                }
+            })
+            .untuple_one()
+    }}
+}
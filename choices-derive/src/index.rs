@@ -1,92 +1,123 @@
+use crate::auth::if_readable;
 use crate::util::compute_type_string;
 use crate::{attributes::Attributes, DEFAULT_ROOT_MESSAGE};
 use derive_new::new;
 use proc_macro2::TokenStream;
-#[cfg(not(feature = "json"))]
-use proc_macro_error::abort_call_site;
+use quote::quote;
 use syn::{punctuated::Punctuated, token::Comma, *};
 
 #[derive(new)]
 pub(crate) struct IndexData {
-    pub(crate) body: String,
-    pub(crate) content_type: &'static str,
+    /// Expression evaluating to the body of the index as text, as a `String`.
+    pub(crate) text: TokenStream,
+    /// Expression evaluating to the body of the index as json, as a `String`, available only
+    /// with the feature `json`.
+    pub(crate) json: Option<TokenStream>,
+    /// Whether the configuration's default format is json.
+    pub(crate) default_json: bool,
 }
 
-/// Returns the body of the configuration index page.
+/// Returns the bodies of the configuration index page.
 pub(crate) fn compute_index(
     fields: &Punctuated<Field, Comma>,
     json: bool,
     root_message: &Option<TokenStream>,
 ) -> IndexData {
-    if json {
-        compute_index_json(fields)
-    } else {
-        compute_index_text(fields, root_message)
-    }
+    #[cfg(feature = "json")]
+    let json_body = Some(compute_index_json(fields));
+    #[cfg(not(feature = "json"))]
+    let json_body = None;
+    IndexData::new(compute_index_text(fields, root_message), json_body, json)
+}
+
+/// Returns the TokenStream evaluating to the variants allowed for the type `ty`, if any.
+fn variants_of(ty: &Type) -> TokenStream {
+    quote! {{
+        #[allow(unused_imports)]
+        use choices::{HasVariants, NoVariants};
+        (&choices::VariantsOf::<#ty>::new()).variants()
+    }}
 }
 
 fn compute_index_text(
     fields: &Punctuated<Field, Comma>,
     root_message: &Option<TokenStream>,
-) -> IndexData {
-    let mut index = if let Some(ts) = root_message {
+) -> TokenStream {
+    let mut header = if let Some(ts) = root_message {
         let ts_str = ts.to_string();
         ts_str[1..ts_str.len() - 1].to_string()
     } else {
         DEFAULT_ROOT_MESSAGE.to_string()
     };
-    if !index.is_empty() {
-        index += "\n";
+    if !header.is_empty() {
+        header += "\n";
     }
-    fields.iter().for_each(|field| {
+    let entries = fields.iter().filter_map(|field| {
         let field_attr = Attributes::from_field(field);
-        if !field_attr.skip {
+        if field_attr.skip {
+            None
+        } else {
             let field_ident = field
                 .ident
                 .as_ref()
                 .expect("unnamed fields are not supported!");
-            let type_name = compute_type_string(&field.ty);
-            index += &format!("  - {}: {}\n", &field_ident.to_string(), type_name);
+            let entry = format!(
+                "  - {}: {}",
+                &field_ident.to_string(),
+                compute_type_string(&field.ty)
+            );
+            let variants = variants_of(&field.ty);
+            Some(if_readable(
+                &field_attr,
+                quote! {
+                    index += #entry;
+                    if let Some(variants) = #variants {
+                        index += &format!(" [{}]", variants.join(", "));
+                    }
+                    index += "\n";
+                },
+            ))
         }
     });
-    IndexData::new(index, crate::constants::CONTENT_TYPE_TEXT)
+    quote! {{
+        #[allow(unused_mut)]
+        let mut index = String::from(#header);
+        #( #entries )*
+        index
+    }}
 }
 
-fn compute_index_json(_fields: &Punctuated<Field, Comma>) -> IndexData {
-    #[cfg(not(feature = "json"))]
-    abort_call_site!("you must enable the choices feature `json` in order to use it in a macro");
-
-    #[cfg(feature = "json")]
-    {
-        use serde::Serialize;
-
-        #[derive(Serialize, new)]
-        struct Entry {
-            name: String,
-            r#type: String,
+#[cfg(feature = "json")]
+fn compute_index_json(fields: &Punctuated<Field, Comma>) -> TokenStream {
+    let entries = fields.iter().filter_map(|field| {
+        let field_attr = Attributes::from_field(field);
+        if field_attr.skip {
+            None
+        } else {
+            let field_ident = field
+                .ident
+                .as_ref()
+                .expect("unnamed fields are not supported!");
+            let name = field_ident.to_string();
+            let type_name = compute_type_string(&field.ty);
+            let variants = variants_of(&field.ty);
+            Some(if_readable(
+                &field_attr,
+                quote! {{
+                    let mut entry =
+                        choices::serde_json::json!({"name": #name, "type": #type_name});
+                    if let Some(variants) = #variants {
+                        entry["variants"] = choices::serde_json::json!(variants);
+                    }
+                    entries.push(entry);
+                }},
+            ))
         }
-
-        let v: Vec<_> = _fields
-            .iter()
-            .filter_map(|field| {
-                let field_attr = Attributes::from_field(field);
-                if field_attr.skip {
-                    None
-                } else {
-                    let field_ident = field
-                        .ident
-                        .as_ref()
-                        .expect("unnamed fields are not supported!");
-                    Some(Entry::new(
-                        field_ident.to_string(),
-                        compute_type_string(&field.ty),
-                    ))
-                }
-            })
-            .collect();
-        IndexData::new(
-            serde_json::to_string(&v).expect("failed to serialize index json"),
-            crate::constants::CONTENT_TYPE_JSON,
-        )
-    }
+    });
+    quote! {{
+        #[allow(unused_mut)]
+        let mut entries = Vec::new();
+        #( #entries )*
+        choices::serde_json::Value::Array(entries).to_string()
+    }}
 }
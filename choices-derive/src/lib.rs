@@ -6,14 +6,24 @@
 extern crate proc_macro;
 
 mod attributes;
+mod auth;
+mod bulk;
 mod constants;
+mod env;
+mod events;
 mod index;
+mod negotiate;
+mod persist;
+mod revision;
 mod util;
+mod value;
 mod warp;
+#[cfg(feature = "json")]
+mod ws;
 
 use derive_new::new;
 use proc_macro2::TokenStream;
-use proc_macro_error::{abort_call_site, proc_macro_error, set_dummy};
+use proc_macro_error::{abort, abort_call_site, proc_macro_error, set_dummy};
 use quote::quote;
 use syn::{punctuated::Punctuated, token::Comma, *};
 
@@ -37,6 +47,15 @@ pub fn choices(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     gen.into()
 }
 
+/// Generates the `ChoicesInput` and `ChoicesOutput` impls for user defined types.
+#[proc_macro_derive(ChoicesValue, attributes(choices))]
+#[proc_macro_error]
+pub fn choices_value(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+    let gen = value::impl_choices_value(&input);
+    gen.into()
+}
+
 fn impl_choices(input: &DeriveInput) -> TokenStream {
     use syn::Data::*;
 
@@ -63,7 +82,8 @@ fn impl_choices_for_struct(
     fields: &Punctuated<Field, Comma>,
     attrs: &[Attribute],
 ) -> TokenStream {
-    let choices = warp::gen_choices(fields, attrs);
+    let (state, fields) = split_state(fields);
+    let choices = warp::gen_choices(&fields, state, attrs);
 
     let macro_block = choices.macro_block;
     let impl_block = choices.impl_block;
@@ -110,3 +130,29 @@ fn impl_choices_for_struct(
         }
     }
 }
+
+/// Splits `fields` into the ident of the field holding the state of the configuration, of type
+/// `choices::State`, and the fields of the configuration.
+fn split_state(fields: &Punctuated<Field, Comma>) -> (&Ident, Punctuated<Field, Comma>) {
+    let mut state = None;
+    let mut config_fields = Punctuated::new();
+    for field in fields {
+        if util::is_state(&field.ty) {
+            if state.is_some() {
+                abort!(
+                    field,
+                    "choices supports only one field of type `choices::State`"
+                );
+            }
+            state = field.ident.as_ref();
+        } else {
+            config_fields.push(field.clone());
+        }
+    }
+    match state {
+        Some(state) => (state, config_fields),
+        None => abort_call_site!(
+            "choices requires a field of type `choices::State`, e.g. `state: choices::State`"
+        ),
+    }
+}
@@ -0,0 +1,188 @@
+//! Generation of the code serving configurations both as text and as json, where the format of
+//! each request is negotiated at runtime by `choices::negotiate`.
+//!
+//! Values are converted directly in the format of their configuration, and through
+//! `choices::negotiate::ReprOf` in the other one, so that types which don't implement both
+//! representations can still be served in both formats.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Returns the TokenStream of the filter extracting the format of the reply, where `json` tells
+/// the format of the configuration.
+pub(crate) fn accept_filter(json: bool) -> TokenStream {
+    quote! { choices::negotiate::accept(#json) }
+}
+
+/// Returns the TokenStream of the filter extracting the format of the body, bound to
+/// `body_format`, and the one of the reply, bound to `format`, where `json` tells the format of
+/// the configuration.
+pub(crate) fn content_type_filter(json: bool) -> TokenStream {
+    quote! { choices::negotiate::content_type(#json) }
+}
+
+/// Returns the TokenStream generated by `gen` if the feature `json` is enabled, otherwise nothing.
+pub(crate) fn with_json(gen: impl FnOnce() -> TokenStream) -> TokenStream {
+    #[cfg(feature = "json")]
+    return gen();
+
+    #[cfg(not(feature = "json"))]
+    {
+        let _ = gen;
+        quote! {}
+    }
+}
+
+/// Generates the match on the format `format`, evaluating `text` for text and the TokenStream
+/// generated by `json` for json.
+pub(crate) fn negotiate(
+    format: TokenStream,
+    text: TokenStream,
+    json: impl FnOnce() -> TokenStream,
+) -> TokenStream {
+    let json = with_json(|| {
+        let json = json();
+        quote! { choices::negotiate::Format::Json => #json, }
+    });
+    quote! {
+        match #format {
+            choices::negotiate::Format::Text => #text,
+            #json
+        }
+    }
+}
+
+/// Generates the reply, in the format bound to `format`, whose body is `text` as text and the
+/// result `json` of the serialization as json.
+pub(crate) fn value_reply(text: TokenStream, json: impl FnOnce() -> TokenStream) -> TokenStream {
+    negotiate(
+        quote! { format },
+        quote! { choices::negotiate::reply(format, #text, StatusCode::OK) },
+        || {
+            let json = json();
+            let error_reply = internal_error_reply(quote! { err });
+            quote! {
+                match #json {
+                    Ok(body) => choices::negotiate::reply(format, body, StatusCode::OK),
+                    Err(err) => #error_reply,
+                }
+            }
+        },
+    )
+}
+
+/// Generates the empty reply, in the format bound to `format`, of a successful modification.
+pub(crate) fn empty_reply() -> TokenStream {
+    quote! { choices::negotiate::reply(format, String::new(), StatusCode::OK) }
+}
+
+/// Generates the reply, in the format bound to `format`, of the `ChoicesError` `err` raised by
+/// the field `field`, if any. The input is the body bound to `bytes` if `input` is true.
+pub(crate) fn error_reply(
+    err: TokenStream,
+    field: Option<&str>,
+    input: bool,
+    status: TokenStream,
+) -> TokenStream {
+    let field = match field {
+        Some(field) => quote! { Some(#field) },
+        None => quote! { None },
+    };
+    let input = if input {
+        quote! { Some(&bytes) }
+    } else {
+        quote! { None }
+    };
+    quote! { choices::negotiate::error_reply(format, &#err, #field, #input, #status) }
+}
+
+/// Generates the reply, in the format bound to `format`, of the error `err` which isn't caused by
+/// the request, such as the failed access to the configuration.
+pub(crate) fn internal_error_reply(err: TokenStream) -> TokenStream {
+    quote! { choices::negotiate::internal_error_reply(format, #err) }
+}
+
+/// Generates the conversion of `value`, a reference to a `ty`, through `choices::negotiate`.
+fn convert(ty: &TokenStream, value: &TokenStream, call: TokenStream) -> TokenStream {
+    quote! {{
+        #[allow(unused_imports)]
+        use choices::negotiate::{
+            JsonAsTextInput, JsonAsTextOutput, JsonInput, JsonOutput, TextAsJsonInput,
+            TextAsJsonOutput, TextInput, TextOutput,
+        };
+        #[allow(clippy::needless_borrow)]
+        let result = (&choices::negotiate::ReprOf::<#ty>::new()).#call(#value);
+        result
+    }}
+}
+
+/// Generates the expression writing `value`, a reference to a `ty`, as text, where `json` tells
+/// the format of the configuration.
+pub(crate) fn text_output(ty: &TokenStream, value: &TokenStream, json: bool) -> TokenStream {
+    if json {
+        convert(ty, value, quote! { to_text })
+    } else {
+        quote! { choices::ChoicesOutput::body_string(#value) }
+    }
+}
+
+/// Generates the expression serializing `value`, a reference to a `ty`, as a json
+/// `serde_json::Value`, where `json` tells the format of the configuration.
+pub(crate) fn json_output(ty: &TokenStream, value: &TokenStream, json: bool) -> TokenStream {
+    if json {
+        quote! { choices::serde_json::to_value(#value) }
+    } else {
+        convert(ty, value, quote! { to_json })
+    }
+}
+
+/// Generates the expression serializing `value`, a reference to a `ty`, as a json `String`,
+/// where `json` tells the format of the configuration.
+pub(crate) fn json_string(ty: &TokenStream, value: &TokenStream, json: bool) -> TokenStream {
+    if json {
+        quote! { choices::serde_json::to_string(#value) }
+    } else {
+        let value = json_output(ty, value, json);
+        quote! { #value.map(|value| value.to_string()) }
+    }
+}
+
+/// Generates the expression parsing a `ty` from the text `bytes`, a reference to
+/// `choices::bytes::Bytes`, where `json` tells the format of the configuration.
+pub(crate) fn text_input(ty: &TokenStream, bytes: &TokenStream, json: bool) -> TokenStream {
+    if json {
+        convert(ty, bytes, quote! { parse_text })
+    } else {
+        quote! { <#ty as choices::ChoicesInput>::from_chars(#bytes) }
+    }
+}
+
+/// Generates the expression parsing a `ty` from the json `value`, a `serde_json::Value`, where
+/// `json` tells the format of the configuration.
+pub(crate) fn json_input(ty: &TokenStream, value: &TokenStream, json: bool) -> TokenStream {
+    if json {
+        quote! {
+            choices::serde_json::from_value::<#ty>(#value)
+                .map_err(|err| choices::ChoicesError::ParseError(err.to_string()))
+        }
+    } else {
+        convert(ty, value, quote! { parse_json })
+    }
+}
+
+/// Generates the expression parsing a `ty` from the body `bytes` in the format bound to
+/// `body_format`, where `json` tells the format of the configuration.
+pub(crate) fn parse_body(ty: &TokenStream, json: bool) -> TokenStream {
+    negotiate(
+        quote! { body_format },
+        text_input(ty, &quote! { &bytes }, json),
+        || {
+            let value = json_input(ty, &quote! { value }, json);
+            quote! {
+                choices::serde_json::from_slice::<choices::serde_json::Value>(&bytes)
+                    .map_err(|err| choices::ChoicesError::ParseError(err.to_string()))
+                    .and_then(|value| #value)
+            }
+        },
+    )
+}
@@ -0,0 +1,191 @@
+//! Generation of the methods saving configurations to files and loading them back.
+
+use crate::bulk::stored_fields;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{punctuated::Punctuated, token::Comma, Field, LitStr};
+
+/// Generates the methods saving the configuration to files and loading it from them, and the
+/// persistence of each configuration object, to the file `path` of `#[choices(persist)]` if any.
+///
+/// The persistence of each nested configuration is linked to the one of its parent, so that the
+/// changes made through its setters are saved to the parent's file as well.
+pub(crate) fn gen_persistence(
+    fields: &Punctuated<Field, Comma>,
+    path: Option<&LitStr>,
+) -> TokenStream {
+    let path = path.map_or(quote! { None }, |path| quote! { Some(#path) });
+    let nested = stored_fields(fields)
+        .into_iter()
+        .filter(|(_, _, field_attr)| field_attr.nested)
+        .map(|(field, name, _)| {
+            let field_ident = &field.ident;
+            quote! { self.#field_ident.persistence().nest_in(&persistence, #name); }
+        });
+    let encode = gen_encode();
+    let encode_part = gen_encode_part();
+    let decode = gen_decode();
+    quote! {
+        /// Returns the persistence of this configuration object, i.e. the file where it's saved
+        /// after every change, if any.
+        pub fn persistence(&self) -> std::sync::Arc<choices::persist::Persistence> {
+            let persistence = self.__state().persistence(#path);
+            #( #nested )*
+            persistence
+        }
+
+        /// Saves the values of all fields but the skipped ones to the file `path`, atomically.
+        /// The format of the file is told by its extension.
+        pub fn save(&self, path: impl AsRef<std::path::Path>) -> choices::ChoicesResult<()> {
+            let path = path.as_ref();
+            choices::persist::write_atomic(path, &self.__encode(path)?)
+        }
+
+        /// Encodes the values of all fields but the skipped ones in the format of the file
+        /// `path`.
+        #[doc(hidden)]
+        pub fn __encode(&self, path: &std::path::Path) -> choices::ChoicesResult<Vec<u8>> {
+            Ok(match choices::persist::FileFormat::of(path) {
+                choices::persist::FileFormat::Text => {
+                    let mut values = String::new();
+                    self.__stored_text("", &mut values);
+                    values.into_bytes()
+                }
+                #encode
+            })
+        }
+
+        /// Encodes the values of all fields but the skipped ones as the part of the file `path`
+        /// holding this configuration, nested at `names` within the one of the file.
+        #[doc(hidden)]
+        pub fn __encode_part(
+            &self,
+            path: &std::path::Path,
+            names: &[&'static str],
+        ) -> choices::ChoicesResult<choices::persist::Part> {
+            Ok(match choices::persist::FileFormat::of(path) {
+                choices::persist::FileFormat::Text => {
+                    let prefix = format!("{}.", names.join("."));
+                    let mut lines = String::new();
+                    self.__stored_text(&prefix, &mut lines);
+                    choices::persist::Part::Text(lines)
+                }
+                #encode_part
+            })
+        }
+
+        /// Loads the values of the fields from the file `path`, running the validators of the
+        /// fields. Either all values are loaded or none, and the fields missing from the file
+        /// are left unchanged.
+        pub fn load(&mut self, path: impl AsRef<std::path::Path>) -> choices::ChoicesResult<()> {
+            let path = path.as_ref();
+            let bytes = choices::persist::read(path)?;
+            let patches = match choices::persist::FileFormat::of(path) {
+                choices::persist::FileFormat::Text => {
+                    choices::persist::prepare_text(&bytes, |name, value| {
+                        self.__prepare_stored_text(name, value)
+                    })?
+                }
+                #decode
+            };
+            for patch in patches {
+                patch(self);
+            }
+            Ok(())
+        }
+
+        /// Loads the values of the fields from the file of `persistence()`, if it exists.
+        pub fn restore(&mut self) -> choices::ChoicesResult<()> {
+            let persistence = self.persistence();
+            match persistence.path() {
+                Some(path) if path.exists() => {
+                    self.load(&path)?;
+                    persistence.mark_saved(self.__revision());
+                    Ok(())
+                }
+                _ => Ok(()),
+            }
+        }
+
+        /// Returns the save of the configuration to the file of `persistence()`, if any, if it
+        /// changed since it was last saved.
+        #[doc(hidden)]
+        pub fn __pending_save(&self) -> Option<choices::persist::PendingSave> {
+            let persistence = self.persistence();
+            let revision = self.__revision();
+            let path = persistence.pending(revision)?;
+            let contents = self.__encode(&path);
+            Some(choices::persist::PendingSave::new(persistence, path, revision, contents))
+        }
+
+        /// Saves the configuration in the background to the file of `persistence()`, if any, if
+        /// it changed since it was last saved, and to the files of the configurations it's
+        /// nested in. Failed saves are recorded by the persistences.
+        #[doc(hidden)]
+        pub fn __persist(&self) {
+            let persistence = self.persistence();
+            if let Some(pending) = self.__pending_save() {
+                pending.spawn();
+            }
+            for (outer, path, names) in persistence.outer_files() {
+                let part = self.__encode_part(&path, &names);
+                choices::persist::PendingSave::part(outer, path, names, part).spawn();
+            }
+        }
+    }
+}
+
+/// Generates the match arm encoding the values of the fields in the formats other than text.
+fn gen_encode() -> TokenStream {
+    #[cfg(feature = "json")]
+    return quote! {
+        format => {
+            let values = self
+                .__stored_json()
+                .map_err(|err| choices::ChoicesError::ParseError(err.to_string()))?;
+            choices::persist::encode_json(format, values)?
+        }
+    };
+
+    #[cfg(not(feature = "json"))]
+    quote! {
+        format => return Err(choices::persist::unsupported(format)),
+    }
+}
+
+/// Generates the match arm encoding the values of the fields as part of a file in the formats
+/// other than text.
+fn gen_encode_part() -> TokenStream {
+    #[cfg(feature = "json")]
+    return quote! {
+        _ => {
+            let values = self
+                .__stored_json()
+                .map_err(|err| choices::ChoicesError::ParseError(err.to_string()))?;
+            choices::persist::Part::Json(values)
+        }
+    };
+
+    #[cfg(not(feature = "json"))]
+    quote! {
+        format => return Err(choices::persist::unsupported(format)),
+    }
+}
+
+/// Generates the match arm decoding the values of the fields from the formats other than text.
+fn gen_decode() -> TokenStream {
+    #[cfg(feature = "json")]
+    return quote! {
+        format => {
+            let values = choices::persist::decode_json(format, &bytes)?;
+            choices::patch::prepare_json(values, |name, value| {
+                self.__prepare_stored_json(name, value)
+            })?
+        }
+    };
+
+    #[cfg(not(feature = "json"))]
+    quote! {
+        format => return Err(choices::persist::unsupported(format)),
+    }
+}
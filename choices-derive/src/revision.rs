@@ -0,0 +1,135 @@
+//! Generation of the revisions of configurations, exposed as `ETag`s and checked against the
+//! `If-Match` header of modifying requests.
+
+use crate::attributes::Attributes;
+use crate::constants::{ETAG_HEADER, IF_MATCH_HEADER};
+use crate::negotiate::error_reply;
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::{punctuated::Punctuated, token::Comma, *};
+
+/// Returns the ident of the hidden fn returning the revision of the field `field_ident`.
+fn field_revision_ident(field_ident: &Ident) -> Ident {
+    format_ident!("__revision_{}", field_ident)
+}
+
+/// Returns the TokenStream of the revision of the field `field_ident` of the configuration bound
+/// to `config`.
+pub(crate) fn field_revision(field_ident: &Ident) -> TokenStream {
+    let revision_ident = field_revision_ident(field_ident);
+    quote! { config.#revision_ident() }
+}
+
+/// Returns the TokenStream of the revision of the whole configuration bound to `config`.
+pub(crate) fn config_revision() -> TokenStream {
+    quote! { config.__revision() }
+}
+
+/// Generates the hidden fns returning the revisions of the configuration and of its fields.
+///
+/// The revision of a configuration includes the ones of its nested configurations, each of which
+/// has revisions of its own.
+pub(crate) fn gen_revisions(fields: &Punctuated<Field, Comma>) -> TokenStream {
+    let fields_count = fields.len();
+    let nested = fields.iter().filter_map(|field| {
+        if Attributes::from_field(field).nested {
+            field.ident.as_ref()
+        } else {
+            None
+        }
+    });
+    let fields_revisions = fields.iter().enumerate().filter_map(|(index, field)| {
+        if Attributes::from_field(field).skip {
+            return None;
+        }
+        let field_ident = field
+            .ident
+            .as_ref()
+            .expect("unnamed fields are not supported!");
+        let revision_ident = field_revision_ident(field_ident);
+        Some(quote! {
+            /// Returns the revision at which the field was last set.
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            pub fn #revision_ident(&self) -> u64 {
+                self.__revisions().field(#index)
+            }
+        })
+    });
+    quote! {
+        /// Returns the revisions of this configuration object.
+        #[doc(hidden)]
+        pub fn __revisions(&self) -> std::sync::Arc<choices::revision::Revisions> {
+            self.__state().revisions(#fields_count)
+        }
+
+        /// Returns the revision of this configuration object, including the nested ones.
+        #[doc(hidden)]
+        pub fn __revision(&self) -> u64 {
+            self.__revisions().config() #( + self.#nested.__revision() )*
+        }
+
+        #( #fields_revisions )*
+    }
+}
+
+/// Generates the expression recording that the field at `index` has been set, returning the
+/// new revision.
+pub(crate) fn bump_revision(index: usize) -> TokenStream {
+    quote! { self.__revisions().bump(#index) }
+}
+
+/// Generates the reply built by `reply`, with the `ETag` of the revision `revision`.
+///
+/// `reply` matches on `access`, the result of `access_pattern`, and `revision` is evaluated with
+/// the configuration bound to `config`, so that it's read while the configuration is being
+/// accessed.
+pub(crate) fn reply_with_etag(
+    access_pattern: &TokenStream,
+    revision: &TokenStream,
+    reply: TokenStream,
+) -> TokenStream {
+    quote! {{
+        let access = #access_pattern;
+        let revision: u64 = match &access {
+            Ok(config) => #revision,
+            Err(_) => 0,
+        };
+        let reply = #reply;
+        choices::warp::reply::with_header(
+            reply,
+            #ETAG_HEADER,
+            choices::revision::etag(revision),
+        )
+    }}
+}
+
+/// Returns the TokenStream of the warp filter extracting the optional `If-Match` header.
+pub(crate) fn if_match_filter() -> TokenStream {
+    quote! { choices::warp::header::optional::<String>(#IF_MATCH_HEADER) }
+}
+
+/// Generates the check of the `If-Match` header, bound to `if_match`, against the revision
+/// `revision` of `field`, or of the whole configuration if `None`, evaluated with the
+/// configuration bound to `config`: `reply` is evaluated only if they match, otherwise the reply
+/// is `412 Precondition Failed`.
+pub(crate) fn check_if_match(
+    revision: &TokenStream,
+    field: Option<&str>,
+    reply: TokenStream,
+) -> TokenStream {
+    let error_reply = error_reply(
+        quote! { choices::ChoicesError::PreconditionFailed(revision) },
+        field,
+        false,
+        quote! { StatusCode::PRECONDITION_FAILED },
+    );
+    quote! {{
+        let revision: u64 = #revision;
+        if choices::revision::if_match(if_match.as_deref(), revision) {
+            #reply
+        } else {
+            #error_reply
+        }
+    }}
+}
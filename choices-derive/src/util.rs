@@ -1,36 +1,91 @@
+use proc_macro2::TokenStream;
 use proc_macro_error::abort_call_site;
+use quote::quote;
 use syn::*;
 
 /// Returns a string representation of a type.
 pub(crate) fn compute_type_string(ty: &Type) -> String {
     match ty {
-        Type::Path(ref typepath) if typepath.qself.is_none() => typepath
-            .path
-            .segments
-            .iter()
-            .into_iter()
-            .fold(String::new(), |mut acc, v| {
-                acc.push_str(&v.ident.to_string());
-                if let PathArguments::AngleBracketed(ref arguments) = &v.arguments {
-                    if !arguments.args.is_empty() {
-                        acc.push('<');
-                        for (i, arg) in arguments.args.iter().enumerate() {
-                            if let GenericArgument::Type(inner_type) = arg {
-                                if i != 0 {
-                                    acc.push_str(", ");
+        Type::Path(ref typepath) if typepath.qself.is_none() => {
+            typepath
+                .path
+                .segments
+                .iter()
+                .fold(String::new(), |mut acc, v| {
+                    acc.push_str(&v.ident.to_string());
+                    if let PathArguments::AngleBracketed(ref arguments) = &v.arguments {
+                        if !arguments.args.is_empty() {
+                            acc.push('<');
+                            for (i, arg) in arguments.args.iter().enumerate() {
+                                if let GenericArgument::Type(inner_type) = arg {
+                                    if i != 0 {
+                                        acc.push_str(", ");
+                                    }
+                                    acc.push_str(&compute_type_string(inner_type));
+                                } else {
+                                    abort_call_site!(
+                                        "choices supports only generic arguments of kind Type"
+                                    )
                                 }
-                                acc.push_str(&compute_type_string(inner_type));
-                            } else {
-                                abort_call_site!(
-                                    "choices supports only generic arguments of kind Type"
-                                )
                             }
+                            acc.push('>');
                         }
-                        acc.push('>');
                     }
-                }
-                acc
-            }),
+                    acc
+                })
+        }
         _ => abort_call_site!("choices supports only simple types (syn::Type::Path) for fields"),
     }
 }
+
+/// Kinds of fields whose elements are exposed as resources.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum CollectionKind {
+    /// `Vec`, `VecDeque` and `HashSet`, implementing `ChoicesCollection`.
+    Collection,
+    /// `HashMap` and `BTreeMap`, implementing `ChoicesMap`.
+    Map,
+}
+
+impl CollectionKind {
+    /// Returns the path of the trait implemented by this kind of field.
+    pub(crate) fn trait_path(self) -> TokenStream {
+        match self {
+            CollectionKind::Collection => quote! { choices::ChoicesCollection },
+            CollectionKind::Map => quote! { choices::ChoicesMap },
+        }
+    }
+}
+
+/// Returns the kind of `ty` if its elements are exposed as resources.
+pub(crate) fn collection_kind(ty: &Type) -> Option<CollectionKind> {
+    match ty {
+        Type::Path(ref typepath) if typepath.qself.is_none() => {
+            let segment = typepath.path.segments.last()?;
+            if segment.ident == "Vec" || segment.ident == "VecDeque" || segment.ident == "HashSet" {
+                Some(CollectionKind::Collection)
+            } else if segment.ident == "HashMap" || segment.ident == "BTreeMap" {
+                Some(CollectionKind::Map)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if `ty` is `choices::State`, the type of the field holding the state of a
+/// configuration.
+pub(crate) fn is_state(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ref typepath) if typepath.qself.is_none() => {
+            let segments: Vec<_> = typepath.path.segments.iter().collect();
+            match segments[..] {
+                [state] => state.ident == "State",
+                [choices, state] => choices.ident == "choices" && state.ident == "State",
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,298 @@
+//! Implementation of the `ChoicesValue` derive, which generates `ChoicesInput` and
+//! `ChoicesOutput` for user defined types.
+
+use crate::attributes::{parse_choices_attributes, ChoicesAttribute};
+use proc_macro2::TokenStream;
+use proc_macro_error::{abort, abort_call_site};
+use quote::{format_ident, quote};
+use syn::*;
+
+/// The textual representation of a type deriving `ChoicesValue`.
+enum Layout {
+    /// Enum made only of unit variants, represented by their name.
+    Variants,
+    /// Through the traits `FromStr` and `Display`.
+    FromStr,
+    /// Fields separated by a delimiter, preceded by the variant name in case of enums.
+    Delimiter(String),
+    /// Json, through `serde`.
+    SerdeJson,
+}
+
+impl Layout {
+    fn from_attributes(input: &DeriveInput) -> Self {
+        let mut layout = None;
+        for attr in parse_choices_attributes(&input.attrs) {
+            let (ident, value) = match attr {
+                ChoicesAttribute::FromStr(ident) => (ident, Layout::FromStr),
+                ChoicesAttribute::SerdeJson(ident) => (ident, Layout::SerdeJson),
+                ChoicesAttribute::Delimiter(ident, lit) => (ident, Layout::Delimiter(lit.value())),
+                _ => abort_call_site!(
+                    "ChoicesValue supports only #[choices(from_str)], #[choices(serde_json)] \
+                    and #[choices(delimiter = \"...\")]"
+                ),
+            };
+            if layout.is_some() {
+                abort!(
+                    ident,
+                    "ChoicesValue accepts only one representation attribute"
+                );
+            }
+            layout = Some(value);
+        }
+        match (layout, &input.data) {
+            (Some(layout), _) => layout,
+            (None, Data::Enum(_)) => Layout::Variants,
+            (None, _) => abort_call_site!(
+                "ChoicesValue on structs requires one of #[choices(from_str)], \
+                #[choices(serde_json)] or #[choices(delimiter = \"...\")]"
+            ),
+        }
+    }
+}
+
+pub(crate) fn impl_choices_value(input: &DeriveInput) -> TokenStream {
+    if !input.generics.params.is_empty() {
+        abort!(input.generics, "ChoicesValue doesn't support generic types");
+    }
+    let name = &input.ident;
+
+    let (input_body, output_body) = match (Layout::from_attributes(input), &input.data) {
+        (Layout::Variants, Data::Enum(data)) => return impl_for_unit_enum(name, data),
+        (Layout::FromStr, _) => gen_from_str(),
+        (Layout::SerdeJson, _) => gen_serde_json(),
+        (Layout::Delimiter(delimiter), Data::Struct(data)) => {
+            gen_delimited_struct(&data.fields, &delimiter)
+        }
+        (Layout::Delimiter(delimiter), Data::Enum(data)) => gen_delimited_enum(data, &delimiter),
+        _ => abort_call_site!("ChoicesValue only supports structs and enums"),
+    };
+
+    quote! {
+        impl choices::ChoicesInput<'_> for #name {
+            fn from_chars(bytes: &choices::bytes::Bytes) -> choices::ChoicesResult<Self> {
+                #input_body
+            }
+        }
+
+        impl choices::ChoicesOutput for #name {
+            fn body_string(&self) -> String {
+                #output_body
+            }
+        }
+    }
+}
+
+/// Returns the name by which `variant` is parsed and printed.
+fn variant_name(variant: &Variant) -> String {
+    let mut name = variant.ident.to_string();
+    for attr in parse_choices_attributes(&variant.attrs) {
+        match attr {
+            ChoicesAttribute::Rename(_, lit) => name = lit.value(),
+            _ => abort!(
+                variant,
+                "only #[choices(rename)] can be used on enum variants"
+            ),
+        }
+    }
+    name
+}
+
+/// Returns the names of all variants of an enum, checking that they are unique.
+fn variant_names(data: &DataEnum) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for variant in &data.variants {
+        let variant_name = variant_name(variant);
+        if names.contains(&variant_name) {
+            abort!(variant, "duplicated variant name `{}`", variant_name);
+        }
+        names.push(variant_name);
+    }
+    names
+}
+
+/// Generates the traits' impl for an enum made only of unit variants.
+fn impl_for_unit_enum(name: &Ident, data: &DataEnum) -> TokenStream {
+    if let Some(variant) = data
+        .variants
+        .iter()
+        .find(|variant| !matches!(variant.fields, Fields::Unit))
+    {
+        abort!(
+            variant,
+            "ChoicesValue supports only enums with unit variants, unless a \
+            representation attribute is specified"
+        );
+    }
+    let names = variant_names(data);
+    let idents = data.variants.iter().map(|variant| &variant.ident);
+    let idents2 = idents.clone();
+    let expected = names.join(", ");
+
+    quote! {
+        impl choices::ChoicesInput<'_> for #name {
+            fn from_chars(bytes: &choices::bytes::Bytes) -> choices::ChoicesResult<Self> {
+                match std::str::from_utf8(bytes)? {
+                    #( #names => Ok(Self::#idents), )*
+                    other => Err(choices::ChoicesError::ParseError(format!(
+                        "unknown variant `{}`, expected one of: {}",
+                        other, #expected
+                    ))),
+                }
+            }
+        }
+
+        impl choices::ChoicesOutput for #name {
+            fn body_string(&self) -> String {
+                match *self {
+                    #( Self::#idents2 => #names.to_string(), )*
+                }
+            }
+        }
+
+        impl choices::ChoicesVariants for #name {
+            const VARIANTS: &'static [&'static str] = &[#( #names ),*];
+        }
+    }
+}
+
+/// Generates the bodies of `from_chars` and `body_string` delegating to `FromStr` and `Display`.
+fn gen_from_str() -> (TokenStream, TokenStream) {
+    (
+        quote! {
+            std::str::from_utf8(bytes)?
+                .parse::<Self>()
+                .map_err(|err| choices::ChoicesError::ParseError(err.to_string()))
+        },
+        quote! {
+            self.to_string()
+        },
+    )
+}
+
+/// Generates the bodies of `from_chars` and `body_string` delegating to `serde_json`.
+fn gen_serde_json() -> (TokenStream, TokenStream) {
+    #[cfg(not(feature = "json"))]
+    abort_call_site!("you must enable the choices feature `json` in order to use it in a macro");
+
+    #[cfg(feature = "json")]
+    (
+        quote! {
+            choices::serde_json::from_slice(bytes)
+                .map_err(|err| choices::ChoicesError::ParseError(err.to_string()))
+        },
+        quote! {
+            match choices::serde_json::to_string(self) {
+                Ok(v) => v,
+                Err(err) => err.to_string(),
+            }
+        },
+    )
+}
+
+/// Returns the patterns binding all `fields` to the idents `field_0`, `field_1` and so on.
+fn fields_pattern(fields: &Fields) -> (TokenStream, Vec<Ident>) {
+    let bindings: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("field_{}", i))
+        .collect();
+    let pattern = match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            quote! { { #( #idents: #bindings ),* } }
+        }
+        Fields::Unnamed(_) => quote! { ( #( #bindings ),* ) },
+        Fields::Unit => quote! {},
+    };
+    (pattern, bindings)
+}
+
+/// Returns the expression parsing `fields` from the vector of `Bytes` named `parts`.
+fn parse_fields(fields: &Fields) -> TokenStream {
+    let values =
+        (0..fields.len()).map(|i| quote! { choices::ChoicesInput::from_chars(&parts[#i])? });
+    match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            quote! { { #( #idents: #values ),* } }
+        }
+        Fields::Unnamed(_) => quote! { ( #( #values ),* ) },
+        Fields::Unit => quote! {},
+    }
+}
+
+/// Generates the bodies of `from_chars` and `body_string` for a struct whose fields are
+/// separated by `delimiter`, which is escaped by a backslash inside the fields.
+fn gen_delimited_struct(fields: &Fields, delimiter: &str) -> (TokenStream, TokenStream) {
+    let count = fields.len();
+    let parse = parse_fields(fields);
+    let (pattern, bindings) = fields_pattern(fields);
+    (
+        quote! {
+            let chars = std::str::from_utf8(bytes)?;
+            #[allow(unused_variables)]
+            let parts = choices::split_fields(Some(chars), #count, #delimiter)?;
+            Ok(Self #parse)
+        },
+        quote! {
+            let Self #pattern = self;
+            let parts: Vec<String> = vec![#(
+                choices::escape(&choices::ChoicesOutput::body_string(#bindings), &[#delimiter])
+            ),*];
+            parts.join(#delimiter)
+        },
+    )
+}
+
+/// Generates the bodies of `from_chars` and `body_string` for an enum whose variants are
+/// represented by their name followed by their fields, all separated by `delimiter`, which is
+/// escaped by a backslash inside the fields.
+fn gen_delimited_enum(data: &DataEnum, delimiter: &str) -> (TokenStream, TokenStream) {
+    let names = variant_names(data);
+    let expected = names.join(", ");
+    let parse_arms = data.variants.iter().zip(&names).map(|(variant, name)| {
+        let ident = &variant.ident;
+        let count = variant.fields.len();
+        let parse = parse_fields(&variant.fields);
+        quote! {
+            #name => {
+                #[allow(unused_variables)]
+                let parts = choices::split_fields(fields, #count, #delimiter)?;
+                Ok(Self::#ident #parse)
+            }
+        }
+    });
+    let output_arms = data.variants.iter().zip(&names).map(|(variant, name)| {
+        let ident = &variant.ident;
+        let (pattern, bindings) = fields_pattern(&variant.fields);
+        quote! {
+            Self::#ident #pattern => {
+                let parts: Vec<String> = vec![
+                    #name.to_string(),
+                    #( choices::escape(
+                        &choices::ChoicesOutput::body_string(#bindings),
+                        &[#delimiter],
+                    ) ),*
+                ];
+                parts.join(#delimiter)
+            }
+        }
+    });
+    (
+        quote! {
+            let chars = std::str::from_utf8(bytes)?;
+            let (variant, fields) = choices::split_variant(chars, #delimiter);
+            match variant {
+                #( #parse_arms )*
+                other => Err(choices::ChoicesError::ParseError(format!(
+                    "unknown variant `{}`, expected one of: {}",
+                    other, #expected
+                ))),
+            }
+        },
+        quote! {
+            match self {
+                #( #output_arms )*
+            }
+        },
+    )
+}
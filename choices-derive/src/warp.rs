@@ -1,358 +1,868 @@
 //! Implementation of the configuration HTTP server built upon `warp`.
 
 use crate::attributes::Attributes;
+use crate::auth::{gen_gates, gen_roles, Gates};
+use crate::bulk::{gen_all_resource, gen_patch_resource, gen_prepare, gen_values};
+use crate::env::gen_env;
+use crate::events::{
+    gen_events, gen_events_resource, gen_field_subscription, notify_watch, send_event, wait_filter,
+};
 use crate::index::{compute_index, IndexData};
+use crate::negotiate::{
+    accept_filter, content_type_filter, empty_reply, error_reply, internal_error_reply,
+    json_output, json_string, negotiate, parse_body, text_output, value_reply,
+};
+use crate::persist::gen_persistence;
+use crate::revision::{
+    bump_revision, check_if_match, field_revision, gen_revisions, if_match_filter, reply_with_etag,
+};
+use crate::util::{collection_kind, CollectionKind};
 use crate::{GenChoicesOutput, DEFAULT_ROOT_PATH};
 use proc_macro2::{Ident, TokenStream};
-#[cfg(not(feature = "json"))]
-use proc_macro_error::abort_call_site;
-use quote::quote;
+use proc_macro_error::abort;
+use quote::{format_ident, quote};
 use syn::{punctuated::Punctuated, token::Comma, *};
 
 pub(crate) fn gen_choices(
     fields: &Punctuated<Field, Comma>,
+    state: &Ident,
     struct_attrs: &[Attribute],
 ) -> GenChoicesOutput {
     let attrs = Attributes::from_struct(struct_attrs);
-    let root_path = attrs.root_path.unwrap_or(quote! { #DEFAULT_ROOT_PATH });
+    let gates = gen_gates(fields, &attrs);
+    let root_path = attrs
+        .root_path
+        .clone()
+        .unwrap_or(quote! { #DEFAULT_ROOT_PATH });
 
     let index_data = compute_index(fields, attrs.json, &attrs.root_message);
-    let fields_resources = gen_fields_resources(fields, &root_path, attrs.json);
-    let fields_resources_mutable =
-        gen_fields_resources_mutable(fields, &root_path, attrs.json, attrs.rw_lock);
+    let mut fields_resources = gen_fields_resources(fields, attrs.json);
+    fields_resources.push(gen_all_resource(attrs.json, immutable_access_pattern()));
+    let mut fields_resources_mutable = gen_fields_resources_mutable(fields, attrs.json);
+    let all_resource_mutable = gen_all_resource(attrs.json, read_access_pattern());
+    let patch_resource = gen_patch_resource(attrs.json, write_access_pattern());
+    fields_resources_mutable.push(quote! {{
+        let choices = $choices.clone();
+        #all_resource_mutable
+    }});
+    fields_resources_mutable.push(gen_events_resource(attrs.json, read_access_pattern()));
+    #[cfg(feature = "json")]
+    {
+        let ws_resource =
+            crate::ws::gen_ws_resource(attrs.json, read_access_pattern(), write_access_pattern());
+        fields_resources_mutable.push(quote! {{
+            let choices = $choices.clone();
+            #ws_resource
+        }});
+    }
+    // The PATCH resource shares the root path with the index, which accepts any method, so its
+    // invalid signatures must be replied before falling back to the index.
+    let root_resources_mutable = vec![quote! {{
+        let choices = $choices.clone();
+        #patch_resource.recover(choices::signature::recover)
+    }}];
 
     let macros_tk = gen_macros(
-        &root_path,
         index_data,
         &fields_resources,
         &fields_resources_mutable,
+        &root_resources_mutable,
+        gates,
+        attrs.cors.as_ref(),
     );
-    let impl_tk = gen_impl(fields, attrs.rw_lock);
-    let trait_tk = gen_trait(attrs.rw_lock);
+    let values_tk = gen_values(fields, attrs.json);
+    let prepare_tk = gen_prepare(fields, attrs.json);
+    let env_tk = gen_env(fields, attrs.json, attrs.env_prefix.as_ref());
+    #[cfg(feature = "json")]
+    let value_tk = if attrs.json {
+        quote! {}
+    } else {
+        crate::ws::gen_value_text(fields)
+    };
+    #[cfg(not(feature = "json"))]
+    let value_tk = quote! {};
+    let bulk_tk = quote! {
+        #values_tk
+        #value_tk
+        #prepare_tk
+        #env_tk
+    };
+    let impl_tk = gen_impl(fields, state, &attrs, &bulk_tk, &root_path);
+    let trait_tk = gen_trait(&root_path, attrs.rw_lock);
 
     GenChoicesOutput::new(macros_tk, impl_tk, trait_tk)
 }
 
+/// Returns the TokenStream of the warp filter matching exactly the path of the field
+/// `field_name`, relative to the configuration root filter `$root`.
+fn field_path(field_name: &str) -> TokenStream {
+    quote! {
+        $root.clone().and(choices::warp::path(#field_name)).and(choices::warp::path::end())
+    }
+}
+
+/// Returns the TokenStream of the root filter for the nested configuration stored in the
+/// field `field_name`.
+fn nested_root(field_name: &str) -> TokenStream {
+    quote! {
+        $root.clone().and(choices::warp::path(#field_name)).boxed()
+    }
+}
+
+/// Returns the TokenStream of the warp filter matching the path of an element of the collection
+/// or map field `field_name`, of type `ty`, which extracts the key of the element.
+///
+/// Segments are percent-decoded, and the ones which aren't valid keys are rejected as not found.
+fn item_path(field_name: &str, ty: &Type, kind: CollectionKind) -> TokenStream {
+    let trait_path = kind.trait_path();
+    quote! {
+        $root.clone()
+            .and(choices::warp::path(#field_name))
+            .and(choices::warp::path::param::<String>())
+            .and(choices::warp::path::end())
+            .and_then(|segment: String| async move {
+                choices::collection::segment_key(&segment, <#ty as #trait_path>::parse_key)
+                    .ok_or_else(choices::warp::reject::not_found)
+            })
+    }
+}
+
 /// Generates the fields' HTTP resources, i.e. the GET methods to retrieve the value of
 /// fields from an immutable `self`.
-fn gen_fields_resources(
-    fields: &Punctuated<Field, Comma>,
-    root_path: &TokenStream,
-    json: bool,
-) -> Vec<TokenStream> {
+fn gen_fields_resources(fields: &Punctuated<Field, Comma>, json: bool) -> Vec<TokenStream> {
     fields
         .iter()
-        .filter_map(|field| {
+        .flat_map(|field| {
             let field_attr = Attributes::from_field(field);
             if field_attr.skip || field_attr.hide_get {
-                None
+                vec![]
             } else {
                 let field_ident = field
                     .ident
                     .as_ref()
                     .expect("unnamed fields are not supported!");
                 let field_name = field_ident.to_string();
-                let get_reply = if json {
-                    get_reply_for_field_json(
-                        field_ident,
-                        quote! { { let r: choices::ChoicesResult<_>  = Ok($self); r } }
-                    )
-                } else {
-                    get_reply_for_field_text(
-                        field_ident,
-                        quote! { { let r: choices::ChoicesResult<_>  = Ok($self); r } }
-                    )
-                };
-                Some(quote! {
-                    choices::warp::path!(#root_path / #field_name).and(choices::warp::path::end()).#get_reply
-                })
+                if field_attr.nested {
+                    let nested_root = nested_root(&field_name);
+                    return vec![quote! {
+                        $self.#field_ident.__filter_nested(#nested_root)
+                    }];
+                }
+                let access_pattern = immutable_access_pattern();
+                let path = field_path(&field_name);
+                let kind = collection_kind(&field.ty);
+                let get_reply =
+                    get_reply_for_field(field_ident, &field.ty, kind, json, access_pattern.clone());
+                let mut resources = vec![quote! {
+                    #path.#get_reply
+                }];
+                if let Some(kind) = kind {
+                    let item_path = item_path(&field_name, &field.ty, kind);
+                    let get_item_reply =
+                        get_reply_for_item(field_ident, &field.ty, kind, json, access_pattern);
+                    resources.push(quote! {
+                        #item_path.and(choices::warp::get()).#get_item_reply
+                    });
+                }
+                resources
             }
         })
         .collect()
 }
 
-/// Generates the warp::reply map() for GET field, to return text.
+/// Generates the warp::reply map() for GET field, where fields of kind `kind` are maps whose
+/// keys are listed in place of their value.
 ///
-/// `field_ident` is the ident of the field, `access_pattern` represents the way the field
-/// can be accessed.
-fn get_reply_for_field_text(field_ident: &Ident, access_pattern: TokenStream) -> TokenStream {
-    let content_type_header = crate::constants::CONTENT_TYPE_HEADER;
-    let content_type_value = crate::constants::CONTENT_TYPE_TEXT;
-    quote! {
-        map(choices::warp::reply)
-        .map(move |reply| {
-            use choices::warp::{reply::{with_header, with_status}, http::StatusCode};
-            match #access_pattern {
-                Ok(config) => with_status(
-                    with_header(
-                        config.#field_ident.body_string(),
-                        #content_type_header,
-                        #content_type_value,
-                    ),
-                    StatusCode::OK,
-                ),
-                Err(err) => with_status(
-                    with_header(err.to_string(), #content_type_header, #content_type_value),
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                ),
+/// `field_ident` is the ident of the field, of type `ty`, `access_pattern` represents the way
+/// the field can be accessed.
+fn get_reply_for_field(
+    field_ident: &Ident,
+    ty: &Type,
+    kind: Option<CollectionKind>,
+    json: bool,
+    access_pattern: TokenStream,
+) -> TokenStream {
+    let value = quote! { &config.#field_ident };
+    let reply = match kind {
+        Some(CollectionKind::Map) => keys_reply(field_ident, ty, json),
+        _ => {
+            let ty = quote! { #ty };
+            value_reply(text_output(&ty, &value, json), || {
+                json_string(&ty, &value, json)
+            })
+        }
+    };
+    let error_reply = internal_error_reply(quote! { err });
+    let reply = reply_with_etag(
+        &access_pattern,
+        &field_revision(field_ident),
+        quote! {
+            match access {
+                Ok(config) => #reply,
+                Err(err) => #error_reply,
             }
+        },
+    );
+    let accept_filter = accept_filter(json);
+    quote! {
+        and(#accept_filter)
+        .map(move |format: choices::negotiate::Format| {
+            use choices::warp::http::StatusCode;
+            #reply
         })
     }
 }
 
-/// Generates the warp::reply map() for GET field, to return json.
-///
-/// `field_ident` is the ident of the field, `_access_pattern` represents the way the field
-/// can be accessed.
-fn get_reply_for_field_json(_field_ident: &Ident, _access_pattern: TokenStream) -> TokenStream {
-    #[cfg(not(feature = "json"))]
-    abort_call_site!("you must enable the choices feature `json` in order to use it in a macro");
+/// Generates the reply listing the keys of the map field `field_ident`, of type `ty`.
+fn keys_reply(field_ident: &Ident, ty: &Type, json: bool) -> TokenStream {
+    let key_type = quote! { <#ty as choices::ChoicesMap>::Key };
+    let text = text_output(&key_type, &quote! { key }, json);
+    let key_json = json_output(&key_type, &quote! { key }, json);
+    let reply = value_reply(
+        quote! {
+            keys.iter().map(|&key| #text).collect::<Vec<_>>().join(",")
+        },
+        || {
+            if json {
+                quote! { choices::serde_json::to_string(&keys) }
+            } else {
+                quote! {
+                    keys.iter()
+                        .map(|&key| #key_json)
+                        .collect::<choices::serde_json::Result<Vec<_>>>()
+                        .map(|keys| choices::serde_json::Value::Array(keys).to_string())
+                }
+            }
+        },
+    );
+    quote! {{
+        let keys = choices::ChoicesMap::keys_list(&config.#field_ident);
+        #reply
+    }}
+}
 
-    #[cfg(feature = "json")]
-    {
-        let content_type_header = crate::constants::CONTENT_TYPE_HEADER;
-        let content_type_value = crate::constants::CONTENT_TYPE_JSON;
+/// Generates the warp::reply map() for GET of an element of the collection or map field
+/// `field_ident`, of type `ty`.
+///
+/// `access_pattern` represents the way the field can be accessed.
+fn get_reply_for_item(
+    field_ident: &Ident,
+    ty: &Type,
+    kind: CollectionKind,
+    json: bool,
+    access_pattern: TokenStream,
+) -> TokenStream {
+    let trait_path = kind.trait_path();
+    let item_type = quote! { <#ty as #trait_path>::Item };
+    let item_reply = value_reply(text_output(&item_type, &quote! { item }, json), || {
+        json_string(&item_type, &quote! { item }, json)
+    });
+    let not_found_reply = error_reply(
+        not_found_error(field_ident),
+        Some(&field_ident.to_string()),
+        false,
+        quote! { StatusCode::NOT_FOUND },
+    );
+    let internal_error_reply = internal_error_reply(quote! { err });
+    let reply = reply_with_etag(
+        &access_pattern,
+        &field_revision(field_ident),
         quote! {
-            map(choices::warp::reply)
-            .map(move |reply| {
-                use choices::warp::{reply::{with_header, with_status}, http::StatusCode};
-                match #_access_pattern {
-                    Ok(config) => {
-                        let body = choices::serde_json::to_string(&config.#_field_ident);
-                        let status = if body.is_ok() {
-                            StatusCode::OK
-                        } else {
-                            StatusCode::INTERNAL_SERVER_ERROR
-                        };
-                        with_status(
-                            with_header(
-                                match body {
-                                    Ok(v) => v,
-                                    Err(err) => format!("\"{}\"", err.to_string())
-                                },
-                                #content_type_header,
-                                #content_type_value
+            match access {
+                Ok(config) => match #trait_path::get_item(&config.#field_ident, &key) {
+                    Some(item) => #item_reply,
+                    None => #not_found_reply,
+                },
+                Err(err) => #internal_error_reply,
+            }
+        },
+    );
+    let accept_filter = accept_filter(json);
+    quote! {
+        and(#accept_filter)
+        .map(move |key: <#ty as #trait_path>::Key, format: choices::negotiate::Format| {
+            use choices::warp::http::StatusCode;
+            #reply
+        })
+    }
+}
+
+/// Generates the mutable fields' HTTP resources, i.e. the GET methods to retrieve the value of
+/// fields from a `ChoicesAccess` (such as Arc<Mutex<T>> or Arc<RwLock<T>>) and the PUT methods
+/// to modify such fields.
+fn gen_fields_resources_mutable(fields: &Punctuated<Field, Comma>, json: bool) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .flat_map(|field| {
+            let field_attr = Attributes::from_field(field);
+            if field_attr.skip || (field_attr.hide_get && field_attr.hide_put) {
+                vec![]
+            } else {
+                let field_ident = field
+                    .ident
+                    .as_ref()
+                    .expect("unnamed fields are not supported!");
+                let field_name = field_ident.to_string();
+                let arg_type = &field.ty;
+                if field_attr.nested {
+                    let nested_root = nested_root(&field_name);
+                    return vec![quote! {
+                        <#arg_type>::__filter_mutable_nested(
+                            choices::NestedAccess::new(
+                                $choices.clone(),
+                                |config: &Self| &config.#field_ident,
+                                |config: &mut Self| &mut config.#field_ident,
                             ),
-                            status
+                            #nested_root,
+                            signature.clone(),
                         )
-                    }
-                    Err(err) => with_status(
-                        with_header(err.to_string(), #content_type_header, #content_type_value),
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                    ),
+                    }];
                 }
-            })
-        }
-    }
+                let path = field_path(&field_name);
+                let setter_ident = format_ident!("__set_{}", field_ident);
+                let kind = collection_kind(arg_type);
+                let get_reply =
+                    get_reply_for_field(field_ident, arg_type, kind, json, read_access_pattern());
+                let wait_filter = wait_filter(field_ident, json);
+                let revision = field_revision(field_ident);
+                let put_reply = put_reply_for_field(
+                    &field_name,
+                    arg_type,
+                    &setter_ident,
+                    &revision,
+                    write_access_pattern(),
+                    json,
+                );
+                let resource = if field_attr.hide_get {
+                    quote! {{
+                        let choices = $choices.clone();
+                        let put = choices::warp::put()
+                            .and(choices::warp::body::content_length_limit(1024 * 16))
+                            .#put_reply;
+                        #path.and(put)
+                    }}
+                } else if field_attr.hide_put {
+                    quote! {{
+                        let choices = $choices.clone();
+                        let get = choices::warp::get()
+                            .and(#wait_filter)
+                            .#get_reply
+                            .recover(choices::wait::recover);
+                        #path.and(get)
+                    }}
+                } else {
+                    quote! {{
+                        let choices = $choices.clone();
+                        let get = choices::warp::get()
+                            .and(#wait_filter)
+                            .#get_reply
+                            .recover(choices::wait::recover);
+                        let choices = $choices.clone();
+                        let put = choices::warp::put()
+                            .and(choices::warp::body::content_length_limit(1024 * 16))
+                            .#put_reply;
+                        #path.and(get.or(put))
+                    }}
+                };
+                let mut resources = vec![resource];
+                if let Some(kind) = kind {
+                    resources.extend(gen_items_resources_mutable(
+                        field_ident,
+                        arg_type,
+                        kind,
+                        &field_attr,
+                        json,
+                    ));
+                }
+                resources
+            }
+        })
+        .collect()
 }
 
-/// Generates the mutable fields' HTTP resources, i.e. the GET methods to retrieve the value of
-/// fields from an Arc<Mutex<T>> or Arc<RwLock<T>> and the PUT methods to modify such fields.
-fn gen_fields_resources_mutable(
-    fields: &Punctuated<Field, Comma>,
-    root_path: &TokenStream,
+/// Generates the HTTP resources of the elements of the collection or map field `field_ident`, of
+/// type `ty`: GET and PUT of an element, DELETE to remove it and, for collections, POST to append
+/// a new one.
+fn gen_items_resources_mutable(
+    field_ident: &Ident,
+    ty: &Type,
+    kind: CollectionKind,
+    field_attr: &Attributes,
     json: bool,
-    rw_lock: bool,
 ) -> Vec<TokenStream> {
-    fields.iter().filter_map(|field| {
-        let field_attr = Attributes::from_field(field);
-        if field_attr.skip || (field_attr.hide_get && field_attr.hide_put) {
-            None
-        } else {
-            let field_ident = field
-                .ident
-                .as_ref()
-                .expect("unnamed fields are not supported!");
-            let field_name = field_ident.to_string();
-            let setter_ident = quote::format_ident!("set_{}", field_ident);
-            let arg_type = &field.ty;
-            let (get_reply, put_reply) = if json {
-                (get_reply_for_field_json(field_ident, read_access_pattern(rw_lock)),
-                put_reply_for_field_json(arg_type, &setter_ident, write_access_pattern(rw_lock)))
-            } else {
-                (get_reply_for_field_text(field_ident, read_access_pattern(rw_lock)),
-                put_reply_for_field_text(arg_type, &setter_ident, write_access_pattern(rw_lock)))
-            };
-            if field_attr.hide_get {
-                Some(quote! {{
-                    let choices = $choices.clone();
-                    let put = choices::warp::put()
-                        .and(choices::warp::body::content_length_limit(1024 * 16))
-                        .#put_reply;
-                    choices::warp::path!(#root_path / #field_name).and(choices::warp::path::end()).and(put)
-                }})
-            } else if field_attr.hide_put {
-                Some(quote! {{
-                    let choices = $choices.clone();
-                    let get = choices::warp::get().#get_reply;
-                    choices::warp::path!(#root_path / #field_name).and(choices::warp::path::end()).and(get)
-                }})
-            } else {
-                Some(quote! {{
-                    let choices = $choices.clone();
-                    let get = choices::warp::get().#get_reply;
-                    let choices = $choices.clone();
-                    let put = choices::warp::put()
-                        .and(choices::warp::body::content_length_limit(1024 * 16))
-                        .#put_reply;
-                    choices::warp::path!(#root_path / #field_name).and(choices::warp::path::end()).and(get.or(put))
-                }})
-            }
+    let field_name = field_ident.to_string();
+    let path = field_path(&field_name);
+    let item_path = item_path(&field_name, ty, kind);
+    let trait_path = kind.trait_path();
+    let key_type = quote! { <#ty as #trait_path>::Key };
+    let write_access_pattern = write_access_pattern();
+    let revision = field_revision(field_ident);
+    let if_match_filter = if_match_filter();
+    let mut resources = vec![];
+    if !field_attr.hide_get {
+        let get_item_reply = get_reply_for_item(field_ident, ty, kind, json, read_access_pattern());
+        resources.push(quote! {{
+            let choices = $choices.clone();
+            #item_path.and(choices::warp::get()).#get_item_reply
+        }});
+    }
+    if !field_attr.hide_put {
+        let set_item_ident = format_ident!("__set_{}_{}", field_ident, item_name(kind));
+        let remove_item_ident = format_ident!("__remove_{}_{}", field_ident, item_name(kind));
+        let put_item_reply = put_reply_for_item(
+            &field_name,
+            ty,
+            kind,
+            Some(&key_type),
+            quote! { config.#set_item_ident(key, value) },
+            &revision,
+            json,
+        );
+        let delete_result = check_if_match(
+            &revision,
+            Some(&field_name),
+            reply_for_item_result(
+                quote! { config.#remove_item_ident(key) },
+                &field_name,
+                false,
+            ),
+        );
+        let accept_filter = accept_filter(json);
+        let error_reply = internal_error_reply(quote! { err });
+        resources.push(quote! {{
+            let choices = $choices.clone();
+            #item_path
+                .and(choices::warp::put())
+                .and(choices::warp::body::content_length_limit(1024 * 16))
+                .#put_item_reply
+        }});
+        resources.push(quote! {{
+            let choices = $choices.clone();
+            #item_path
+                .and(choices::warp::delete())
+                .and(choices::signature::verify(signature.clone(), #json))
+                .and(#if_match_filter)
+                .and(#accept_filter)
+                .map(move |
+                    key: #key_type,
+                    if_match: Option<String>,
+                    format: choices::negotiate::Format,
+                | {
+                    use choices::warp::http::StatusCode;
+                    match #write_access_pattern {
+                        Ok(mut config) => #delete_result,
+                        Err(err) => #error_reply,
+                    }
+                })
+        }});
+        if kind == CollectionKind::Collection {
+            let push_ident = format_ident!("__push_{}", field_ident);
+            let post_reply = put_reply_for_item(
+                &field_name,
+                ty,
+                kind,
+                None,
+                quote! { config.#push_ident(value) },
+                &revision,
+                json,
+            );
+            resources.push(quote! {{
+                let choices = $choices.clone();
+                #path
+                    .and(choices::warp::post())
+                    .and(choices::warp::body::content_length_limit(1024 * 16))
+                    .#post_reply
+            }});
         }
-    }).collect()
+    }
+    resources
 }
 
-/// Returns the TokenStream to access the configuration object in read mode.
-fn read_access_pattern(rw_lock: bool) -> TokenStream {
-    if rw_lock {
-        quote! { choices.read() }
-    } else {
-        quote! { choices.lock() }
+/// Returns the name of the elements of a kind of field, used in the setters' idents.
+fn item_name(kind: CollectionKind) -> &'static str {
+    match kind {
+        CollectionKind::Collection => "item",
+        CollectionKind::Map => "entry",
     }
 }
 
-/// Returns the TokenStream to access the configuration object in write mode.
-fn write_access_pattern(rw_lock: bool) -> TokenStream {
-    if rw_lock {
-        quote! { choices.write() }
-    } else {
-        quote! { choices.lock() }
+/// Returns the TokenStream of the error raised when an element of the field `field_ident`
+/// doesn't exist.
+fn not_found_error(field_ident: &Ident) -> TokenStream {
+    let message = format!("no such element in `{}`", field_ident);
+    quote! { choices::ChoicesError::NotFound(#message.to_string()) }
+}
+
+/// Generates the reply for the result `call` of the modification of an element of the field
+/// `field_name`, where `input` tells whether the request has a body bound to `bytes`.
+fn reply_for_item_result(call: TokenStream, field_name: &str, input: bool) -> TokenStream {
+    let empty_reply = empty_reply();
+    let not_found_reply = error_reply(
+        quote! { err },
+        Some(field_name),
+        input,
+        quote! { StatusCode::NOT_FOUND },
+    );
+    let error_reply = error_reply(
+        quote! { err },
+        Some(field_name),
+        input,
+        quote! { StatusCode::BAD_REQUEST },
+    );
+    quote! {
+        match #call {
+            Ok(_) => #empty_reply,
+            Err(err) if matches!(err.kind(), choices::ChoicesError::NotFound(_)) => {
+                #not_found_reply
+            }
+            Err(err) => #error_reply,
+        }
     }
 }
 
-/// Generates the warp::reply map() for PUT field, accepting text.
+/// Generates the warp::reply map() for PUT or POST of an element of a collection or map of type
+/// `ty`.
 ///
-/// `_access_pattern` represents the way the field can be accessed.
-fn put_reply_for_field_text(
-    arg_type: &Type,
-    setter_ident: &Ident,
-    _access_pattern: TokenStream,
+/// The element is parsed from the body, then `call` is executed with `value` bound to it and
+/// `key` bound to the element's key, if `key_type` is given, provided that the `If-Match` header
+/// matches the revision `revision` of the field `field_name`.
+fn put_reply_for_item(
+    field_name: &str,
+    ty: &Type,
+    kind: CollectionKind,
+    key_type: Option<&TokenStream>,
+    call: TokenStream,
+    revision: &TokenStream,
+    json: bool,
 ) -> TokenStream {
+    let trait_path = kind.trait_path();
+    let item_type = quote! { <#ty as #trait_path>::Item };
+    let key_arg = key_type.map(|key_type| quote! { key: #key_type, });
+    let write_access_pattern = write_access_pattern();
+    let result = check_if_match(
+        revision,
+        Some(field_name),
+        reply_for_item_result(call, field_name, true),
+    );
+    let internal_error_reply = internal_error_reply(quote! { err });
+    let apply = quote! {
+        match #write_access_pattern {
+            Ok(mut config) => #result,
+            Err(err) => #internal_error_reply,
+        }
+    };
+    let error_reply = error_reply(
+        quote! { err },
+        Some(field_name),
+        true,
+        quote! { StatusCode::BAD_REQUEST },
+    );
+    let if_match_filter = if_match_filter();
+    let content_type_filter = content_type_filter(json);
+    let parse = parse_body(&item_type, json);
     quote! {
-        and(choices::warp::body::bytes())
-        .map(move |bytes: choices::bytes::Bytes| {
-            use choices::warp::{reply::with_status, http::StatusCode};
-            let result: choices::ChoicesResult<#arg_type> = choices::ChoicesInput::from_chars(&bytes);
+        and(#if_match_filter)
+        .and(#content_type_filter)
+        .and(choices::signature::body(signature.clone(), #json))
+        .map(move |
+            #key_arg
+            if_match: Option<String>,
+            body_format: choices::negotiate::Format,
+            format: choices::negotiate::Format,
+            bytes: choices::bytes::Bytes,
+        | {
+            use choices::warp::http::StatusCode;
+            let result: choices::ChoicesResult<#item_type> = #parse;
             match result {
-                Ok(value) => {
-                    match #_access_pattern {
-                        Ok(mut config) => {
-                            match config.#setter_ident(value) {
-                                Ok(_) => with_status("".to_string(), StatusCode::OK),
-                                Err(err) => with_status(err.to_string(), StatusCode::BAD_REQUEST)
-                            }
-                        }
-                        Err(err) => with_status(err.to_string(), StatusCode::INTERNAL_SERVER_ERROR)
-                    }
-                }
-                Err(err) => {
-                    with_status(err.to_string(), StatusCode::BAD_REQUEST)
-                }
+                Ok(value) => #apply,
+                Err(err) => #error_reply,
             }
         })
     }
 }
 
-/// Generates the warp::reply map() for PUT field, accepting json.
+/// Returns the TokenStream to access the immutable configuration object `$self`.
+fn immutable_access_pattern() -> TokenStream {
+    quote! { { let r: choices::ChoicesResult<_>  = Ok($self); r } }
+}
+
+/// Returns the TokenStream to access the configuration object in read mode.
+fn read_access_pattern() -> TokenStream {
+    quote! { choices::ChoicesAccess::read_access(&choices) }
+}
+
+/// Returns the TokenStream to access the configuration object in write mode.
+fn write_access_pattern() -> TokenStream {
+    quote! { choices::ChoicesAccess::write_access(&choices) }
+}
+
+/// Generates the warp::reply map() for PUT field, accepting the formats negotiated by
+/// `choices::negotiate`.
 ///
-/// `_access_pattern` represents the way the field can be accessed.
-fn put_reply_for_field_json(
-    _arg_type: &Type,
-    _setter_ident: &Ident,
-    _access_pattern: TokenStream,
+/// The field is set only if the `If-Match` header matches its `revision`. `access_pattern`
+/// represents the way the field can be accessed.
+fn put_reply_for_field(
+    field_name: &str,
+    arg_type: &Type,
+    setter_ident: &Ident,
+    revision: &TokenStream,
+    access_pattern: TokenStream,
+    json: bool,
 ) -> TokenStream {
-    #[cfg(not(feature = "json"))]
-    abort_call_site!("you must enable the choices feature `json` in order to use it in a macro");
-
-    #[cfg(feature = "json")]
-    {
+    let if_match_filter = if_match_filter();
+    let content_type_filter = content_type_filter(json);
+    let parse = parse_body(&quote! { #arg_type }, json);
+    let empty_reply = empty_reply();
+    let error_reply = error_reply(
+        quote! { err },
+        Some(field_name),
+        true,
+        quote! { StatusCode::BAD_REQUEST },
+    );
+    let set = check_if_match(
+        revision,
+        Some(field_name),
         quote! {
-            and(choices::warp::body::json())
-            .map(move |value: #_arg_type| {
-                use choices::warp::{reply::with_status, http::StatusCode};
-                match #_access_pattern {
-                    Ok(mut config) => {
-                        match config.#_setter_ident(value) {
-                            Ok(_) => with_status("".to_string(), StatusCode::OK),
-                            Err(err) => with_status(err.to_string(), StatusCode::BAD_REQUEST)
-                        }
+            match config.#setter_ident(value) {
+                Ok(_) => #empty_reply,
+                Err(err) => #error_reply,
+            }
+        },
+    );
+    let internal_error_reply = internal_error_reply(quote! { err });
+    quote! {
+        and(#if_match_filter)
+        .and(#content_type_filter)
+        .and(choices::signature::body(signature.clone(), #json))
+        .map(move |
+            if_match: Option<String>,
+            body_format: choices::negotiate::Format,
+            format: choices::negotiate::Format,
+            bytes: choices::bytes::Bytes,
+        | {
+            use choices::warp::http::StatusCode;
+            let result: choices::ChoicesResult<#arg_type> = #parse;
+            match result {
+                Ok(value) => {
+                    match #access_pattern {
+                        Ok(mut config) => #set,
+                        Err(err) => #internal_error_reply,
                     }
-                    Err(err) => with_status(err.to_string(), StatusCode::INTERNAL_SERVER_ERROR)
                 }
-            })
-        }
+                Err(err) => #error_reply,
+            }
+        })
     }
 }
 
 /// Generates the macros used to build the warp filters.
+///
+/// `root_resources_mutable` are matched before the index of the mutable configuration. `cors` is
+/// the expression of `#[choices(cors)]`, if any, wrapping the filters.
 fn gen_macros(
-    root_path: &TokenStream,
     index_data: IndexData,
     fields_resources: &[TokenStream],
     fields_resources_mutable: &[TokenStream],
+    root_resources_mutable: &[TokenStream],
+    gates: Gates,
+    cors: Option<&Expr>,
 ) -> TokenStream {
-    let content_type_header = crate::constants::CONTENT_TYPE_HEADER;
-    let index_body = index_data.body;
-    let index_content_type = index_data.content_type;
+    let index_text = index_data.text;
+    let index_json = index_data
+        .json
+        .map(|json| quote! { let index_json: String = #json; });
+    let accept_filter = accept_filter(index_data.default_json);
+    let index_body = negotiate(quote! { format }, quote! { index_text.clone() }, || {
+        quote! { index_json.clone() }
+    });
+    // With an identity extractor the index lists only the fields readable by the caller, so
+    // it's computed for each request.
+    let index_filter = match &gates.identify {
+        Some(identify) => quote! {
+            #identify
+            let index_filter = $root.clone()
+                .and(choices::warp::path::end())
+                .and(#accept_filter)
+                .and(choices::auth::identity(identify.clone()))
+                .map(move |
+                    format: choices::negotiate::Format,
+                    identity: choices::auth::Identity,
+                | {
+                    let identity = &identity;
+                    let index_text: String = #index_text;
+                    #index_json
+                    let body = #index_body;
+                    choices::negotiate::reply(format, body, choices::warp::http::StatusCode::OK)
+                });
+        },
+        None => quote! {
+            let index_text: String = #index_text;
+            #index_json
+            let index_filter = $root.clone()
+                .and(choices::warp::path::end())
+                .and(#accept_filter)
+                .map(move |format: choices::negotiate::Format| {
+                    let body = #index_body;
+                    choices::negotiate::reply(format, body, choices::warp::http::StatusCode::OK)
+                });
+        },
+    };
+    let roles_gates = &gates.roles;
+    // The immutable filter serves only reads, so it's gated only if reads are authenticated.
+    let with_auth = gates
+        .auth
+        .as_ref()
+        .map_or(quote! { filter }, |gate| quote! { #gate.or(filter) });
+    let with_auth_immutable = if gates.auth_get {
+        with_auth.clone()
+    } else {
+        quote! { filter }
+    };
+    // CORS wraps the gates as well, so that their replies carry its headers too.
+    let with_cors = |filter: TokenStream| match cors {
+        Some(cors) => quote! { #filter.with(#cors) },
+        None => filter,
+    };
+    let with_auth = with_cors(with_auth);
+    let with_auth_immutable = with_cors(with_auth_immutable);
 
     quote! {
         macro_rules! create_filter {
-            ($self:ident) => {{
+            ($self:ident, $root:ident) => {{
                 use choices::warp::Filter;
                 #[allow(unused_imports)]
                 use choices::ChoicesOutput;
 
-                choices::warp::path(#root_path)
-                    .and(choices::warp::path::end())
-                    .map(choices::warp::reply)
-                    .map(|reply| {
-                        choices::warp::reply::with_header(#index_body, #content_type_header, #index_content_type)
-                    })
-                #( .or(#fields_resources) )*
+                #index_filter
+                let filter = index_filter
+                #( .or(#fields_resources) )*;
+                #( let filter = #roles_gates.or(filter); )*
+                #with_auth_immutable
             }};
         }
 
         macro_rules! create_filter_mutable {
-            ($choices:ident) => {{
+            ($choices:ident, $root:ident, $signature:expr) => {{
                 use choices::warp::Filter;
                 #[allow(unused_imports)]
                 use choices::{ChoicesInput, ChoicesOutput};
 
-                choices::warp::path(#root_path)
-                    .and(choices::warp::path::end())
-                    .map(choices::warp::reply)
-                    .map(|reply| {
-                        choices::warp::reply::with_header(#index_body, #content_type_header, #index_content_type)
-                    })
-                #( .or(#fields_resources_mutable) )*
+                #[allow(unused_variables)]
+                let signature: Option<std::sync::Arc<choices::signature::Signature>> = $signature;
+
+                #index_filter
+                #( let index_filter = #root_resources_mutable.or(index_filter); )*
+                let filter = index_filter
+                #( .or(#fields_resources_mutable) )*;
+                let filter = filter.recover(choices::signature::recover);
+                #( let filter = #roles_gates.or(filter); )*
+                #with_auth
             }};
         }
     }
 }
 
-/// Generates the struct impl block.
-fn gen_impl(fields: &Punctuated<Field, Comma>, rw_lock: bool) -> TokenStream {
-    let setters = gen_setters(fields);
-    let filter_mutable = gen_impl_filter_mutable(rw_lock);
+/// Generates the struct impl block, where `state` is the ident of the field holding the state
+/// of the configuration and `attrs` are the attributes of the struct.
+fn gen_impl(
+    fields: &Punctuated<Field, Comma>,
+    state: &Ident,
+    attrs: &Attributes,
+    bulk: &TokenStream,
+    root_path: &TokenStream,
+) -> TokenStream {
+    let setters = gen_setters(fields, attrs.json);
+    let revisions = gen_revisions(fields);
+    let events = gen_events(fields);
+    let filter_mutable = gen_impl_filter_mutable(root_path, attrs.rw_lock);
+    let persistence = gen_persistence(fields, attrs.persist.as_ref());
+    let roles = gen_roles(fields, attrs);
+    let signature = attrs
+        .signature
+        .as_ref()
+        .map_or(quote! { None }, |signature| {
+            quote! { Some(std::sync::Arc::new(#signature)) }
+        });
 
     quote! {
         #setters
 
+        /// Returns the state of this configuration object.
+        #[doc(hidden)]
+        pub fn __state(&self) -> &choices::State {
+            &self.#state
+        }
+
+        #revisions
+
+        #events
+
+        #bulk
+
+        #persistence
+
+        #roles
+
         /// If you want more control over the http server instance you can use this
         /// function to retrieve the configuration's `warp::Filter`.
         pub fn filter(
             &'static self,
         ) -> choices::warp::filters::BoxedFilter<(impl choices::warp::Reply,)> {
             use choices::warp::Filter;
-            create_filter!(self).boxed()
+            let root = choices::warp::path(#root_path).boxed();
+            create_filter!(self, root).boxed()
+        }
+
+        /// Returns the `warp::Filter` of this configuration when it's nested inside another one.
+        #[doc(hidden)]
+        pub fn __filter_nested(
+            &'static self,
+            root: choices::warp::filters::BoxedFilter<()>,
+        ) -> choices::warp::filters::BoxedFilter<(impl choices::warp::Reply,)> {
+            use choices::warp::Filter;
+            create_filter!(self, root).boxed()
+        }
+
+        /// Returns the mutable `warp::Filter` of this configuration when it's nested inside
+        /// another one.
+        ///
+        /// The requests are verified with the signature `signature` of the outer configuration,
+        /// if any, or else with the one of this configuration.
+        #[doc(hidden)]
+        pub fn __filter_mutable_nested<A: choices::ChoicesAccess<Self>>(
+            choices: A,
+            root: choices::warp::filters::BoxedFilter<()>,
+            signature: Option<std::sync::Arc<choices::signature::Signature>>,
+        ) -> choices::warp::filters::BoxedFilter<(impl choices::warp::Reply,)> {
+            use choices::warp::Filter;
+            create_filter_mutable!(choices, root, signature.or_else(Self::__signature)).boxed()
+        }
+
+        /// Returns the signature verifying the requests modifying this configuration, if any.
+        #[doc(hidden)]
+        pub fn __signature() -> Option<std::sync::Arc<choices::signature::Signature>> {
+            #signature
         }
 
         #filter_mutable
     }
 }
 
+/// Returns the TokenStream of the mutable filter of the configuration `choices`, relative to the
+/// root filter `root`, which saves the configuration after the requests changing it.
+fn persisted_filter_mutable() -> TokenStream {
+    quote! {
+        choices::persist::after_requests(
+            create_filter_mutable!(choices, root, Self::__signature()),
+            choices,
+            Self::__pending_save,
+        )
+    }
+}
+
 /// Generates the fn implementation `filter_mutable`.
-fn gen_impl_filter_mutable(rw_lock: bool) -> TokenStream {
+fn gen_impl_filter_mutable(root_path: &TokenStream, rw_lock: bool) -> TokenStream {
+    let filter = persisted_filter_mutable();
     macro_rules! def {
         ($ty:ty) => {{
             quote! {
@@ -362,7 +872,8 @@ fn gen_impl_filter_mutable(rw_lock: bool) -> TokenStream {
                     choices: std::sync::Arc<$ty>,
                 ) -> choices::warp::filters::BoxedFilter<(impl choices::warp::Reply,)> {
                     use choices::warp::Filter;
-                    create_filter_mutable!(choices).boxed()
+                    let root = choices::warp::path(#root_path).boxed();
+                    #filter.boxed()
                 }
             }
         }};
@@ -376,8 +887,11 @@ fn gen_impl_filter_mutable(rw_lock: bool) -> TokenStream {
 }
 
 /// Generates the fields' setters.
-fn gen_setters(fields: &Punctuated<Field, Comma>) -> TokenStream {
-    let setters = fields.iter().map(|field| {
+///
+/// Collection and map fields also get methods to modify single elements. Setting a field
+/// increments the revision of the configuration and notifies the subscribers.
+fn gen_setters(fields: &Punctuated<Field, Comma>, json: bool) -> TokenStream {
+    let setters = fields.iter().enumerate().map(|(index, field)| {
         let field_attr = Attributes::from_field(field);
         if field_attr.skip {
             None
@@ -386,29 +900,88 @@ fn gen_setters(fields: &Punctuated<Field, Comma>) -> TokenStream {
                 .ident
                 .as_ref()
                 .expect("unnamed fields are not supported!");
-            let setter_ident = quote::format_ident!("set_{}", field_ident);
+            let setter_ident = format_ident!("set_{}", field_ident);
+            let hidden_setter_ident = format_ident!("__set_{}", field_ident);
             let arg_type = &field.ty;
+            let kind = collection_kind(arg_type);
             // Generate the callback tokenstream.
-            let callback = if let Some(callback) = field_attr.on_set {
+            let callback = if let Some(callback) = &field_attr.on_set {
                 quote! { #callback(&value); }
             } else {
                 quote! {}
             };
             // Generate the validator tokenstream.
-            let validator = if let Some(validator) = field_attr.validator {
-                quote! { #validator(&value)?; }
+            let validator = if let Some(validator) = &field_attr.validator {
+                quote! { #validator(value)?; }
+            } else {
+                quote! {}
+            };
+            // Generate the tokenstream validating each element of a collection.
+            let item_validator = match (&field_attr.item_validator, kind) {
+                (Some(item_validator), Some(CollectionKind::Collection)) => quote! {
+                    for item in value.iter() {
+                        #item_validator(item)?;
+                    }
+                },
+                (Some(item_validator), Some(CollectionKind::Map)) => quote! {
+                    for item in value.values() {
+                        #item_validator(item)?;
+                    }
+                },
+                (Some(_), None) => abort!(
+                    field,
+                    "#[choices(item_validator)] can be used only on collection and map fields"
+                ),
+                (None, _) => quote! {},
+            };
+            let check_ident = format_ident!("__check_{}", field_ident);
+            let apply_ident = format_ident!("__apply_{}", field_ident);
+            let send_event = send_event(field_ident, &field_attr, json, bump_revision(index));
+            let notify_watch = notify_watch(&field_attr, index);
+            let subscription = gen_field_subscription(field_ident, arg_type, &field_attr, index);
+            let items_setters = if let Some(kind) = kind {
+                gen_items_setters(field_ident, arg_type, kind, &field_attr)
             } else {
                 quote! {}
             };
             // Output the setter tokenstream.
             Some(quote! {
                 pub fn #setter_ident(&mut self, value: impl Into<#arg_type>) -> choices::ChoicesResult<()> {
+                    self.#hidden_setter_ident(value)?;
+                    self.__persist();
+                    Ok(())
+                }
+
+                /// Sets the field without saving the configuration, which is saved once it's
+                /// released by the requests.
+                #[doc(hidden)]
+                pub fn #hidden_setter_ident(&mut self, value: impl Into<#arg_type>) -> choices::ChoicesResult<()> {
                     let value = value.into();
+                    self.#check_ident(&value)?;
+                    self.#apply_ident(value);
+                    Ok(())
+                }
+
+                /// Runs the validators of the field on a new value.
+                #[doc(hidden)]
+                pub fn #check_ident(&self, value: &#arg_type) -> choices::ChoicesResult<()> {
+                    #item_validator
                     #validator
+                    Ok(())
+                }
+
+                /// Assigns a new value, already validated, to the field.
+                #[doc(hidden)]
+                pub fn #apply_ident(&mut self, value: #arg_type) {
                     #callback
                     self.#field_ident = value;
-                    Ok(())
+                    #send_event
+                    #notify_watch
                 }
+
+                #subscription
+
+                #items_setters
             })
         }
     });
@@ -417,39 +990,220 @@ fn gen_setters(fields: &Punctuated<Field, Comma>) -> TokenStream {
     }
 }
 
+/// Generates the methods to modify single elements of the collection or map field `field_ident`:
+/// append, replace and remove for collections, insert and remove for maps.
+///
+/// The modified collection goes through the field's validator, and the modification is
+/// reverted if it fails, before being assigned.
+fn gen_items_setters(
+    field_ident: &Ident,
+    ty: &Type,
+    kind: CollectionKind,
+    field_attr: &Attributes,
+) -> TokenStream {
+    let trait_path = kind.trait_path();
+    let set_item_ident = format_ident!("__set_{}_{}", field_ident, item_name(kind));
+    let remove_item_ident = format_ident!("__remove_{}_{}", field_ident, item_name(kind));
+    let commit_ident = format_ident!("__commit_{}", field_ident);
+    let apply_ident = format_ident!("__apply_{}", field_ident);
+    let not_found = not_found_error(field_ident);
+    let item_validator = if let Some(item_validator) = &field_attr.item_validator {
+        quote! { #item_validator(&value)?; }
+    } else {
+        quote! {}
+    };
+    let validator = if let Some(validator) = &field_attr.validator {
+        quote! {
+            let result: choices::ChoicesResult<()> = #validator(&value);
+            if let Err(err) = result {
+                #trait_path::undo(&mut value, undo);
+                self.#field_ident = value;
+                return Err(err);
+            }
+        }
+    } else {
+        quote! {}
+    };
+    // Map keys are often strings, hence they're accepted through `Into`, while sequence
+    // positions are taken as they are to allow integer literals.
+    let key_type = match kind {
+        CollectionKind::Collection => quote! { <#ty as #trait_path>::Key },
+        CollectionKind::Map => quote! { impl Into<<#ty as #trait_path>::Key> },
+    };
+    let add_methods = match kind {
+        CollectionKind::Collection => {
+            let push_ident = format_ident!("__push_{}", field_ident);
+            quote! {
+                #[doc(hidden)]
+                pub fn #push_ident(
+                    &mut self,
+                    value: impl Into<<#ty as #trait_path>::Item>,
+                ) -> choices::ChoicesResult<()> {
+                    let value = value.into();
+                    #item_validator
+                    let mut collection = std::mem::take(&mut self.#field_ident);
+                    let undo = #trait_path::add_item(&mut collection, value);
+                    self.#commit_ident(collection, undo)
+                }
+
+                #[doc(hidden)]
+                pub fn #set_item_ident(
+                    &mut self,
+                    key: <#ty as #trait_path>::Key,
+                    value: impl Into<<#ty as #trait_path>::Item>,
+                ) -> choices::ChoicesResult<()> {
+                    let value = value.into();
+                    #item_validator
+                    let mut collection = std::mem::take(&mut self.#field_ident);
+                    match #trait_path::replace_item(&mut collection, key, value) {
+                        Ok(undo) => self.#commit_ident(collection, undo),
+                        Err(_) => {
+                            self.#field_ident = collection;
+                            Err(#not_found)
+                        }
+                    }
+                }
+            }
+        }
+        CollectionKind::Map => quote! {
+            #[doc(hidden)]
+            pub fn #set_item_ident(
+                &mut self,
+                key: #key_type,
+                value: impl Into<<#ty as #trait_path>::Item>,
+            ) -> choices::ChoicesResult<()> {
+                let value = value.into();
+                #item_validator
+                let mut collection = std::mem::take(&mut self.#field_ident);
+                let undo = #trait_path::insert_item(&mut collection, key.into(), value);
+                self.#commit_ident(collection, undo)
+            }
+        },
+    };
+    let saving_methods = saving_items_setters(field_ident, ty, kind, &key_type);
+    quote! {
+        #saving_methods
+
+        #add_methods
+
+        #[doc(hidden)]
+        pub fn #remove_item_ident(
+            &mut self,
+            key: #key_type,
+        ) -> choices::ChoicesResult<()> {
+            let mut collection = std::mem::take(&mut self.#field_ident);
+            match #trait_path::remove_item(&mut collection, key.into()) {
+                Some(undo) => self.#commit_ident(collection, undo),
+                None => {
+                    self.#field_ident = collection;
+                    Err(#not_found)
+                }
+            }
+        }
+
+        #[allow(unused_mut)]
+        fn #commit_ident(
+            &mut self,
+            mut value: #ty,
+            undo: <#ty as #trait_path>::Undo,
+        ) -> choices::ChoicesResult<()> {
+            #validator
+            self.#apply_ident(value);
+            Ok(())
+        }
+    }
+}
+
+/// Generates the public methods modifying single elements of the field `field_ident`, which save
+/// the configuration after the hidden ones, used by the requests, modified it.
+fn saving_items_setters(
+    field_ident: &Ident,
+    ty: &Type,
+    kind: CollectionKind,
+    key_type: &TokenStream,
+) -> TokenStream {
+    let trait_path = kind.trait_path();
+    let item_type = quote! { impl Into<<#ty as #trait_path>::Item> };
+    let mut methods = vec![
+        (
+            format_ident!("set_{}_{}", field_ident, item_name(kind)),
+            quote! { key: #key_type, value: #item_type },
+            quote! { key, value },
+        ),
+        (
+            format_ident!("remove_{}_{}", field_ident, item_name(kind)),
+            quote! { key: #key_type },
+            quote! { key },
+        ),
+    ];
+    if kind == CollectionKind::Collection {
+        methods.push((
+            format_ident!("push_{}", field_ident),
+            quote! { value: #item_type },
+            quote! { value },
+        ));
+    }
+    let methods = methods.into_iter().map(|(ident, params, args)| {
+        let hidden_ident = format_ident!("__{}", ident);
+        quote! {
+            pub fn #ident(&mut self, #params) -> choices::ChoicesResult<()> {
+                self.#hidden_ident(#args)?;
+                self.__persist();
+                Ok(())
+            }
+        }
+    });
+    quote! {
+        #( #methods )*
+    }
+}
+
 /// Generates the Choices trait impl block.
-fn gen_trait(rw_lock: bool) -> TokenStream {
-    let run_mutable = gen_trait_run_mutable(rw_lock);
+fn gen_trait(root_path: &TokenStream, rw_lock: bool) -> TokenStream {
+    let run_mutable = gen_trait_run_mutable(root_path, rw_lock);
 
     quote! {
         async fn run<T: Into<std::net::SocketAddr> + Send>(&'static self, addr: T) {
-            let filter = create_filter!(self);
+            use choices::warp::Filter;
+            let root = choices::warp::path(#root_path).boxed();
+            let filter = create_filter!(self, root);
             choices::warp::serve(filter).run(addr).await
         }
 
+        fn __filter(&'static self) -> choices::server::ConfigFilter {
+            choices::server::boxed_filter(self.filter())
+        }
+
         #run_mutable
     }
 }
 
-/// Generates the trait fn implementation `run_mutable*`.
-fn gen_trait_run_mutable(rw_lock: bool) -> TokenStream {
+/// Generates the trait fn implementations `run_mutable*` and `__filter_mutable*`.
+fn gen_trait_run_mutable(root_path: &TokenStream, rw_lock: bool) -> TokenStream {
+    let filter = persisted_filter_mutable();
     macro_rules! def {
-        ($fn_name:ident, $ty:ty) => {{
+        ($fn_name:ident, $filter_fn_name:ident, $ty:ty) => {{
             quote! {
                 async fn $fn_name<T: Into<std::net::SocketAddr> + Send>(
                     choices: std::sync::Arc<$ty>,
                     addr: T,
                 ) {
-                    let filter = create_filter_mutable!(choices);
+                    use choices::warp::Filter;
+                    let root = choices::warp::path(#root_path).boxed();
+                    let filter = #filter;
                     choices::warp::serve(filter).run(addr).await
                 }
+
+                fn $filter_fn_name(choices: std::sync::Arc<$ty>) -> choices::server::ConfigFilter {
+                    choices::server::boxed_filter(Self::filter_mutable(choices))
+                }
             }
         }};
     }
 
     if rw_lock {
-        def!(run_mutable_rw, std::sync::RwLock<Self>)
+        def!(run_mutable_rw, __filter_mutable_rw, std::sync::RwLock<Self>)
     } else {
-        def!(run_mutable, std::sync::Mutex<Self>)
+        def!(run_mutable, __filter_mutable, std::sync::Mutex<Self>)
     }
 }
@@ -0,0 +1,135 @@
+//! Generation of the WebSocket resource, used to read, set and watch the fields interactively.
+//!
+//! The protocol is implemented by `choices::ws`, which requires the feature `json`.
+
+use crate::bulk::readable_fields;
+use crate::constants::WS_PATH;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{punctuated::Punctuated, token::Comma, *};
+
+/// Generates the hidden method returning the value of the field `name` as text, where fields of
+/// nested configurations are named `nested.name`.
+pub(crate) fn gen_value_text(fields: &Punctuated<Field, Comma>) -> TokenStream {
+    let readable_fields = readable_fields(fields);
+    let (nested, scalar): (Vec<_>, Vec<_>) = readable_fields
+        .iter()
+        .partition(|(_, _, field_attr)| field_attr.nested);
+    let scalar_arms = scalar.into_iter().map(|(field, name, _)| {
+        let field_ident = &field.ident;
+        quote! {
+            #name => Some(self.#field_ident.body_string()),
+        }
+    });
+    let nested_arms = nested.into_iter().map(|(field, name, _)| {
+        let field_ident = &field.ident;
+        quote! {
+            Some((#name, name)) => self.#field_ident.__value_text(name),
+        }
+    });
+    quote! {
+        /// Returns the value of the field `name`, or `None` if there's no such readable field.
+        #[doc(hidden)]
+        pub fn __value_text(&self, name: &str) -> Option<String> {
+            #[allow(unused_imports)]
+            use choices::ChoicesOutput;
+            match name {
+                #( #scalar_arms )*
+                _ => match name.split_once('.') {
+                    #( #nested_arms )*
+                    _ => None,
+                },
+            }
+        }
+    }
+}
+
+/// Generates the WebSocket resource, whose requests get and set the fields with the same
+/// validations and errors of the HTTP resources.
+///
+/// `read_access_pattern` and `write_access_pattern` represent the ways the configuration can be
+/// accessed.
+pub(crate) fn gen_ws_resource(
+    json: bool,
+    read_access_pattern: TokenStream,
+    write_access_pattern: TokenStream,
+) -> TokenStream {
+    let get = if json {
+        quote! {
+            match config.__values_json() {
+                Ok(values) => choices::ws::select(values, name)
+                    .ok_or_else(|| choices::ws::error(&choices::patch::unknown_field(name), None)),
+                Err(err) => Err(choices::ws::internal_error(err)),
+            }
+        }
+    } else {
+        quote! {
+            config
+                .__value_text(name)
+                .map(choices::serde_json::Value::String)
+                .ok_or_else(|| choices::ws::error(&choices::patch::unknown_field(name), None))
+        }
+    };
+    let set = if json {
+        quote! {
+            let patches = choices::patch::prepare_json(choices::ws::nest(name, value), |name, value| {
+                config.__prepare_json(name, value)
+            })
+            .map_err(|err| choices::ws::error(&err, None))?;
+            let config: &mut Self = &mut config;
+            for patch in patches {
+                patch(config);
+            }
+            Ok(())
+        }
+    } else {
+        quote! {
+            let value = choices::ws::text_value(value)?;
+            let patch = config
+                .__prepare_text(name, &value)
+                .map_err(|err| choices::ws::error(&err, Some(name)))?;
+            let config: &mut Self = &mut config;
+            patch(config);
+            Ok(())
+        }
+    };
+    quote! {
+        $root.clone()
+            .and(choices::warp::path(#WS_PATH))
+            .and(choices::warp::path::end())
+            .and(choices::warp::ws())
+            .and(choices::signature::verify(signature.clone(), #json))
+            .map(move |ws: choices::warp::ws::Ws| {
+                let get = {
+                    let choices = choices.clone();
+                    move |name: &str| match #read_access_pattern {
+                        Ok(config) => { #get }
+                        Err(err) => Err(choices::ws::internal_error(err)),
+                    }
+                };
+                let set = {
+                    let choices = choices.clone();
+                    move |name: &str, value: choices::serde_json::Value| {
+                        let result = match #write_access_pattern {
+                            #[allow(unused_mut)]
+                            Ok(mut config) => (|| { #set })(),
+                            Err(err) => Err(choices::ws::internal_error(err)),
+                        };
+                        // The file is written once the configuration is released.
+                        choices::persist::save_after(&choices, Self::__pending_save);
+                        result
+                    }
+                };
+                let subscribe = {
+                    let choices = choices.clone();
+                    move || match #read_access_pattern {
+                        Ok(config) => Ok(config.subscribe()),
+                        Err(err) => Err(choices::ws::internal_error(err)),
+                    }
+                };
+                ws.on_upgrade(move |socket| {
+                    choices::ws::serve(socket, #json, get, set, subscribe)
+                })
+            })
+    }
+}
@@ -0,0 +1,36 @@
+//! Require a bearer token to modify the configuration.
+
+use choices::auth::Bearer;
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+#[choices(auth = Bearer::new(token()))]
+struct Config {
+    debug: bool,
+    retries: u8,
+    state: choices::State,
+}
+
+/// Reads the token from the environment, so that it isn't part of the binary.
+fn token() -> String {
+    std::env::var("CONFIG_TOKEN").unwrap_or_else(|_| "secret".to_string())
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        debug: false,
+        retries: 3,
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // Reading needs no token: curl localhost:8081/config/retries
+    // Rejected with 401: curl -X PUT localhost:8081/config/retries -d "5"
+    // To change retries: curl -X PUT localhost:8081/config/retries -H "Authorization: Bearer secret" -d "5"
+}
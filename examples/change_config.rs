@@ -10,12 +10,14 @@ use std::sync::{Arc, Mutex};
 struct Config {
     port: u16,
     name: String,
+    state: choices::State,
 }
 
 lazy_static! {
     static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
         port: 10,
-        name: String::from("service")
+        name: String::from("service"),
+        state: Default::default(),
     }));
 }
 
@@ -0,0 +1,47 @@
+//! Access and modify single elements of collection fields.
+
+use choices::{Choices, ChoicesError, ChoicesResult};
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+struct Config {
+    #[choices(item_validator = check_host)]
+    allowed_hosts: Vec<String>,
+    state: choices::State,
+}
+
+fn check_host(host: &str) -> ChoicesResult<()> {
+    if host.is_empty() {
+        Err(ChoicesError::ValidationError(
+            "host can't be empty".to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        allowed_hosts: vec![String::from("localhost")],
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    // Elements can be modified programmatically too.
+    CONFIG
+        .lock()
+        .unwrap()
+        .push_allowed_hosts("127.0.0.1")
+        .unwrap();
+
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // View all the elements: curl localhost:8081/config/allowed_hosts
+    // View an element: curl localhost:8081/config/allowed_hosts/1
+    // Append an element: curl -X POST localhost:8081/config/allowed_hosts -d "example.com"
+    // Replace an element: curl -X PUT localhost:8081/config/allowed_hosts/0 -d "::1"
+    // Remove an element: curl -X DELETE localhost:8081/config/allowed_hosts/1
+}
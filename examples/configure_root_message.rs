@@ -9,10 +9,14 @@ use lazy_static::lazy_static;
 #[choices(message = "Welcome!")]
 struct Config {
     debug: bool,
+    state: choices::State,
 }
 
 lazy_static! {
-    static ref CONFIG: Config = Config { debug: false };
+    static ref CONFIG: Config = Config {
+        debug: false,
+        state: Default::default()
+    };
 }
 
 #[tokio::main]
@@ -15,17 +15,20 @@ use std::sync::{Arc, Mutex};
 #[derive(Choices)]
 struct Config {
     user: String,
+    state: choices::State,
 }
 
 lazy_static! {
     static ref CONFIG: Config = {
         Config {
             user: "Matt".to_string(),
+            state: Default::default(),
         }
     };
     static ref CONFIG2: Arc<Mutex<Config>> = {
         Arc::new(Mutex::new(Config {
             user: "David".to_string(),
+            state: Default::default(),
         }))
     };
 }
@@ -0,0 +1,39 @@
+//! Using enums as configuration types.
+
+// Run the example with `cargo run --example enum_type`
+
+use choices::{Choices, ChoicesValue};
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+// Derive ChoicesValue to parse and print the enum's variants by name.
+#[derive(ChoicesValue)]
+enum LogFormat {
+    // Variants can be renamed.
+    #[choices(rename = "json")]
+    Json,
+    #[choices(rename = "text")]
+    Text,
+}
+
+#[derive(Choices)]
+struct Config {
+    log_format: LogFormat,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        log_format: LogFormat::Text,
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // See the allowed variants in the index: curl localhost:8081/config
+    // View the value: curl localhost:8081/config/log_format
+    // Set the value: curl -X PUT localhost:8081/config/log_format -d "json"
+}
@@ -0,0 +1,37 @@
+//! Load the configuration from environment variables at startup.
+
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+#[choices(env_prefix = "APP")]
+struct Config {
+    debug: bool,
+    retries: u8,
+    state: choices::State,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            debug: false,
+            retries: 3,
+            state: Default::default(),
+        }
+    }
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(
+        Config::from_env().expect("invalid environment variables")
+    ));
+}
+
+#[tokio::main]
+async fn main() {
+    // Run with: APP_DEBUG=true APP_RETRIES=5 cargo run --example env
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // To check the values: curl localhost:8081/config/retries
+}
@@ -0,0 +1,28 @@
+//! Stream the changes of the configuration fields.
+
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+struct Config {
+    debug: bool,
+    port: u16,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        debug: false,
+        port: 8080,
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // Listen to the changes: curl -N localhost:8081/config/_events
+    // Change a field in another terminal: curl -X PUT localhost:8081/config/port -d "80"
+}
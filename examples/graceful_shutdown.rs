@@ -0,0 +1,38 @@
+//! Run the configuration server in the background and stop it gracefully.
+
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+struct Config {
+    debug: bool,
+    stop: bool,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        debug: false,
+        stop: false,
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    // Bind to any free port.
+    let server = CONFIG.spawn((std::net::Ipv4Addr::LOCALHOST, 0));
+    println!("serving the configuration at {}", server.addr());
+
+    // Stop the server once the field `stop` is set to true.
+    let mut stop = CONFIG.lock().unwrap().subscribe_stop();
+    while !*stop.borrow_and_update() {
+        stop.changed().await.unwrap();
+    }
+    server.shutdown();
+    server.join().await;
+    println!("server stopped");
+
+    // To stop the server: curl -X PUT localhost:<port>/config/stop -d "true"
+}
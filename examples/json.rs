@@ -11,12 +11,14 @@ use std::sync::{Arc, Mutex};
 struct Config {
     port: u16,
     files: Vec<String>,
+    state: choices::State,
 }
 
 lazy_static! {
     static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
         port: 10,
-        files: vec![String::from("tmp.txt"), String::from("log.txt")]
+        files: vec![String::from("tmp.txt"), String::from("log.txt")],
+        state: Default::default(),
     }));
 }
 
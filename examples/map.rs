@@ -0,0 +1,36 @@
+//! Access and modify single entries of map fields.
+
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+struct Config {
+    tenant_limits: HashMap<String, u32>,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        tenant_limits: HashMap::new(),
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    // Entries can be modified programmatically too.
+    CONFIG
+        .lock()
+        .unwrap()
+        .set_tenant_limits_entry("acme", 100u32)
+        .unwrap();
+
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // List the keys: curl localhost:8081/config/tenant_limits
+    // View an entry: curl localhost:8081/config/tenant_limits/acme
+    // Insert or replace an entry: curl -X PUT localhost:8081/config/tenant_limits/globex -d "50"
+    // Remove an entry: curl -X DELETE localhost:8081/config/tenant_limits/acme
+}
@@ -0,0 +1,45 @@
+//! Group configuration fields into nested structs.
+
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+struct DatabaseConfig {
+    host: String,
+    port: u16,
+    state: choices::State,
+}
+
+#[derive(Choices)]
+struct Config {
+    debug: bool,
+    // The nested configuration type must derive `Choices` as well.
+    #[choices(nested)]
+    db: DatabaseConfig,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        debug: false,
+        db: DatabaseConfig {
+            host: String::from("localhost"),
+            port: 5432,
+            state: Default::default(),
+        },
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    // Nested setters are reachable from the parent configuration.
+    CONFIG.lock().unwrap().db.set_port(5433u16).unwrap();
+
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // View the nested configuration's index: curl localhost:8081/config/db
+    // View a nested value: curl localhost:8081/config/db/host
+    // Change a nested value: curl -X PUT localhost:8081/config/db/port -d "6543"
+}
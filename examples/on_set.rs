@@ -17,6 +17,7 @@ struct Config {
     user: String,
     #[choices(skip)]
     file_callback: Box<dyn Fn(&String) + Send + Sync>,
+    state: choices::State,
 }
 
 lazy_static! {
@@ -25,6 +26,7 @@ lazy_static! {
         file: String::from("tmp"),
         user: String::from("tom"),
         file_callback: Box::new(|_| {}),
+        state: Default::default(),
     }));
 }
 
@@ -0,0 +1,36 @@
+//! Restore the configuration from a file at startup and save it after every change.
+
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+#[choices(persist = "persist_example.conf")]
+struct Config {
+    debug: bool,
+    retries: u8,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        debug: false,
+        retries: 3,
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    // Loads the values saved by a previous run, if any.
+    CONFIG
+        .lock()
+        .unwrap()
+        .restore()
+        .expect("invalid configuration file");
+
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // To change retries and save it: curl -X PUT localhost:8081/config/retries -d "5"
+    // Then: cat persist_example.conf
+}
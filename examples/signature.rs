@@ -0,0 +1,44 @@
+//! Accept only the modifications signed with a shared key.
+
+use choices::signature::Signature;
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+#[choices(signature = Signature::new(key()))]
+struct Config {
+    debug: bool,
+    retries: u8,
+    state: choices::State,
+}
+
+/// Reads the shared key from the environment, so that it isn't part of the binary.
+fn key() -> String {
+    std::env::var("CONFIG_KEY").unwrap_or_else(|_| "secret".to_string())
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        debug: false,
+        retries: 3,
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    // Prints the headers signing a request to change retries.
+    let headers = Signature::new(key()).headers("PUT", "/config/retries", "first", b"5");
+    for (name, value) in headers {
+        println!("{}: {}", name, value);
+    }
+
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // Reading needs no signature: curl localhost:8081/config/retries
+    // Rejected with 401: curl -X PUT localhost:8081/config/retries -d "5"
+    // To change retries, within 5 minutes:
+    // curl -X PUT localhost:8081/config/retries -d "5" -H "x-choices-timestamp: ..." \
+    //     -H "x-choices-nonce: first" -H "x-choices-signature: ..."
+}
@@ -15,16 +15,17 @@ struct Config {
     retries: u8,
     delay: f64,
     score: Option<i32>,
+    state: choices::State,
 }
 
 #[tokio::main]
 async fn main() {
-    Config {
+    static CONFIG: Config = Config {
         debug: true,
         retries: 3,
         delay: 0.1,
         score: Some(3),
-    }
-    .run((std::net::Ipv4Addr::LOCALHOST, 8081))
-    .await;
+        state: choices::State::new(),
+    };
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
 }
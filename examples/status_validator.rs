@@ -0,0 +1,58 @@
+//! Reply to the values rejected by validators with the HTTP status of your choice.
+
+use choices::{Choices, ChoicesError, ChoicesResult};
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+struct Config {
+    #[choices(validator = check_port)]
+    port: u16,
+    #[choices(validator = check_upstream)]
+    upstream: String,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        port: 10000,
+        upstream: String::from("127.0.0.1:9000"),
+        state: Default::default(),
+    }));
+}
+
+/// Ports used by other services.
+const TAKEN_PORTS: [u16; 2] = [8080, 8081];
+
+fn check_port(port: &u16) -> ChoicesResult<()> {
+    if TAKEN_PORTS.contains(port) {
+        // Replies `409 Conflict` in place of `400 Bad Request`.
+        Err(ChoicesError::ValidationError(format!("port {} is taken", port)).with_status(409))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_upstream(upstream: &str) -> ChoicesResult<()> {
+    // Wraps the parsing error, replying `422 Unprocessable Entity`.
+    upstream
+        .parse::<std::net::SocketAddr>()
+        .map(|_| ())
+        .map_err(|err| ChoicesError::custom(err).with_status(422))
+}
+
+#[tokio::main]
+async fn main() {
+    let err = CONFIG.lock().unwrap().set_port(8080u16).unwrap_err();
+    assert_eq!(err.status(), Some(409));
+    CONFIG
+        .lock()
+        .unwrap()
+        .set_upstream("127.0.0.1:9001")
+        .unwrap();
+
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // To get a 409: curl -i -X PUT localhost:8081/config/port -d "8080"
+    // To get a 422: curl -i -X PUT localhost:8081/config/upstream -d "localhost"
+}
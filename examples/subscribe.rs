@@ -0,0 +1,44 @@
+//! React to configuration changes from other tasks.
+
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+#[derive(Choices)]
+struct Config {
+    debug: bool,
+    log_level: String,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        debug: false,
+        log_level: "info".to_string(),
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    let mut log_level = CONFIG.lock().unwrap().subscribe_log_level();
+    tokio::spawn(async move {
+        while log_level.changed().await.is_ok() {
+            println!("new log level: {}", *log_level.borrow_and_update());
+        }
+    });
+
+    let mut changes = CONFIG.lock().unwrap().subscribe();
+    tokio::spawn(async move {
+        while let Some(event) = changes.recv().await {
+            println!(
+                "{} = {} (revision {})",
+                event.field, event.value, event.revision
+            );
+        }
+    });
+
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // Change a field: curl -X PUT localhost:8081/config/log_level -d "debug"
+}
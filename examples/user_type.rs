@@ -40,6 +40,7 @@ impl ChoicesOutput for TimePoint {
 #[derive(Choices, Default)]
 struct Config {
     time_point: TimePoint,
+    state: choices::State,
 }
 
 lazy_static! {
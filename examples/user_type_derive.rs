@@ -0,0 +1,49 @@
+//! Deriving the traits needed by your own configuration types.
+
+// Run the example with `cargo run --example user_type_derive`
+
+use choices::{Choices, ChoicesValue};
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+// Fields are parsed and printed in order, separated by the delimiter.
+#[derive(ChoicesValue, Default)]
+#[choices(delimiter = ".")]
+struct TimePoint {
+    years: u32,
+    seconds: u32,
+}
+
+// Enum variants are preceded by their name.
+#[derive(ChoicesValue)]
+#[choices(delimiter = ",")]
+enum Retry {
+    #[choices(rename = "never")]
+    Never,
+    #[choices(rename = "fixed")]
+    Fixed { attempts: u8, delay_ms: u32 },
+}
+
+#[derive(Choices)]
+struct Config {
+    time_point: TimePoint,
+    retry: Retry,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        time_point: TimePoint::default(),
+        retry: Retry::Never,
+        state: Default::default(),
+    }));
+}
+
+#[tokio::main]
+async fn main() {
+    CONFIG.run((std::net::Ipv4Addr::LOCALHOST, 8081)).await;
+
+    // View the value: curl localhost:8081/config/time_point
+    // Set the value: curl -X PUT localhost:8081/config/time_point -d "42.1"
+    // Set an enum value: curl -X PUT localhost:8081/config/retry -d "fixed,3,500"
+}
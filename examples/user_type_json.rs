@@ -17,6 +17,7 @@ struct TimePoint {
 #[choices(json)]
 struct Config {
     time_point: TimePoint,
+    state: choices::State,
 }
 
 lazy_static! {
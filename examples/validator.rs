@@ -16,6 +16,7 @@ struct Config {
     file: String,
     #[choices(skip)]
     file_validator: Box<FileValidatorFn>,
+    state: choices::State,
 }
 
 lazy_static! {
@@ -23,6 +24,7 @@ lazy_static! {
         port: 10000,
         file: String::from("tmp"),
         file_validator: Box::new(|_| { Ok(()) }),
+        state: Default::default(),
     }));
 }
 
@@ -0,0 +1,137 @@
+//! Access to configuration objects shared between threads.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Guard granting read access to a configuration object.
+pub type ReadGuard<'a, T> = Box<dyn Deref<Target = T> + 'a>;
+
+/// Guard granting write access to a configuration object.
+pub type WriteGuard<'a, T> = Box<dyn DerefMut<Target = T> + 'a>;
+
+/// Trait to lock a configuration object held in a shared container.
+///
+/// It's implemented for `Arc<Mutex<T>>`, `Arc<RwLock<T>>` and for nested configurations
+/// accessed through the lock of their parent.
+pub trait ChoicesAccess<T>: Clone + Send + Sync + 'static {
+    /// Locks the configuration object for reading.
+    fn read_access(&self) -> Result<ReadGuard<'_, T>, String>;
+
+    /// Locks the configuration object for writing.
+    fn write_access(&self) -> Result<WriteGuard<'_, T>, String>;
+}
+
+impl<T: Send + 'static> ChoicesAccess<T> for Arc<Mutex<T>> {
+    fn read_access(&self) -> Result<ReadGuard<'_, T>, String> {
+        match self.lock() {
+            Ok(guard) => Ok(Box::new(guard)),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    fn write_access(&self) -> Result<WriteGuard<'_, T>, String> {
+        match self.lock() {
+            Ok(guard) => Ok(Box::new(guard)),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> ChoicesAccess<T> for Arc<RwLock<T>> {
+    fn read_access(&self) -> Result<ReadGuard<'_, T>, String> {
+        match self.read() {
+            Ok(guard) => Ok(Box::new(guard)),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    fn write_access(&self) -> Result<WriteGuard<'_, T>, String> {
+        match self.write() {
+            Ok(guard) => Ok(Box::new(guard)),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+/// Access to a nested configuration object of type `T` through the lock of its parent `P`.
+pub struct NestedAccess<A, P, T> {
+    parent: A,
+    get: fn(&P) -> &T,
+    get_mut: fn(&mut P) -> &mut T,
+}
+
+impl<A, P, T> NestedAccess<A, P, T> {
+    /// Creates a new `NestedAccess`.
+    ///
+    /// `get` and `get_mut` must return the nested object from its parent.
+    pub fn new(parent: A, get: fn(&P) -> &T, get_mut: fn(&mut P) -> &mut T) -> Self {
+        Self {
+            parent,
+            get,
+            get_mut,
+        }
+    }
+}
+
+impl<A: Clone, P, T> Clone for NestedAccess<A, P, T> {
+    fn clone(&self) -> Self {
+        Self::new(self.parent.clone(), self.get, self.get_mut)
+    }
+}
+
+impl<A, P, T> ChoicesAccess<T> for NestedAccess<A, P, T>
+where
+    A: ChoicesAccess<P>,
+    P: 'static,
+    T: 'static,
+{
+    fn read_access(&self) -> Result<ReadGuard<'_, T>, String> {
+        let guard = self.parent.read_access()?;
+        Ok(Box::new(NestedReadGuard {
+            guard,
+            get: self.get,
+        }))
+    }
+
+    fn write_access(&self) -> Result<WriteGuard<'_, T>, String> {
+        let guard = self.parent.write_access()?;
+        Ok(Box::new(NestedWriteGuard {
+            guard,
+            get: self.get,
+            get_mut: self.get_mut,
+        }))
+    }
+}
+
+struct NestedReadGuard<'a, P, T> {
+    guard: ReadGuard<'a, P>,
+    get: fn(&P) -> &T,
+}
+
+impl<P, T> Deref for NestedReadGuard<'_, P, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        (self.get)(&self.guard)
+    }
+}
+
+struct NestedWriteGuard<'a, P, T> {
+    guard: WriteGuard<'a, P>,
+    get: fn(&P) -> &T,
+    get_mut: fn(&mut P) -> &mut T,
+}
+
+impl<P, T> Deref for NestedWriteGuard<'_, P, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        (self.get)(&self.guard)
+    }
+}
+
+impl<P, T> DerefMut for NestedWriteGuard<'_, P, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        (self.get_mut)(&mut self.guard)
+    }
+}
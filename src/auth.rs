@@ -0,0 +1,426 @@
+//! Authentication of requests.
+//!
+//! Configurations with `#[choices(auth = expr)]` authenticate the requests modifying them, and
+//! with `#[choices(auth_get)]` the requests reading them as well, through the `Authenticate`
+//! value returned by `expr`. Requests which fail the authentication are replied with
+//! `401 Unauthorized`, carrying the `WWW-Authenticate` challenge, or with `403 Forbidden`.
+//!
+//! Fields with `#[choices(read_role = "...")]` or `#[choices(write_role = "...")]` can be read or
+//! modified only by the callers holding the role, as told by the `Identify` value returned by
+//! the expression of `#[choices(identity = expr)]`.
+
+use crate::error::{ChoicesError, ChoicesResult};
+use crate::negotiate::{error_reply, Format};
+use std::convert::Infallible;
+use std::sync::Arc;
+use warp::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Realm of the challenges of the built-in authentications.
+pub const REALM: &str = "choices";
+
+/// Authentication of the requests of a configuration.
+///
+/// Implemented by `Bearer`, `Basic` and by closures taking the headers of the request.
+pub trait Authenticate: Send + Sync {
+    /// Checks the credentials carried by the `headers` of a request.
+    ///
+    /// Requests failing with `ChoicesError::Forbidden` are replied with `403 Forbidden`, all
+    /// other errors with `401 Unauthorized`, unless they request another status.
+    fn authenticate(&self, headers: &HeaderMap) -> ChoicesResult<()>;
+
+    /// Returns the challenge sent in the `WWW-Authenticate` header of `401 Unauthorized` replies.
+    fn challenge(&self) -> String {
+        format!("Bearer realm=\"{}\"", REALM)
+    }
+}
+
+impl<F> Authenticate for F
+where
+    F: Fn(&HeaderMap) -> ChoicesResult<()> + Send + Sync,
+{
+    fn authenticate(&self, headers: &HeaderMap) -> ChoicesResult<()> {
+        self(headers)
+    }
+}
+
+/// Authentication through a bearer token, sent as `Authorization: Bearer <token>`.
+pub struct Bearer {
+    token: String,
+}
+
+impl Bearer {
+    /// Returns the authentication accepting only the token `token`.
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+        }
+    }
+}
+
+impl Authenticate for Bearer {
+    fn authenticate(&self, headers: &HeaderMap) -> ChoicesResult<()> {
+        match credentials(headers, "Bearer") {
+            Some(token) if constant_time_eq(token.as_bytes(), self.token.as_bytes()) => Ok(()),
+            Some(_) => Err(ChoicesError::Unauthorized("invalid token".to_string())),
+            None => Err(ChoicesError::Unauthorized("missing token".to_string())),
+        }
+    }
+}
+
+/// Authentication through HTTP Basic, sent as `Authorization: Basic <base64(user:password)>`.
+pub struct Basic {
+    user: String,
+    password: String,
+}
+
+impl Basic {
+    /// Returns the authentication accepting only the user `user` with the password `password`.
+    pub fn new(user: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            user: user.into(),
+            password: password.into(),
+        }
+    }
+}
+
+impl Authenticate for Basic {
+    fn authenticate(&self, headers: &HeaderMap) -> ChoicesResult<()> {
+        use base64::Engine;
+
+        let credentials = credentials(headers, "Basic")
+            .ok_or_else(|| ChoicesError::Unauthorized("missing credentials".to_string()))?;
+        let credentials = base64::engine::general_purpose::STANDARD
+            .decode(credentials)
+            .ok()
+            .and_then(|credentials| String::from_utf8(credentials).ok())
+            .ok_or_else(|| ChoicesError::Unauthorized("malformed credentials".to_string()))?;
+        let (user, password) = credentials.split_once(':').unwrap_or((&credentials, ""));
+        // Both are compared, so that the time doesn't tell which one is wrong.
+        let user_ok = constant_time_eq(user.as_bytes(), self.user.as_bytes());
+        let password_ok = constant_time_eq(password.as_bytes(), self.password.as_bytes());
+        if user_ok & password_ok {
+            Ok(())
+        } else {
+            Err(ChoicesError::Unauthorized(
+                "invalid credentials".to_string(),
+            ))
+        }
+    }
+
+    fn challenge(&self) -> String {
+        format!("Basic realm=\"{}\", charset=\"UTF-8\"", REALM)
+    }
+}
+
+/// Identity of the caller of a request, i.e. the roles it holds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identity {
+    roles: Vec<String>,
+}
+
+impl Identity {
+    /// Returns the identity holding the roles `roles`.
+    pub fn new<I, S>(roles: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            roles: roles.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns the identity of anonymous callers, which hold no role.
+    pub fn anonymous() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if the identity holds the role `role`.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|held| held == role)
+    }
+
+    /// Returns the roles held by the identity.
+    pub fn roles(&self) -> &[String] {
+        &self.roles
+    }
+}
+
+/// Extraction of the identity of the caller of a request, whose roles are checked against the
+/// roles of the fields.
+///
+/// Implemented by `Tokens` and by closures taking the headers of the request.
+pub trait Identify: Send + Sync {
+    /// Returns the identity of the caller of a request with the headers `headers`.
+    fn identify(&self, headers: &HeaderMap) -> Identity;
+}
+
+impl<F> Identify for F
+where
+    F: Fn(&HeaderMap) -> Identity + Send + Sync,
+{
+    fn identify(&self, headers: &HeaderMap) -> Identity {
+        self(headers)
+    }
+}
+
+/// Authentication through bearer tokens, sent as `Authorization: Bearer <token>`, each granting
+/// its roles to the caller.
+#[derive(Default)]
+pub struct Tokens {
+    tokens: Vec<(String, Identity)>,
+}
+
+impl Tokens {
+    /// Returns the authentication accepting no token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts the token `token`, granting the roles `roles`.
+    pub fn token<I, S>(mut self, token: impl Into<String>, roles: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tokens.push((token.into(), Identity::new(roles)));
+        self
+    }
+
+    /// Returns the identity of the token of `headers`.
+    fn find(&self, headers: &HeaderMap) -> ChoicesResult<&Identity> {
+        let token = credentials(headers, "Bearer")
+            .ok_or_else(|| ChoicesError::Unauthorized("missing token".to_string()))?;
+        // All tokens are compared, so that the time doesn't tell which one is close.
+        self.tokens
+            .iter()
+            .fold(None, |found, (accepted, identity)| {
+                match constant_time_eq(token.as_bytes(), accepted.as_bytes()) {
+                    true => Some(identity),
+                    false => found,
+                }
+            })
+            .ok_or_else(|| ChoicesError::Unauthorized("invalid token".to_string()))
+    }
+}
+
+impl Authenticate for Tokens {
+    fn authenticate(&self, headers: &HeaderMap) -> ChoicesResult<()> {
+        self.find(headers).map(|_| ())
+    }
+}
+
+impl Identify for Tokens {
+    fn identify(&self, headers: &HeaderMap) -> Identity {
+        self.find(headers).cloned().unwrap_or_default()
+    }
+}
+
+/// Returns the credentials of the `Authorization` header of `headers` if they use `scheme`.
+fn credentials<'a>(headers: &'a HeaderMap, scheme: &str) -> Option<&'a str> {
+    let authorization = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (request_scheme, credentials) = authorization.split_once(' ')?;
+    if request_scheme.eq_ignore_ascii_case(scheme) {
+        Some(credentials.trim())
+    } else {
+        None
+    }
+}
+
+/// Compares `a` and `b` in a time which depends only on their lengths.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Returns true if the request of method `method` with the headers `headers` only reads the
+/// configuration. WebSocket upgrades aren't reads, since the socket can set fields.
+fn is_read(method: &Method, headers: &HeaderMap) -> bool {
+    let websocket = headers
+        .get(header::UPGRADE)
+        .and_then(|upgrade| upgrade.to_str().ok())
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+    (method == Method::GET || method == Method::HEAD) && !websocket
+}
+
+/// Returns the filter replying to the requests failing the authentication `auth`, which rejects
+/// the other requests as not found so that they reach the configuration's filter. Reads are
+/// authenticated only if `reads` is true, and preflight requests are never authenticated.
+/// `json` tells the format of the configuration.
+#[doc(hidden)]
+pub fn gate(
+    auth: Arc<dyn Authenticate>,
+    reads: bool,
+    json: bool,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::header::headers_cloned())
+        .and(crate::negotiate::accept(json))
+        .and_then(move |method: Method, headers: HeaderMap, format: Format| {
+            let auth = auth.clone();
+            async move {
+                if method == Method::OPTIONS || (!reads && is_read(&method, &headers)) {
+                    return Err(warp::reject::not_found());
+                }
+                match auth.authenticate(&headers) {
+                    Ok(()) => Err(warp::reject::not_found()),
+                    Err(err) => Ok(failure_reply(auth.as_ref(), format, &err)),
+                }
+            }
+        })
+}
+
+/// Returns the filter extracting the identity of the caller through `identify`.
+#[doc(hidden)]
+pub fn identity(
+    identify: Arc<dyn Identify>,
+) -> impl Filter<Extract = (Identity,), Error = Infallible> + Clone {
+    warp::header::headers_cloned().map(move |headers: HeaderMap| identify.identify(&headers))
+}
+
+/// Returns the filter replying `403 Forbidden` to the reads whose caller, identified through
+/// `identify`, lacks one of `read_roles` and to the other requests whose caller lacks one of
+/// `write_roles`. Without `identify`, callers are anonymous. It rejects the allowed requests as
+/// not found, so that they reach the configuration's filter. `field` is the name of the field
+/// protected by the roles, if any, and `json` tells the format of the configuration.
+#[doc(hidden)]
+pub fn roles_gate(
+    identify: Option<Arc<dyn Identify>>,
+    read_roles: Vec<&'static str>,
+    write_roles: Vec<&'static str>,
+    field: Option<&'static str>,
+    json: bool,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let read_roles: Arc<[&'static str]> = read_roles.into();
+    let write_roles: Arc<[&'static str]> = write_roles.into();
+    warp::method()
+        .and(warp::header::headers_cloned())
+        .and(crate::negotiate::accept(json))
+        .and_then(move |method: Method, headers: HeaderMap, format: Format| {
+            let identify = identify.clone();
+            let roles = match method {
+                Method::OPTIONS => None,
+                _ if is_read(&method, &headers) => Some(read_roles.clone()),
+                _ => Some(write_roles.clone()),
+            };
+            async move {
+                let roles = roles.ok_or_else(warp::reject::not_found)?;
+                let identity = identify
+                    .map_or_else(Identity::anonymous, |identify| identify.identify(&headers));
+                match roles.iter().find(|role| !identity.has_role(role)) {
+                    Some(role) => {
+                        let err =
+                            ChoicesError::Forbidden(format!("the role `{}` is required", role));
+                        Ok(
+                            error_reply(format, &err, field, None, StatusCode::FORBIDDEN)
+                                .into_response(),
+                        )
+                    }
+                    None => Err(warp::reject::not_found()),
+                }
+            }
+        })
+}
+
+/// Returns the reply of the request which failed the authentication `auth` with the error `err`.
+fn failure_reply(auth: &dyn Authenticate, format: Format, err: &ChoicesError) -> Response {
+    let status = match err.kind() {
+        ChoicesError::Forbidden(_) => StatusCode::FORBIDDEN,
+        _ => StatusCode::UNAUTHORIZED,
+    };
+    let mut response = error_reply(format, err, None, None, status).into_response();
+    if response.status() == StatusCode::UNAUTHORIZED {
+        if let Ok(challenge) = HeaderValue::from_str(&auth.challenge()) {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, challenge);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(authorization).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn bearer() {
+        let auth = Bearer::new("secret");
+        assert!(auth.authenticate(&headers("Bearer secret")).is_ok());
+        assert!(auth.authenticate(&headers("bearer secret")).is_ok());
+        assert_eq!(
+            auth.authenticate(&headers("Bearer secre")),
+            Err(ChoicesError::Unauthorized("invalid token".to_string()))
+        );
+        assert_eq!(
+            auth.authenticate(&HeaderMap::new()),
+            Err(ChoicesError::Unauthorized("missing token".to_string()))
+        );
+        assert!(auth.authenticate(&headers("Basic secret")).is_err());
+    }
+
+    #[test]
+    fn basic() {
+        let auth = Basic::new("admin", "pa:ss");
+        // admin:pa:ss
+        assert!(auth
+            .authenticate(&headers("Basic YWRtaW46cGE6c3M="))
+            .is_ok());
+        // admin:pass
+        assert_eq!(
+            auth.authenticate(&headers("Basic YWRtaW46cGFzcw==")),
+            Err(ChoicesError::Unauthorized(
+                "invalid credentials".to_string()
+            ))
+        );
+        assert_eq!(
+            auth.authenticate(&headers("Basic !")),
+            Err(ChoicesError::Unauthorized(
+                "malformed credentials".to_string()
+            ))
+        );
+        assert_eq!(
+            auth.challenge(),
+            "Basic realm=\"choices\", charset=\"UTF-8\""
+        );
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = Tokens::new()
+            .token("ops-token", ["ops"])
+            .token("admin-token", ["ops", "admin"]);
+        assert!(tokens.authenticate(&headers("Bearer ops-token")).is_ok());
+        assert!(tokens.authenticate(&headers("Bearer other")).is_err());
+        assert_eq!(
+            tokens.identify(&headers("Bearer admin-token")),
+            Identity::new(["ops", "admin"])
+        );
+        assert_eq!(tokens.identify(&HeaderMap::new()), Identity::anonymous());
+        assert!(tokens
+            .identify(&headers("Bearer ops-token"))
+            .has_role("ops"));
+        assert!(!tokens
+            .identify(&headers("Bearer ops-token"))
+            .has_role("admin"));
+    }
+
+    #[test]
+    fn reads() {
+        let mut headers = HeaderMap::new();
+        assert!(is_read(&Method::GET, &headers));
+        assert!(!is_read(&Method::PUT, &headers));
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        assert!(!is_read(&Method::GET, &headers));
+    }
+}
@@ -0,0 +1,348 @@
+//! Collections and maps whose elements can be accessed individually.
+
+use crate::bytes::Bytes;
+use crate::ChoicesInput;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash};
+
+/// Returns the key of an element from `segment`, a path segment percent-encoded as in URIs,
+/// parsed by `parse_key`. Segments which aren't UTF-8 once decoded have no key.
+#[doc(hidden)]
+pub fn segment_key<K>(segment: &str, parse_key: impl FnOnce(&str) -> Option<K>) -> Option<K> {
+    parse_key(
+        &percent_encoding::percent_decode_str(segment)
+            .decode_utf8()
+            .ok()?,
+    )
+}
+
+/// Trait for collection types whose elements are exposed as HTTP resources.
+///
+/// Modifications return an `Undo` that restores the collection's previous state, used to roll
+/// back changes rejected by a validator.
+pub trait ChoicesCollection: Default {
+    /// Type of the elements.
+    type Item;
+    /// Type of the keys identifying an element.
+    type Key;
+    /// Information needed to revert a modification.
+    type Undo;
+
+    /// Parses a key from a path segment.
+    fn parse_key(segment: &str) -> Option<Self::Key>;
+
+    /// Returns the element identified by `key`, if any.
+    fn get_item(&self, key: &Self::Key) -> Option<&Self::Item>;
+
+    /// Adds a new element to the collection.
+    fn add_item(&mut self, item: Self::Item) -> Self::Undo;
+
+    /// Replaces the element identified by `key` with `item`.
+    ///
+    /// Gives back `item` if there's no such element.
+    fn replace_item(&mut self, key: Self::Key, item: Self::Item) -> Result<Self::Undo, Self::Item>;
+
+    /// Removes the element identified by `key`, if any.
+    fn remove_item(&mut self, key: Self::Key) -> Option<Self::Undo>;
+
+    /// Reverts a modification.
+    fn undo(&mut self, undo: Self::Undo);
+}
+
+/// Undo of a modification to a sequence.
+#[doc(hidden)]
+pub enum SequenceUndo<T> {
+    Pushed,
+    Replaced(usize, T),
+    Removed(usize, T),
+}
+
+/// Implements `ChoicesCollection` for sequences indexed by position.
+macro_rules! sequence_collection_impl {
+    ($ty:ident, $push:ident, $pop:ident) => {
+        impl<T> ChoicesCollection for $ty<T> {
+            type Item = T;
+            type Key = usize;
+            type Undo = SequenceUndo<T>;
+
+            fn parse_key(segment: &str) -> Option<usize> {
+                segment.parse().ok()
+            }
+
+            fn get_item(&self, key: &usize) -> Option<&T> {
+                self.get(*key)
+            }
+
+            fn add_item(&mut self, item: T) -> Self::Undo {
+                self.$push(item);
+                SequenceUndo::Pushed
+            }
+
+            fn replace_item(&mut self, key: usize, item: T) -> Result<Self::Undo, T> {
+                match self.get_mut(key) {
+                    Some(old) => Ok(SequenceUndo::Replaced(key, std::mem::replace(old, item))),
+                    None => Err(item),
+                }
+            }
+
+            fn remove_item(&mut self, key: usize) -> Option<Self::Undo> {
+                if key < self.len() {
+                    self.drain(key..key + 1)
+                        .next()
+                        .map(|item| SequenceUndo::Removed(key, item))
+                } else {
+                    None
+                }
+            }
+
+            fn undo(&mut self, undo: Self::Undo) {
+                match undo {
+                    SequenceUndo::Pushed => {
+                        self.$pop();
+                    }
+                    SequenceUndo::Replaced(key, item) => self[key] = item,
+                    SequenceUndo::Removed(key, item) => self.insert(key, item),
+                }
+            }
+        }
+    };
+}
+
+sequence_collection_impl! {Vec, push, pop}
+sequence_collection_impl! {VecDeque, push_back, pop_back}
+
+/// Undo of a modification to a set.
+#[doc(hidden)]
+pub enum SetUndo<T> {
+    Inserted(Option<T>),
+    Replaced(T, Option<T>),
+    Removed(T),
+}
+
+impl<T, S> ChoicesCollection for HashSet<T, S>
+where
+    T: Clone + Eq + Hash + for<'a> ChoicesInput<'a>,
+    S: BuildHasher + Default,
+{
+    type Item = T;
+    type Key = T;
+    type Undo = SetUndo<T>;
+
+    fn parse_key(segment: &str) -> Option<T> {
+        T::from_chars(&Bytes::copy_from_slice(segment.as_bytes())).ok()
+    }
+
+    fn get_item(&self, key: &T) -> Option<&T> {
+        self.get(key)
+    }
+
+    fn add_item(&mut self, item: T) -> Self::Undo {
+        SetUndo::Inserted(self.insert_new(item))
+    }
+
+    fn replace_item(&mut self, key: T, item: T) -> Result<Self::Undo, T> {
+        match self.take(&key) {
+            Some(old) => Ok(SetUndo::Replaced(old, self.insert_new(item))),
+            None => Err(item),
+        }
+    }
+
+    fn remove_item(&mut self, key: T) -> Option<Self::Undo> {
+        self.take(&key).map(SetUndo::Removed)
+    }
+
+    fn undo(&mut self, undo: Self::Undo) {
+        match undo {
+            SetUndo::Inserted(inserted) => self.remove_inserted(inserted),
+            SetUndo::Replaced(old, inserted) => {
+                self.remove_inserted(inserted);
+                self.insert(old);
+            }
+            SetUndo::Removed(old) => {
+                self.insert(old);
+            }
+        }
+    }
+}
+
+/// Helpers to insert elements in a set and take them back out.
+trait InsertNew<T> {
+    /// Inserts `item`, returning a copy of it if it wasn't already present.
+    fn insert_new(&mut self, item: T) -> Option<T>;
+
+    /// Removes an element previously inserted by `insert_new`.
+    fn remove_inserted(&mut self, inserted: Option<T>);
+}
+
+impl<T: Clone + Eq + Hash, S: BuildHasher> InsertNew<T> for HashSet<T, S> {
+    fn insert_new(&mut self, item: T) -> Option<T> {
+        if self.contains(&item) {
+            None
+        } else {
+            self.insert(item.clone());
+            Some(item)
+        }
+    }
+
+    fn remove_inserted(&mut self, inserted: Option<T>) {
+        if let Some(item) = inserted {
+            self.remove(&item);
+        }
+    }
+}
+
+/// Trait for map types whose entries are exposed as HTTP resources.
+///
+/// Modifications return an `Undo` that restores the map's previous state, used to roll back
+/// changes rejected by a validator.
+pub trait ChoicesMap: Default {
+    /// Type of the values.
+    type Item;
+    /// Type of the keys.
+    type Key;
+    /// Information needed to revert a modification.
+    type Undo;
+
+    /// Parses a key from a path segment.
+    fn parse_key(segment: &str) -> Option<Self::Key>;
+
+    /// Returns the keys of all entries.
+    fn keys_list(&self) -> Vec<&Self::Key>;
+
+    /// Returns the value associated to `key`, if any.
+    fn get_item(&self, key: &Self::Key) -> Option<&Self::Item>;
+
+    /// Inserts a new entry or replaces the value of an existing one.
+    fn insert_item(&mut self, key: Self::Key, item: Self::Item) -> Self::Undo;
+
+    /// Removes the entry identified by `key`, if any.
+    fn remove_item(&mut self, key: Self::Key) -> Option<Self::Undo>;
+
+    /// Reverts a modification.
+    fn undo(&mut self, undo: Self::Undo);
+}
+
+/// Undo of a modification to a map.
+#[doc(hidden)]
+pub enum MapUndo<K, V> {
+    Inserted(K, Option<V>),
+    Removed(K, V),
+}
+
+/// Implements `ChoicesMap` for maps.
+macro_rules! map_impl {
+    ([$($generics:tt)*] $ty:ty $(, $bound:path)*) => {
+        impl<K, V, $($generics)*> ChoicesMap for $ty
+        where
+            K: Clone + for<'a> ChoicesInput<'a> $(+ $bound)*,
+        {
+            type Item = V;
+            type Key = K;
+            type Undo = MapUndo<K, V>;
+
+            fn parse_key(segment: &str) -> Option<K> {
+                K::from_chars(&Bytes::copy_from_slice(segment.as_bytes())).ok()
+            }
+
+            fn keys_list(&self) -> Vec<&K> {
+                self.keys().collect()
+            }
+
+            fn get_item(&self, key: &K) -> Option<&V> {
+                self.get(key)
+            }
+
+            fn insert_item(&mut self, key: K, item: V) -> Self::Undo {
+                let old = self.insert(key.clone(), item);
+                MapUndo::Inserted(key, old)
+            }
+
+            fn remove_item(&mut self, key: K) -> Option<Self::Undo> {
+                self.remove_entry(&key)
+                    .map(|(key, item)| MapUndo::Removed(key, item))
+            }
+
+            fn undo(&mut self, undo: Self::Undo) {
+                match undo {
+                    MapUndo::Inserted(key, Some(old)) | MapUndo::Removed(key, old) => {
+                        self.insert(key, old);
+                    }
+                    MapUndo::Inserted(key, None) => {
+                        self.remove(&key);
+                    }
+                }
+            }
+        }
+    };
+}
+
+map_impl! {[S: BuildHasher + Default] HashMap<K, V, S>, Eq, Hash}
+map_impl! {[] BTreeMap<K, V>, Ord}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence_undo() {
+        let mut v = vec![1, 2, 3];
+        let undo = v.add_item(4);
+        v.undo(undo);
+        assert_eq!(v, vec![1, 2, 3]);
+        let undo = v.replace_item(1, 5).ok().unwrap();
+        assert_eq!(v, vec![1, 5, 3]);
+        v.undo(undo);
+        assert_eq!(v, vec![1, 2, 3]);
+        let undo = v.remove_item(0).unwrap();
+        assert_eq!(v, vec![2, 3]);
+        v.undo(undo);
+        assert_eq!(v, vec![1, 2, 3]);
+        assert!(v.replace_item(3, 0).is_err());
+        assert!(v.remove_item(3).is_none());
+    }
+
+    #[test]
+    fn set_undo() {
+        let mut s: HashSet<u8> = [1, 2].into_iter().collect();
+        let undo = s.add_item(2);
+        s.undo(undo);
+        assert_eq!(s, [1, 2].into_iter().collect());
+        let undo = s.replace_item(1, 3).ok().unwrap();
+        assert_eq!(s, [2, 3].into_iter().collect());
+        s.undo(undo);
+        assert_eq!(s, [1, 2].into_iter().collect());
+        let undo = s.remove_item(2).unwrap();
+        s.undo(undo);
+        assert_eq!(s, [1, 2].into_iter().collect());
+        assert_eq!(HashSet::<u8>::parse_key("7"), Some(7));
+    }
+
+    #[test]
+    fn map_undo() {
+        let mut m: BTreeMap<String, u8> = [("a".to_string(), 1)].into_iter().collect();
+        let undo = m.insert_item("a".to_string(), 2);
+        assert_eq!(m.get_item(&"a".to_string()), Some(&2));
+        m.undo(undo);
+        assert_eq!(m.get_item(&"a".to_string()), Some(&1));
+        let undo = m.insert_item("b".to_string(), 3);
+        m.undo(undo);
+        assert_eq!(m.keys_list(), vec!["a"]);
+        let undo = m.remove_item("a".to_string()).unwrap();
+        assert!(m.is_empty());
+        m.undo(undo);
+        assert_eq!(m.keys_list(), vec!["a"]);
+        assert!(m.remove_item("b".to_string()).is_none());
+    }
+
+    #[test]
+    fn encoded_segments() {
+        type Map = BTreeMap<String, u8>;
+        assert_eq!(
+            segment_key("a%20b%2Fc", Map::parse_key),
+            Some("a b/c".to_string())
+        );
+        assert_eq!(segment_key("a+b", Map::parse_key), Some("a+b".to_string()));
+        assert_eq!(segment_key("%FF", Map::parse_key), None);
+        assert_eq!(segment_key("%37", HashSet::<u8>::parse_key), Some(7));
+    }
+}
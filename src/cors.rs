@@ -0,0 +1,49 @@
+//! Cross-origin requests from browsers.
+//!
+//! Configurations with `#[choices(cors = expr)]` reply to the cross-origin requests allowed by
+//! the `warp::cors::Builder` returned by `expr`, answering their preflight requests, and reject
+//! the other ones with `403 Forbidden`. The builders of this module allow the methods and the
+//! headers of the configuration's resources, and can be customized further.
+
+use crate::signature::{NONCE_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use warp::cors::Builder;
+use warp::http::{header, Method};
+
+/// Methods of the configuration's resources.
+pub const METHODS: [Method; 5] = [
+    Method::GET,
+    Method::PUT,
+    Method::POST,
+    Method::PATCH,
+    Method::DELETE,
+];
+
+/// Returns the builder allowing the cross-origin requests from `origins`, such as
+/// `https://admin.example.com`.
+///
+/// # Panics
+///
+/// Panics if any of `origins` isn't a valid origin.
+pub fn origins<'a>(origins: impl IntoIterator<Item = &'a str>) -> Builder {
+    builder().allow_origins(origins)
+}
+
+/// Returns the builder allowing the cross-origin requests from any origin.
+pub fn any_origin() -> Builder {
+    builder().allow_any_origin()
+}
+
+/// Returns the builder allowing the methods and the headers of the configuration's resources,
+/// and exposing their `ETag`s.
+fn builder() -> Builder {
+    warp::cors()
+        .allow_methods(METHODS)
+        .allow_headers([
+            header::ACCEPT,
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::IF_MATCH,
+        ])
+        .allow_headers([TIMESTAMP_HEADER, NONCE_HEADER, SIGNATURE_HEADER])
+        .expose_header(header::ETAG)
+}
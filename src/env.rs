@@ -0,0 +1,164 @@
+//! Loading of configurations from environment variables.
+//!
+//! The generated `load_env(prefix)` reads the value of each field which isn't skipped from the
+//! variable named after the prefix and the path of the field, in uppercase and separated by
+//! underscores, e.g. `APP_PORT` or `APP_DB_PORT` for the field `port` of the nested
+//! configuration `db`. The prefix can't be empty, so that variables such as `PATH` or `HOME`
+//! aren't taken for fields. Values are parsed as in the bodies of **PUT** requests, while in json
+//! mode they're parsed as json, or else taken as strings.
+
+use crate::patch::Patch;
+use crate::{ChoicesError, ChoicesResult};
+use std::env::{self, VarError};
+
+/// Returns the name of the variable holding the value of the field `name`, a path such as
+/// `db.port`, with the prefix `prefix`.
+pub fn var_name(prefix: &str, name: &str) -> String {
+    format!("{}_{}", prefix, name.replace('.', "_").to_uppercase())
+}
+
+/// Returns the default prefix of the variables of the configuration type `type_name`, given by
+/// `std::any::type_name()`: the name of the type in uppercase, with its words separated by
+/// underscores, e.g. `APP_CONFIG` for `app::AppConfig`.
+#[doc(hidden)]
+pub fn default_prefix(type_name: &str) -> String {
+    let name = type_name.split('<').next().unwrap_or_default();
+    let chars: Vec<char> = name
+        .rsplit("::")
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .collect();
+    let mut prefix = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if i > 0 && c.is_uppercase() {
+            let previous = chars[i - 1];
+            let word_start = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if previous.is_lowercase() || previous.is_ascii_digit() || word_start {
+                prefix.push('_');
+            }
+        }
+        prefix.extend(c.to_uppercase());
+    }
+    prefix
+}
+
+/// Prepares the fields `names` whose variables, with the prefix `prefix`, are set through
+/// `prepare`.
+///
+/// Every variable is parsed and validated before failing, so that all errors are returned at
+/// once, as a `MultipleErrors` if there's more than one. An empty prefix is rejected.
+#[doc(hidden)]
+pub fn prepare_env<T>(
+    prefix: &str,
+    names: &[String],
+    mut prepare: impl FnMut(&str, String) -> ChoicesResult<Patch<T>>,
+) -> ChoicesResult<Vec<Patch<T>>> {
+    if prefix.is_empty() {
+        return Err(ChoicesError::ValidationError(
+            "the prefix of the environment variables can't be empty".to_string(),
+        ));
+    }
+    let mut patches = Vec::new();
+    let mut errors = Vec::new();
+    for name in names {
+        let var = var_name(prefix, name);
+        let result = match env::var(&var) {
+            Ok(value) => prepare(name, value).map(Some),
+            Err(VarError::NotPresent) => Ok(None),
+            Err(VarError::NotUnicode(_)) => Err(ChoicesError::ParseError(format!(
+                "the variable `{}` isn't valid unicode",
+                var
+            ))),
+        };
+        match result {
+            Ok(Some(patch)) => patches.push(patch),
+            Ok(None) => {}
+            Err(err) => errors.push(err.with_field(name.as_str())),
+        }
+    }
+    match errors.len() {
+        0 => Ok(patches),
+        1 => Err(errors.remove(0)),
+        _ => Err(ChoicesError::MultipleErrors(errors)),
+    }
+}
+
+/// Prepares the value `value` of the field `name`, a path such as `db.port`, through `prepare`,
+/// which takes the json member of the configuration holding the field, e.g. `db` with the value
+/// `{"port": value}`.
+///
+/// Values are parsed as json, and taken as strings if they aren't valid json or the field
+/// rejects them, e.g. `123` for a `String`; the error of the json value is returned if the string
+/// is rejected too.
+#[cfg(feature = "json")]
+#[doc(hidden)]
+pub fn prepare_json<T>(
+    name: &str,
+    value: String,
+    mut prepare: impl FnMut(&str, serde_json::Value) -> ChoicesResult<Patch<T>>,
+) -> ChoicesResult<Patch<T>> {
+    let parsed: Option<serde_json::Value> = serde_json::from_str(&value).ok();
+    let string = serde_json::Value::String(value);
+    match parsed {
+        Some(parsed) if parsed != string => {
+            let (member, parsed) = json_member(name, parsed);
+            prepare(member, parsed).or_else(|err| {
+                let (member, string) = json_member(name, string);
+                prepare(member, string).map_err(|_| err)
+            })
+        }
+        _ => {
+            let (member, string) = json_member(name, string);
+            prepare(member, string)
+        }
+    }
+}
+
+/// Returns the value `value` of the field `name` as the json member of the configuration holding
+/// it.
+#[cfg(feature = "json")]
+fn json_member(name: &str, mut value: serde_json::Value) -> (&str, serde_json::Value) {
+    let mut path: Vec<_> = name.split('.').collect();
+    let member = path.remove(0);
+    for name in path.into_iter().rev() {
+        let mut object = serde_json::Map::new();
+        object.insert(name.to_string(), value);
+        value = serde_json::Value::Object(object);
+    }
+    (member, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn var_names() {
+        assert_eq!(var_name("APP", "port"), "APP_PORT");
+        assert_eq!(
+            var_name("APP", "db.max_connections"),
+            "APP_DB_MAX_CONNECTIONS"
+        );
+    }
+
+    #[test]
+    fn default_prefixes() {
+        assert_eq!(default_prefix("app::Config"), "CONFIG");
+        assert_eq!(default_prefix("app::config::AppConfig"), "APP_CONFIG");
+        assert_eq!(default_prefix("HTTPServer2Config"), "HTTP_SERVER2_CONFIG");
+        assert_eq!(default_prefix("app::Config<u8>"), "CONFIG");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_members() {
+        use serde_json::json;
+
+        assert_eq!(json_member("port", json!(80)), ("port", json!(80)));
+        assert_eq!(
+            json_member("db.tls.hosts", json!(["a"])),
+            ("db", json!({"tls": {"hosts": ["a"]}}))
+        );
+    }
+}
@@ -4,13 +4,18 @@ use std::char::ParseCharError;
 use std::num::{ParseFloatError, ParseIntError};
 use std::result::Result;
 use std::str::{ParseBoolError, Utf8Error};
+use std::sync::Arc;
 use std::{error, fmt, fmt::Debug, fmt::Display, string::FromUtf8Error};
 
 /// Alias for a `Result` returning a `ChoicesError`.
 pub type ChoicesResult<T> = Result<T, ChoicesError>;
 
 /// Error type for all kind of errors generated by `choices`.
-#[derive(Debug, Clone, Eq, PartialEq)]
+///
+/// Errors can be given the context of the field which raised them and the HTTP status of their
+/// reply through `with_field()` and `with_status()`, e.g. by validators.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ChoicesError {
     /// Generic parsing error.
     ParseError(String),
@@ -30,6 +35,123 @@ pub enum ChoicesError {
     ParseCharError(ParseCharError),
     /// Error when validating a field.
     ValidationError(String),
+    /// Error when an element of a collection doesn't exist.
+    NotFound(String),
+    /// Error when a modification is conditioned to a revision which isn't the current one.
+    PreconditionFailed(u64),
+    /// Error when a request doesn't carry valid credentials.
+    Unauthorized(String),
+    /// Error when a request carries valid credentials which don't allow it.
+    Forbidden(String),
+    /// Error defined by the user, such as the failure of an external check, wrapping its source.
+    CustomError(Arc<dyn error::Error + Send + Sync>),
+    /// Errors raised together, such as by the fields loaded from environment variables.
+    MultipleErrors(Vec<ChoicesError>),
+    /// Error with the context in which it was raised.
+    Context {
+        /// Name of the field which raised the error, if known.
+        field: Option<String>,
+        /// HTTP status of the reply to the error, replacing the default one.
+        status: Option<u16>,
+        /// The error.
+        error: Box<ChoicesError>,
+    },
+}
+
+impl ChoicesError {
+    /// Returns a `CustomError` wrapping `source`.
+    pub fn custom(source: impl Into<Box<dyn error::Error + Send + Sync>>) -> Self {
+        Self::CustomError(Arc::from(source.into()))
+    }
+
+    /// Returns the error raised by the field `field`.
+    pub fn with_field(self, field: impl Into<String>) -> Self {
+        let field = Some(field.into());
+        match self {
+            Self::Context { status, error, .. } => Self::Context {
+                field,
+                status,
+                error,
+            },
+            error => Self::Context {
+                field,
+                status: None,
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Returns the error whose reply has the HTTP status `status`, such as `409` or `422`,
+    /// in place of the default one.
+    ///
+    /// Only client and server error statuses, from `400` to `599`, are accepted: the error is
+    /// returned unchanged with any other status, so that it's never replied as a success.
+    pub fn with_status(self, status: u16) -> Self {
+        if !(400..600).contains(&status) {
+            return self;
+        }
+        let status = Some(status);
+        match self {
+            Self::Context { field, error, .. } => Self::Context {
+                field,
+                status,
+                error,
+            },
+            error => Self::Context {
+                field: None,
+                status,
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Returns the name of the field which raised the error, if known.
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            Self::Context { field, .. } => field.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the HTTP status of the reply to the error, if it replaces the default one.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Context { status, .. } => *status,
+            _ => None,
+        }
+    }
+
+    /// Returns the error without its context.
+    pub fn kind(&self) -> &ChoicesError {
+        match self {
+            Self::Context { error, .. } => error.kind(),
+            error => error,
+        }
+    }
+
+    /// Returns the stable code of the kind of error, sent in structured error replies so that
+    /// clients can tell errors apart without parsing their messages.
+    pub fn code(&self) -> &'static str {
+        use ChoicesError::*;
+        match self {
+            ParseError(_) => "parse_error",
+            Utf8Error(_) => "utf8_error",
+            FromUtf8Error(_) => "from_utf8_error",
+            TryFromSliceError(_, _) => "try_from_slice_error",
+            ParseIntError(_) => "parse_int_error",
+            ParseFloatError(_) => "parse_float_error",
+            ParseBoolError(_) => "parse_bool_error",
+            ParseCharError(_) => "parse_char_error",
+            ValidationError(_) => "validation_error",
+            NotFound(_) => "not_found",
+            PreconditionFailed(_) => "precondition_failed",
+            Unauthorized(_) => "unauthorized",
+            Forbidden(_) => "forbidden",
+            CustomError(_) => "custom_error",
+            MultipleErrors(_) => "multiple_errors",
+            Context { error, .. } => error.code(),
+        }
+    }
 }
 
 impl Display for ChoicesError {
@@ -49,11 +171,91 @@ impl Display for ChoicesError {
             ParseBoolError(err) => write!(f, "ParseBoolError: {}", err),
             ParseCharError(err) => write!(f, "ParseCharError: {}", err),
             ValidationError(err) => write!(f, "ValidationError: {}", err),
+            NotFound(err) => write!(f, "NotFound: {}", err),
+            PreconditionFailed(revision) => {
+                write!(
+                    f,
+                    "PreconditionFailed: the current revision is {}",
+                    revision
+                )
+            }
+            Unauthorized(err) => write!(f, "Unauthorized: {}", err),
+            Forbidden(err) => write!(f, "Forbidden: {}", err),
+            CustomError(err) => write!(f, "CustomError: {}", err),
+            MultipleErrors(errors) => {
+                write!(f, "MultipleErrors: ")?;
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    match err.field() {
+                        Some(field) => write!(f, "{}: {}", field, err)?,
+                        None => write!(f, "{}", err)?,
+                    }
+                }
+                Ok(())
+            }
+            Context { error, .. } => write!(f, "{}", error),
+        }
+    }
+}
+
+impl error::Error for ChoicesError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        use ChoicesError::*;
+        match self {
+            Utf8Error(err) => Some(err),
+            FromUtf8Error(err) => Some(err),
+            ParseIntError(err) => Some(err),
+            ParseFloatError(err) => Some(err),
+            ParseBoolError(err) => Some(err),
+            ParseCharError(err) => Some(err),
+            CustomError(err) => Some(err.as_ref()),
+            Context { error, .. } => error.source(),
+            _ => None,
+        }
+    }
+}
+
+/// Errors are equal if they're of the same kind and have the same context, where custom errors
+/// are compared by their messages.
+impl PartialEq for ChoicesError {
+    fn eq(&self, other: &Self) -> bool {
+        use ChoicesError::*;
+        match (self, other) {
+            (ParseError(a), ParseError(b)) => a == b,
+            (Utf8Error(a), Utf8Error(b)) => a == b,
+            (FromUtf8Error(a), FromUtf8Error(b)) => a == b,
+            (TryFromSliceError(a, b), TryFromSliceError(c, d)) => (a, b) == (c, d),
+            (ParseIntError(a), ParseIntError(b)) => a == b,
+            (ParseFloatError(a), ParseFloatError(b)) => a == b,
+            (ParseBoolError(a), ParseBoolError(b)) => a == b,
+            (ParseCharError(a), ParseCharError(b)) => a == b,
+            (ValidationError(a), ValidationError(b)) => a == b,
+            (NotFound(a), NotFound(b)) => a == b,
+            (PreconditionFailed(a), PreconditionFailed(b)) => a == b,
+            (Unauthorized(a), Unauthorized(b)) => a == b,
+            (Forbidden(a), Forbidden(b)) => a == b,
+            (CustomError(a), CustomError(b)) => a.to_string() == b.to_string(),
+            (MultipleErrors(a), MultipleErrors(b)) => a == b,
+            (
+                Context {
+                    field: a_field,
+                    status: a_status,
+                    error: a,
+                },
+                Context {
+                    field: b_field,
+                    status: b_status,
+                    error: b,
+                },
+            ) => (a_field, a_status, a) == (b_field, b_status, b),
+            _ => false,
         }
     }
 }
 
-impl error::Error for ChoicesError {}
+impl Eq for ChoicesError {}
 
 macro_rules! impl_trivial_from_error {
     ($($ty:ident),*) => {
@@ -66,3 +268,60 @@ macro_rules! impl_trivial_from_error {
 }
 
 impl_trivial_from_error! {Utf8Error, FromUtf8Error, ParseIntError, ParseFloatError, ParseBoolError, ParseCharError}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn context() {
+        let err = ChoicesError::ValidationError("too small".to_string())
+            .with_status(422)
+            .with_field("port");
+        assert_eq!(err.field(), Some("port"));
+        assert_eq!(err.status(), Some(422));
+        assert_eq!(err.code(), "validation_error");
+        assert_eq!(err.to_string(), "ValidationError: too small");
+        assert_eq!(
+            err.kind(),
+            &ChoicesError::ValidationError("too small".to_string())
+        );
+        assert_eq!(ChoicesError::NotFound("x".to_string()).status(), None);
+
+        // Statuses other than errors are rejected.
+        assert_eq!(err.clone().with_status(200).status(), Some(422));
+        assert_eq!(err.clone().with_status(302), err);
+        assert_eq!(err.with_status(600).status(), Some(422));
+        let err = ChoicesError::NotFound("x".to_string()).with_status(99);
+        assert_eq!(err, ChoicesError::NotFound("x".to_string()));
+        assert_eq!(err.with_status(599).status(), Some(599));
+    }
+
+    #[test]
+    fn sources() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let err = ChoicesError::custom(io).with_field("file");
+        assert_eq!(err.code(), "custom_error");
+        assert_eq!(err.to_string(), "CustomError: no such file");
+        assert_eq!(err.source().unwrap().to_string(), "no such file");
+        assert_eq!(err, ChoicesError::custom("no such file").with_field("file"));
+        assert_eq!(err.clone().source().unwrap().to_string(), "no such file");
+
+        let err = ChoicesError::from("x".parse::<u8>().unwrap_err());
+        assert!(err.source().unwrap().is::<ParseIntError>());
+    }
+
+    #[test]
+    fn multiple_errors() {
+        let err = ChoicesError::MultipleErrors(vec![
+            ChoicesError::ValidationError("too small".to_string()).with_field("port"),
+            ChoicesError::ParseError("invalid".to_string()),
+        ]);
+        assert_eq!(err.code(), "multiple_errors");
+        assert_eq!(
+            err.to_string(),
+            "MultipleErrors: port: ValidationError: too small; ParseError: invalid"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Notifications of the changes of configuration fields.
+//!
+//! Each configuration object has a channel where an event is sent every time one of its fields is
+//! set, either through HTTP or through the generated setters. Events are streamed over HTTP as
+//! Server-Sent Events, and can be received in process through the generated `subscribe()`.
+
+use futures_util::stream::{self, BoxStream, Stream, StreamExt};
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender};
+
+/// Number of events retained for subscribers lagging behind.
+const CAPACITY: usize = 256;
+
+/// Change of a configuration field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Name of the field, preceded by the names of its nested configurations (e.g. `db.port`).
+    pub field: String,
+    /// New value of the field, formatted as in the body of the HTTP responses.
+    pub value: String,
+    /// Revision at which the field has been set.
+    pub revision: u64,
+}
+
+/// Channel of the events of a configuration object.
+#[derive(Debug)]
+pub struct Events {
+    sender: Sender<Event>,
+}
+
+impl Events {
+    /// Creates a channel without subscribers.
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+
+    /// Returns true if there's at least one subscriber, so that events are worth formatting.
+    pub fn is_observed(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// Sends `event` to all current subscribers.
+    pub fn send(&self, event: Event) {
+        // Sending fails only if there are no subscribers, in which case the event is dropped.
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns a receiver of all the events sent from now on.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Subscription to the events of a configuration and of its nested configurations, returned by
+/// the generated `subscribe()`.
+///
+/// Events missed by subscribers lagging behind are skipped.
+pub struct Subscription {
+    events: BoxStream<'static, Event>,
+}
+
+impl Subscription {
+    /// Creates the subscription merging the events received by `receivers`, whose field names
+    /// are preceded by the associated prefix.
+    #[doc(hidden)]
+    pub fn new(receivers: Vec<(String, Receiver<Event>)>) -> Self {
+        Self {
+            events: stream::select_all(
+                receivers
+                    .into_iter()
+                    .map(|(prefix, receiver)| receiver_stream(prefix, receiver)),
+            )
+            .boxed(),
+        }
+    }
+
+    /// Waits for the next event.
+    pub async fn recv(&mut self) -> Option<Event> {
+        self.events.next().await
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        self.events.poll_next_unpin(cx)
+    }
+}
+
+fn receiver_stream(prefix: String, receiver: Receiver<Event>) -> BoxStream<'static, Event> {
+    stream::unfold(receiver, move |mut receiver| {
+        let prefix = prefix.clone();
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(mut event) => {
+                        event.field.insert_str(0, &prefix);
+                        return Some((event, receiver));
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+/// Returns the Server-Sent Events reply streaming the events of `subscription`.
+///
+/// Each event is named after its field, its data is the new value and its id is the revision.
+#[doc(hidden)]
+pub fn sse_reply(subscription: Subscription) -> impl warp::Reply {
+    let events = subscription.map(|event| {
+        Ok::<_, Infallible>(
+            warp::sse::Event::default()
+                .event(event.field)
+                .id(event.revision.to_string())
+                .data(event.value),
+        )
+    });
+    warp::sse::reply(warp::sse::keep_alive().stream(events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn prefixed_subscription() {
+        let events = Events::new();
+        let nested_events = Events::new();
+        assert!(!events.is_observed());
+        let mut subscription = Subscription::new(vec![
+            (String::new(), events.subscribe()),
+            ("db.".to_string(), nested_events.subscribe()),
+        ]);
+        assert!(events.is_observed());
+        nested_events.send(Event {
+            field: "port".to_string(),
+            value: "80".to_string(),
+            revision: 1,
+        });
+        assert_eq!(
+            subscription.recv().await,
+            Some(Event {
+                field: "db.port".to_string(),
+                value: "80".to_string(),
+                revision: 1,
+            })
+        );
+    }
+}
@@ -17,6 +17,7 @@
 //!     debug: bool,
 //!     id: Option<i32>,
 //!     log_file: String,
+//!     state: choices::State,
 //! }
 //!
 //! lazy_static! {
@@ -24,7 +25,8 @@
 //!         Arc::new(Mutex::new(Config {
 //!             debug: false,
 //!             id: Some(3),
-//!             log_file: "log.txt".to_string()
+//!             log_file: "log.txt".to_string(),
+//!             state: Default::default(),
 //!         }))
 //!     };
 //! }
@@ -62,6 +64,11 @@ pub mod warp {
     pub use warp::*;
 }
 
+/// Re-export of `tokio`
+pub mod tokio {
+    pub use tokio::*;
+}
+
 #[cfg(feature = "json")]
 /// Re-export of `serde_json`
 pub mod serde_json {
@@ -71,13 +78,59 @@ pub mod serde_json {
 #[doc(hidden)]
 pub use async_trait::*;
 
+pub mod access;
+pub use crate::access::{ChoicesAccess, NestedAccess};
+
+pub mod auth;
+
+pub mod collection;
+pub use crate::collection::{ChoicesCollection, ChoicesMap};
+
+pub mod cors;
+
+pub mod env;
+
 pub mod error;
 pub use crate::error::{ChoicesError, ChoicesResult};
 
+pub mod events;
+
+pub mod negotiate;
+
+pub mod patch;
+pub use crate::patch::Patch;
+
+pub mod persist;
+
+pub mod revision;
+
 pub mod serde;
-pub use crate::serde::{ChoicesInput, ChoicesOutput};
+#[doc(hidden)]
+pub use crate::serde::{escape, split_fields, split_variant, HasVariants, NoVariants, VariantsOf};
+pub use crate::serde::{ChoicesInput, ChoicesOutput, ChoicesVariants};
+
+pub mod server;
+pub use crate::server::ServerHandle;
 
+pub mod signature;
+
+pub mod state;
+pub use crate::state::State;
+
+pub mod wait;
+
+pub mod watch;
+
+#[cfg(feature = "json")]
+pub mod ws;
+
+use crate::server::ConfigFilter;
+use std::future::Future;
+#[cfg(unix)]
+use std::io;
 use std::net::SocketAddr;
+#[cfg(any(unix, feature = "tls"))]
+use std::path::Path;
 use std::sync::{Arc, Mutex, RwLock};
 
 /// A trait to manage the http server responsible for the configuration.
@@ -86,6 +139,125 @@ pub trait Choices {
     /// Starts the configuration http server on the chosen address.
     async fn run<T: Into<SocketAddr> + Send>(&'static self, addr: T);
 
+    /// Starts the configuration http server on the chosen address, until `signal` completes.
+    /// The server then stops accepting connections and ends once the requests in flight are
+    /// completed.
+    async fn run_with_shutdown<T, S>(&'static self, addr: T, signal: S)
+    where
+        T: Into<SocketAddr> + Send,
+        S: Future<Output = ()> + Send + 'static,
+    {
+        let addr: SocketAddr = addr.into();
+        let (_, server) = warp::serve(self.__filter()).bind_with_graceful_shutdown(addr, signal);
+        server.await
+    }
+
+    /// Binds the configuration http server to the chosen address, returning the address it's
+    /// bound to and the future running the server.
+    ///
+    /// The server accepts connections as soon as this returns, and binding to port `0` chooses
+    /// a free port.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address can't be bound or if called outside of a tokio runtime.
+    fn bind<T: Into<SocketAddr>>(
+        &'static self,
+        addr: T,
+    ) -> (SocketAddr, impl Future<Output = ()> + Send + 'static) {
+        let addr: SocketAddr = addr.into();
+        warp::serve(self.__filter()).bind_ephemeral(addr)
+    }
+
+    /// Starts the configuration http server on the chosen address in a background task of the
+    /// current tokio runtime, returning the handle to stop it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address can't be bound or if called outside of a tokio runtime.
+    fn spawn<T: Into<SocketAddr>>(&'static self, addr: T) -> ServerHandle {
+        ServerHandle::spawn(self.__filter(), addr)
+    }
+
+    /// Starts the configuration http server on the Unix domain socket at `path`, which only the
+    /// user of the process can access.
+    ///
+    /// A stale socket file left at `path`, on which no server is listening, is replaced. Fails if
+    /// the socket can't be bound, if another server is listening on it or if `path` is a file
+    /// other than a socket, and ends with the error of accepting a connection, unless it concerns
+    /// only that connection.
+    #[cfg(unix)]
+    async fn run_unix<P: AsRef<Path> + Send>(&'static self, path: P) -> io::Result<()> {
+        self.run_unix_with_mode(path, 0o600).await
+    }
+
+    /// Like `run_unix()`, with the permissions `mode` for the socket file, e.g. `0o660` to let
+    /// the group of the process access it as well.
+    #[cfg(unix)]
+    async fn run_unix_with_mode<P: AsRef<Path> + Send>(
+        &'static self,
+        path: P,
+        mode: u32,
+    ) -> io::Result<()> {
+        server::serve_unix(self.__filter(), path.as_ref(), mode).await
+    }
+
+    /// Starts the configuration https server on the chosen address, with the PEM encoded
+    /// certificate chain at `cert_path` and private key at `key_path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the certificate or the key can't be read or parsed, or if the address can't be
+    /// bound.
+    #[cfg(feature = "tls")]
+    async fn run_tls<T, C, K>(&'static self, addr: T, cert_path: C, key_path: K)
+    where
+        T: Into<SocketAddr> + Send,
+        C: AsRef<Path> + Send,
+        K: AsRef<Path> + Send,
+    {
+        let addr: SocketAddr = addr.into();
+        warp::serve(self.__filter())
+            .tls()
+            .cert_path(cert_path)
+            .key_path(key_path)
+            .run(addr)
+            .await
+    }
+
+    /// Like `run_tls()`, accepting only the clients presenting a certificate signed by one of the
+    /// PEM encoded certificate authorities at `client_ca_path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the certificates or the key can't be read or parsed, or if the address can't be
+    /// bound.
+    #[cfg(feature = "tls")]
+    async fn run_tls_with_client_auth<T, C, K, A>(
+        &'static self,
+        addr: T,
+        cert_path: C,
+        key_path: K,
+        client_ca_path: A,
+    ) where
+        T: Into<SocketAddr> + Send,
+        C: AsRef<Path> + Send,
+        K: AsRef<Path> + Send,
+        A: AsRef<Path> + Send,
+    {
+        let addr: SocketAddr = addr.into();
+        warp::serve(self.__filter())
+            .tls()
+            .cert_path(cert_path)
+            .key_path(key_path)
+            .client_auth_required_path(client_ca_path)
+            .run(addr)
+            .await
+    }
+
+    #[doc(hidden)]
+    fn __filter(&'static self) -> ConfigFilter;
+
     #[doc(hidden)]
     async fn run_mutable<T: Into<SocketAddr> + Send>(_: Arc<Mutex<Self>>, _: T) {
         unimplemented!()
@@ -98,6 +270,19 @@ pub trait Choices {
     {
         unimplemented!()
     }
+
+    #[doc(hidden)]
+    fn __filter_mutable(_: Arc<Mutex<Self>>) -> ConfigFilter {
+        unimplemented!()
+    }
+
+    #[doc(hidden)]
+    fn __filter_mutable_rw(_: Arc<RwLock<Self>>) -> ConfigFilter
+    where
+        Self: Sync,
+    {
+        unimplemented!()
+    }
 }
 
 #[self::async_trait]
@@ -105,6 +290,10 @@ impl<C: Choices + Send> Choices for Arc<Mutex<C>> {
     async fn run<T: Into<SocketAddr> + Send>(&'static self, addr: T) {
         C::run_mutable(self.clone(), addr).await;
     }
+
+    fn __filter(&'static self) -> ConfigFilter {
+        C::__filter_mutable(self.clone())
+    }
 }
 
 #[self::async_trait]
@@ -112,4 +301,8 @@ impl<C: Choices + Send + Sync> Choices for Arc<RwLock<C>> {
     async fn run<T: Into<SocketAddr> + Send>(&'static self, addr: T) {
         C::run_mutable_rw(self.clone(), addr).await;
     }
+
+    fn __filter(&'static self) -> ConfigFilter {
+        C::__filter_mutable_rw(self.clone())
+    }
 }
@@ -0,0 +1,486 @@
+//! Negotiation of the format of requests and replies.
+//!
+//! With the feature `json` every configuration is served both as text and as json: replies use
+//! the format preferred by the `Accept` header of the request, and bodies are parsed according
+//! to their `Content-Type` header. Requests without a preference fall back to the format of the
+//! configuration, i.e. json for configurations with `#[choices(json)]` and text otherwise.
+//!
+//! Without the feature `json`, configurations are served only as text.
+
+use crate::error::ChoicesError;
+use std::fmt::Display;
+use warp::http::StatusCode;
+use warp::reply::{with_header, with_status, WithHeader, WithStatus};
+use warp::{Filter, Rejection};
+
+const CONTENT_TYPE_HEADER: &str = "Content-Type";
+const CONTENT_TYPE_TEXT: &str = "text/plain; charset=utf-8";
+#[cfg(feature = "json")]
+const CONTENT_TYPE_JSON: &str = "application/json";
+pub(crate) const INTERNAL_ERROR: &str = "internal_error";
+
+/// Format of the body of a request or of a reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Plain text, through `ChoicesInput` and `ChoicesOutput`.
+    Text,
+    /// Json, through `serde`.
+    #[cfg(feature = "json")]
+    Json,
+}
+
+impl Format {
+    /// Returns the format of a configuration, json if `json` is true.
+    pub fn new(json: bool) -> Self {
+        #[cfg(feature = "json")]
+        if json {
+            return Format::Json;
+        }
+        let _ = json;
+        Format::Text
+    }
+
+    /// Returns the value of the `Content-Type` header of bodies in this format.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Text => CONTENT_TYPE_TEXT,
+            #[cfg(feature = "json")]
+            Format::Json => CONTENT_TYPE_JSON,
+        }
+    }
+
+    /// Returns the format preferred by the `Accept` header `accept`, or `default` if there's
+    /// no preference between text and json.
+    pub fn from_accept(accept: Option<&str>, default: Self) -> Self {
+        #[cfg(feature = "json")]
+        if let Some(accept) = accept {
+            let text = quality(accept, "text", "plain");
+            let json = quality(accept, "application", "json");
+            if json > text {
+                return Format::Json;
+            } else if text > json {
+                return Format::Text;
+            }
+        }
+        let _ = accept;
+        default
+    }
+
+    /// Returns the format of a body whose `Content-Type` header is `content_type`, or `default`
+    /// if it's neither text nor json.
+    pub fn from_content_type(content_type: Option<&str>, default: Self) -> Self {
+        #[cfg(feature = "json")]
+        if let Some(content_type) = content_type {
+            let media_type = content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+            if media_type == "application/json" || media_type.ends_with("+json") {
+                return Format::Json;
+            } else if media_type == "text/plain" {
+                return Format::Text;
+            }
+        }
+        let _ = content_type;
+        default
+    }
+}
+
+/// Returns the quality that the `Accept` header `accept` gives to the media type
+/// `kind/subtype`, taken from its most specific media range.
+#[cfg(feature = "json")]
+fn quality(accept: &str, kind: &str, subtype: &str) -> u16 {
+    accept
+        .split(',')
+        .filter_map(|range| {
+            let mut params = range.split(';');
+            let media_range = params.next()?.trim().to_ascii_lowercase();
+            let (range_kind, range_subtype) = media_range.split_once('/')?;
+            let specificity = match (range_kind, range_subtype) {
+                (k, s) if k == kind && s == subtype => 2,
+                (k, "*") if k == kind => 1,
+                ("*", "*") => 0,
+                _ => return None,
+            };
+            let quality = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((specificity, (quality.clamp(0.0, 1.0) * 1000.0) as u16))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0, |(_, quality)| quality)
+}
+
+/// Returns the filter extracting the format of the reply from the `Accept` header, where `json`
+/// tells the format of the configuration.
+#[doc(hidden)]
+pub fn accept(json: bool) -> impl Filter<Extract = (Format,), Error = Rejection> + Clone {
+    let default = Format::new(json);
+    warp::header::optional::<String>("accept")
+        .map(move |accept: Option<String>| Format::from_accept(accept.as_deref(), default))
+}
+
+/// Returns the filter extracting the format of the body from the `Content-Type` header and the
+/// format of the reply from the `Accept` header, which defaults to the format of the body.
+/// `json` tells the format of the configuration.
+#[doc(hidden)]
+pub fn content_type(
+    json: bool,
+) -> impl Filter<Extract = (Format, Format), Error = Rejection> + Clone {
+    let default = Format::new(json);
+    warp::header::optional::<String>("content-type")
+        .and(warp::header::optional::<String>("accept"))
+        .map(
+            move |content_type: Option<String>, accept: Option<String>| {
+                let format = Format::from_content_type(content_type.as_deref(), default);
+                (format, Format::from_accept(accept.as_deref(), format))
+            },
+        )
+        .untuple_one()
+}
+
+/// Returns the reply with the given `body` in the format `format`.
+#[doc(hidden)]
+pub fn reply(format: Format, body: String, status: StatusCode) -> WithStatus<WithHeader<String>> {
+    with_status(
+        with_header(body, CONTENT_TYPE_HEADER, format.content_type()),
+        status,
+    )
+}
+
+/// Returns the reply of the error `err`, raised by the field `field` on the body `input`, with
+/// the status `status` unless the error requests another one. The field of the error, if known,
+/// takes precedence over `field`.
+///
+/// As text, the body of the reply is the message of the error. As json, it's an object with the
+/// stable code of the error (see `ChoicesError::code()`), the field, the message and the input,
+/// where the input is included as json if it's valid json and as a string otherwise.
+#[doc(hidden)]
+pub fn error_reply(
+    format: Format,
+    err: &ChoicesError,
+    field: Option<&str>,
+    input: Option<&[u8]>,
+    status: StatusCode,
+) -> WithStatus<WithHeader<String>> {
+    let status = err
+        .status()
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .unwrap_or(status);
+    let field = err.field().or(field);
+    failure_reply(format, err.code(), err.to_string(), field, input, status)
+}
+
+/// Returns the reply of an error which isn't caused by the request, such as a configuration
+/// which can't be accessed. Its code is `internal_error`.
+#[doc(hidden)]
+pub fn internal_error_reply(
+    format: Format,
+    message: impl Display,
+) -> WithStatus<WithHeader<String>> {
+    failure_reply(
+        format,
+        INTERNAL_ERROR,
+        message.to_string(),
+        None,
+        None,
+        StatusCode::INTERNAL_SERVER_ERROR,
+    )
+}
+
+/// Returns the reply of an error with code `code` and message `message`, as detailed in
+/// `error_reply()`.
+fn failure_reply(
+    format: Format,
+    code: &str,
+    message: String,
+    field: Option<&str>,
+    input: Option<&[u8]>,
+    status: StatusCode,
+) -> WithStatus<WithHeader<String>> {
+    let body = match format {
+        Format::Text => {
+            let _ = (code, field, input);
+            message
+        }
+        #[cfg(feature = "json")]
+        Format::Json => error_json(code, message, field, input).to_string(),
+    };
+    reply(format, body, status)
+}
+
+/// Returns the json object describing an error, as detailed in `error_reply()`.
+#[cfg(feature = "json")]
+pub(crate) fn error_json(
+    code: &str,
+    message: String,
+    field: Option<&str>,
+    input: Option<&[u8]>,
+) -> serde_json::Value {
+    use serde_json::{Map, Value};
+
+    let mut error = Map::new();
+    error.insert("error".to_string(), Value::from(code));
+    if let Some(field) = field {
+        error.insert("field".to_string(), Value::from(field));
+    }
+    error.insert("message".to_string(), Value::from(message));
+    if let Some(input) = input {
+        let input = serde_json::from_slice(input)
+            .unwrap_or_else(|_| Value::from(String::from_utf8_lossy(input)));
+        error.insert("input".to_string(), input);
+    }
+    Value::Object(error)
+}
+
+#[cfg(feature = "json")]
+pub use self::repr::*;
+
+/// Representation of values in the format which isn't the one of their configuration.
+///
+/// Types implementing only one of `ChoicesOutput` and `serde::Serialize` are written as text
+/// through json and vice versa; the same holds for `ChoicesInput` and `serde::Deserialize`.
+///
+/// Must be used as `(&ReprOf::<T>::new()).to_text(&value)` with both traits of the conversion in
+/// scope: the one implemented for `ReprOf` is chosen if its bound is satisfied.
+#[cfg(feature = "json")]
+mod repr {
+    use crate::error::{ChoicesError, ChoicesResult};
+    use crate::serde::{ChoicesInput, ChoicesOutput};
+    use bytes::Bytes;
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::Value;
+
+    /// Converts values of type `T` between text and json.
+    #[doc(hidden)]
+    pub struct ReprOf<T>(std::marker::PhantomData<T>);
+
+    impl<T> ReprOf<T> {
+        #[doc(hidden)]
+        pub fn new() -> Self {
+            Self(std::marker::PhantomData)
+        }
+    }
+
+    impl<T> Default for ReprOf<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    #[doc(hidden)]
+    pub trait TextOutput<T> {
+        fn to_text(&self, value: &T) -> String;
+    }
+
+    impl<T: ChoicesOutput> TextOutput<T> for ReprOf<T> {
+        fn to_text(&self, value: &T) -> String {
+            value.body_string()
+        }
+    }
+
+    #[doc(hidden)]
+    pub trait JsonAsTextOutput<T> {
+        fn to_text(&self, value: &T) -> String;
+    }
+
+    impl<T: Serialize> JsonAsTextOutput<T> for &ReprOf<T> {
+        fn to_text(&self, value: &T) -> String {
+            serde_json::to_string(value).unwrap_or_else(|err| format!("\"{}\"", err))
+        }
+    }
+
+    #[doc(hidden)]
+    pub trait JsonOutput<T> {
+        fn to_json(&self, value: &T) -> serde_json::Result<Value>;
+    }
+
+    impl<T: Serialize> JsonOutput<T> for ReprOf<T> {
+        fn to_json(&self, value: &T) -> serde_json::Result<Value> {
+            serde_json::to_value(value)
+        }
+    }
+
+    #[doc(hidden)]
+    pub trait TextAsJsonOutput<T> {
+        fn to_json(&self, value: &T) -> serde_json::Result<Value>;
+    }
+
+    impl<T: ChoicesOutput> TextAsJsonOutput<T> for &ReprOf<T> {
+        fn to_json(&self, value: &T) -> serde_json::Result<Value> {
+            Ok(Value::String(value.body_string()))
+        }
+    }
+
+    #[doc(hidden)]
+    pub trait TextInput<T> {
+        fn parse_text(&self, bytes: &Bytes) -> ChoicesResult<T>;
+    }
+
+    impl<T: for<'a> ChoicesInput<'a>> TextInput<T> for ReprOf<T> {
+        fn parse_text(&self, bytes: &Bytes) -> ChoicesResult<T> {
+            T::from_chars(bytes)
+        }
+    }
+
+    #[doc(hidden)]
+    pub trait JsonAsTextInput<T> {
+        fn parse_text(&self, bytes: &Bytes) -> ChoicesResult<T>;
+    }
+
+    impl<T: DeserializeOwned> JsonAsTextInput<T> for &ReprOf<T> {
+        fn parse_text(&self, bytes: &Bytes) -> ChoicesResult<T> {
+            serde_json::from_slice(bytes).map_err(|err| ChoicesError::ParseError(err.to_string()))
+        }
+    }
+
+    #[doc(hidden)]
+    pub trait JsonInput<T> {
+        fn parse_json(&self, value: Value) -> ChoicesResult<T>;
+    }
+
+    impl<T: DeserializeOwned> JsonInput<T> for ReprOf<T> {
+        fn parse_json(&self, value: Value) -> ChoicesResult<T> {
+            serde_json::from_value(value).map_err(|err| ChoicesError::ParseError(err.to_string()))
+        }
+    }
+
+    #[doc(hidden)]
+    pub trait TextAsJsonInput<T> {
+        fn parse_json(&self, value: Value) -> ChoicesResult<T>;
+    }
+
+    impl<T: for<'a> ChoicesInput<'a>> TextAsJsonInput<T> for &ReprOf<T> {
+        fn parse_json(&self, value: Value) -> ChoicesResult<T> {
+            match value {
+                Value::String(text) => T::from_chars(&Bytes::from(text)),
+                value => T::from_chars(&Bytes::from(value.to_string())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn accept_header() {
+        let text = Format::Text;
+        let json = Format::Json;
+        assert_eq!(Format::from_accept(None, json), json);
+        assert_eq!(Format::from_accept(Some("*/*"), text), text);
+        assert_eq!(Format::from_accept(Some("*/*"), json), json);
+        assert_eq!(Format::from_accept(Some("application/json"), text), json);
+        assert_eq!(Format::from_accept(Some("text/plain"), json), text);
+        assert_eq!(Format::from_accept(Some("text/*"), json), text);
+        assert_eq!(
+            Format::from_accept(Some("text/plain;q=0.5, application/json"), text),
+            json
+        );
+        assert_eq!(
+            Format::from_accept(Some("application/json;q=0.2, */*;q=0.8"), json),
+            text
+        );
+        assert_eq!(Format::from_accept(Some("image/png"), json), json);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn content_type_header() {
+        let text = Format::Text;
+        let json = Format::Json;
+        assert_eq!(Format::from_content_type(None, json), json);
+        assert_eq!(
+            Format::from_content_type(Some("application/json; charset=utf-8"), text),
+            json
+        );
+        assert_eq!(
+            Format::from_content_type(Some("application/merge-patch+json"), text),
+            json
+        );
+        assert_eq!(Format::from_content_type(Some("text/plain"), json), text);
+        // `curl -d` sends form data by default.
+        assert_eq!(
+            Format::from_content_type(Some("application/x-www-form-urlencoded"), json),
+            json
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn error_objects() {
+        use serde_json::json;
+
+        let err = ChoicesError::ValidationError("too small".to_string());
+        assert_eq!(
+            error_json(err.code(), err.to_string(), Some("port"), Some(b"0")),
+            json!({
+                "error": "validation_error",
+                "field": "port",
+                "message": "ValidationError: too small",
+                "input": 0,
+            })
+        );
+        assert_eq!(
+            error_json("parse_error", "x".to_string(), None, Some(b"not json")),
+            json!({"error": "parse_error", "message": "x", "input": "not json"})
+        );
+        assert_eq!(
+            error_json(INTERNAL_ERROR, "poisoned".to_string(), None, None),
+            json!({"error": "internal_error", "message": "poisoned"})
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    #[allow(clippy::needless_borrow)]
+    fn representations() {
+        use crate::serde::{ChoicesInput, ChoicesOutput};
+        use bytes::Bytes;
+        use serde_json::json;
+
+        struct Text(u8);
+
+        impl ChoicesOutput for Text {
+            fn body_string(&self) -> String {
+                self.0.to_string()
+            }
+        }
+
+        impl ChoicesInput<'_> for Text {
+            fn from_chars(bytes: &Bytes) -> crate::ChoicesResult<Self> {
+                Ok(Text(u8::from_chars(bytes)?))
+            }
+        }
+
+        assert_eq!(
+            (&ReprOf::<Text>::new()).to_json(&Text(3)).unwrap(),
+            json!("3")
+        );
+        assert_eq!(
+            (&ReprOf::<Text>::new()).parse_json(json!("4")).unwrap().0,
+            4
+        );
+        assert_eq!((&ReprOf::<Text>::new()).parse_json(json!(5)).unwrap().0, 5);
+        assert_eq!((&ReprOf::<Vec<u8>>::new()).to_text(&vec![1, 2]), "1,2");
+        assert_eq!(
+            (&ReprOf::<Vec<u8>>::new()).to_json(&vec![1, 2]).unwrap(),
+            json!([1, 2])
+        );
+        assert_eq!(
+            (&ReprOf::<Vec<u8>>::new())
+                .parse_text(&Bytes::from("1,2"))
+                .unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            (&ReprOf::<std::time::Duration>::new()).to_text(&std::time::Duration::from_secs(1)),
+            r#"{"secs":1,"nanos":0}"#
+        );
+    }
+}
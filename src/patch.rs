@@ -0,0 +1,111 @@
+//! Updates of multiple fields at once.
+//!
+//! The new values of all fields are parsed and validated first, producing a `Patch` for each
+//! field. Patches are applied only if all of them could be prepared, so that an update is
+//! either fully applied or not at all.
+
+use crate::bytes::Bytes;
+use crate::{ChoicesError, ChoicesResult};
+
+/// Prepared modification of a configuration object, which can't fail anymore.
+pub type Patch<T> = Box<dyn FnOnce(&mut T)>;
+
+/// Separator between the name and the value of a field in text patches.
+const NAME_SEPARATOR: u8 = b'=';
+
+/// Parses a text patch made of `name=value` lines, preparing each of them through `prepare`.
+///
+/// Empty lines are ignored.
+#[doc(hidden)]
+pub fn prepare_text<T>(
+    bytes: &Bytes,
+    mut prepare: impl FnMut(&str, &Bytes) -> ChoicesResult<Patch<T>>,
+) -> ChoicesResult<Vec<Patch<T>>> {
+    bytes
+        .split(|b| *b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .map(|line| prepare_line(&bytes.slice_ref(line), &mut prepare))
+        .collect()
+}
+
+/// Parses the `name=value` line `line` of a text patch, preparing it through `prepare`.
+pub(crate) fn prepare_line<T>(
+    line: &Bytes,
+    prepare: &mut impl FnMut(&str, &Bytes) -> ChoicesResult<Patch<T>>,
+) -> ChoicesResult<Patch<T>> {
+    let mut parts = line.splitn(2, |b| *b == NAME_SEPARATOR);
+    match (parts.next(), parts.next()) {
+        (Some(name), Some(value)) => {
+            let name = std::str::from_utf8(name)?;
+            prepare(name, &line.slice_ref(value)).map_err(|err| in_field(err, name))
+        }
+        _ => Err(ChoicesError::ParseError(format!(
+            "expected `name=value`, found `{}`",
+            String::from_utf8_lossy(line)
+        ))),
+    }
+}
+
+/// Parses a json patch, which must be an object, preparing each of its members through
+/// `prepare`.
+#[cfg(feature = "json")]
+#[doc(hidden)]
+pub fn prepare_json<T>(
+    value: serde_json::Value,
+    mut prepare: impl FnMut(&str, serde_json::Value) -> ChoicesResult<Patch<T>>,
+) -> ChoicesResult<Vec<Patch<T>>> {
+    match value {
+        serde_json::Value::Object(members) => members
+            .into_iter()
+            .map(|(name, value)| prepare(&name, value).map_err(|err| in_field(err, &name)))
+            .collect(),
+        _ => Err(ChoicesError::ParseError(
+            "expected a json object".to_string(),
+        )),
+    }
+}
+
+/// Returns the error `err` raised by the field `name`, whose field, if any, belongs to the
+/// nested configuration `name`.
+fn in_field(err: ChoicesError, name: &str) -> ChoicesError {
+    let field = match err.field() {
+        Some(field) => format!("{}.{}", name, field),
+        None => name.to_string(),
+    };
+    err.with_field(field)
+}
+
+/// Returns the error for a field which doesn't exist or can't be modified.
+#[doc(hidden)]
+pub fn unknown_field(name: &str) -> ChoicesError {
+    ChoicesError::ParseError(format!("unknown field `{}`", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prepare_line(name: &str, value: &Bytes) -> ChoicesResult<Patch<Vec<String>>> {
+        let line = format!("{}={}", name, std::str::from_utf8(value)?);
+        Ok(Box::new(move |lines: &mut Vec<String>| lines.push(line)))
+    }
+
+    #[test]
+    fn text_patch() {
+        let patches = prepare_text(&Bytes::from("a=1\r\n\nb=x=y\n"), prepare_line).unwrap();
+        let mut lines = vec![];
+        for patch in patches {
+            patch(&mut lines);
+        }
+        assert_eq!(lines, vec!["a=1", "b=x=y"]);
+        assert!(prepare_text(&Bytes::from("a=1\nb"), prepare_line).is_err());
+        assert_eq!(
+            prepare_text(&Bytes::from_static(b"a=1\nb=\xff"), prepare_line)
+                .err()
+                .unwrap()
+                .field(),
+            Some("b")
+        );
+    }
+}
@@ -0,0 +1,682 @@
+//! Persistence of configurations to files.
+//!
+//! Configurations can be loaded from a file with the generated `load()` and saved with
+//! `save()`. With `#[choices(persist = "path")]`, or once a path is set on their `persistence()`,
+//! they're saved again after every change, through HTTP or through the setters. Changes are kept
+//! even if they can't be saved: the error is returned by `Persistence::error()` and the save is
+//! retried at the next change.
+//!
+//! Saves are written in the background, in the order of the changes, so that the configuration
+//! isn't kept locked while the file is written: `Persistence::flush()` waits for them. The
+//! requests are replied once their changes are written. Changes through the setters of a nested
+//! configuration are saved to the files of the configurations it's nested in as well, once their
+//! `persistence()` has been set up, e.g. by `restore()`, by their setters or by serving them.
+//!
+//! The format of a file is told by its extension: `.json` files hold a json object, with the
+//! feature `json`, and `.toml` files a TOML table, with the feature `toml`. All other files hold
+//! `name=value` lines, as in the replies of **GET** `/config/_all`, whose line breaks are escaped
+//! by a backslash. Files are written atomically, by writing a temporary file and renaming it over
+//! the old one.
+
+use crate::bytes::Bytes;
+use crate::error::{ChoicesError, ChoicesResult};
+use crate::patch::{self, Patch};
+use crate::serde::{escape, split_escaped, unescaped_part};
+use crate::ChoicesAccess;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use warp::{Filter, Rejection, Reply};
+
+/// Separators between the lines of text files, escaped inside the values.
+const LINE_SEPARATORS: &[&str] = &["\n", "\r"];
+
+/// Format of a configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// `name=value` lines.
+    Text,
+    /// A json object, available with the feature `json`.
+    Json,
+    /// A TOML table, available with the feature `toml`.
+    Toml,
+}
+
+impl FileFormat {
+    /// Returns the format of the file `path`, told by its extension.
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Self::Json,
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Text,
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+            Self::Toml => write!(f, "toml"),
+        }
+    }
+}
+
+/// File where a configuration object is saved after every change, if any.
+#[derive(Debug)]
+pub struct Persistence {
+    state: Mutex<State>,
+    /// Notified every time a save is written or given up.
+    turns: Condvar,
+}
+
+#[derive(Debug)]
+struct State {
+    path: Option<PathBuf>,
+    /// Revision of the configuration when it was last saved or loaded.
+    saved: u64,
+    /// Error of the last save, if it failed.
+    error: Option<ChoicesError>,
+    /// Persistence of the configuration this one is nested in, and the name of its field there.
+    outer: Option<(Arc<Persistence>, &'static str)>,
+    /// Number of saves created so far, each of which has the number of the ones before as turn.
+    created: u64,
+    /// Turn of the next save to be written: the saves before it are written or given up.
+    next: u64,
+    /// Turns after `next` whose saves are written or given up.
+    finished: BTreeSet<u64>,
+}
+
+impl Persistence {
+    /// Creates the persistence to the file `path`, if any.
+    pub fn new(path: Option<&str>) -> Self {
+        Self {
+            state: Mutex::new(State {
+                path: path.map(PathBuf::from),
+                saved: 0,
+                error: None,
+                outer: None,
+                created: 0,
+                next: 0,
+                finished: BTreeSet::new(),
+            }),
+            turns: Condvar::new(),
+        }
+    }
+
+    /// Saves the configuration to the file `path` after every change.
+    ///
+    /// The configuration isn't saved until it changes.
+    pub fn persist_to(&self, path: impl Into<PathBuf>) {
+        self.state().path = Some(path.into());
+    }
+
+    /// Stops saving the configuration.
+    pub fn disable(&self) {
+        self.state().path = None;
+    }
+
+    /// Returns the file where the configuration is saved, if any.
+    pub fn path(&self) -> Option<PathBuf> {
+        self.state().path.clone()
+    }
+
+    /// Returns the error of the last save, if it failed. The changes which couldn't be saved are
+    /// kept, and saved again at the next change.
+    pub fn error(&self) -> Option<ChoicesError> {
+        self.state().error.clone()
+    }
+
+    /// Waits for the saves of the changes made so far to be written, returning the error of the
+    /// last save if it failed.
+    pub fn flush(&self) -> ChoicesResult<()> {
+        let mut state = self.state();
+        let created = state.created;
+        while state.next < created {
+            state = self
+                .turns
+                .wait(state)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+        state.error.clone().map_or(Ok(()), Err)
+    }
+
+    /// Records that the configuration is the field `name` of the configuration saved by `outer`,
+    /// whose file is saved as well after the changes made through the setters.
+    #[doc(hidden)]
+    pub fn nest_in(&self, outer: &Arc<Persistence>, name: &'static str) {
+        self.state().outer = Some((outer.clone(), name));
+    }
+
+    /// Returns the persistences of the configurations this one is nested in which have a file,
+    /// with their files and the path of this configuration within them, innermost first.
+    #[doc(hidden)]
+    pub fn outer_files(&self) -> Vec<(Arc<Persistence>, PathBuf, Vec<&'static str>)> {
+        let mut files = Vec::new();
+        let mut names = Vec::new();
+        let mut outer = self.state().outer.clone();
+        while let Some((persistence, name)) = outer {
+            names.insert(0, name);
+            let state = persistence.state();
+            if let Some(path) = &state.path {
+                files.push((persistence.clone(), path.clone(), names.clone()));
+            }
+            outer = state.outer.clone();
+        }
+        files
+    }
+
+    /// Returns the file where the configuration at revision `revision` must be saved, if it
+    /// changed since it was last saved.
+    #[doc(hidden)]
+    pub fn pending(&self, revision: u64) -> Option<PathBuf> {
+        let state = self.state();
+        match &state.path {
+            Some(path) if state.saved < revision => Some(path.clone()),
+            _ => None,
+        }
+    }
+
+    /// Records that the configuration at revision `revision` matches its file.
+    #[doc(hidden)]
+    pub fn mark_saved(&self, revision: u64) {
+        self.state().saved = revision;
+    }
+
+    /// Returns the turn of a new save.
+    fn take_turn(&self) -> u64 {
+        let mut state = self.state();
+        state.created += 1;
+        state.created - 1
+    }
+
+    /// Waits for the saves before the turn `turn` to be written or given up.
+    fn wait_turn(&self, turn: u64) -> MutexGuard<'_, State> {
+        let mut state = self.state();
+        while state.next < turn {
+            state = self
+                .turns
+                .wait(state)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+        state
+    }
+
+    /// Records that the save of the turn `turn` is written or given up.
+    fn end_turn(&self, turn: u64) {
+        let mut state = self.state();
+        state.finished.insert(turn);
+        loop {
+            let next = state.next;
+            if !state.finished.remove(&next) {
+                break;
+            }
+            state.next += 1;
+        }
+        self.turns.notify_all();
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Part of a file holding the values of a nested configuration.
+#[doc(hidden)]
+#[derive(Debug)]
+pub enum Part {
+    /// The lines of a text file whose names start with the path of the nested configuration.
+    Text(String),
+    /// The member of a json object, or of a TOML table, holding the nested configuration.
+    #[cfg(feature = "json")]
+    Json(serde_json::Value),
+}
+
+/// Contents of a save.
+#[derive(Debug)]
+enum Contents {
+    /// The configuration at `revision`, encoded for its whole file.
+    Whole {
+        revision: u64,
+        contents: ChoicesResult<Vec<u8>>,
+    },
+    /// The configuration nested at `names` within the one of the file.
+    Part {
+        names: Vec<&'static str>,
+        part: ChoicesResult<Part>,
+    },
+}
+
+/// Configuration encoded while it was accessed, to be written to its file once it's released.
+///
+/// Saves are written in the order they're created, and the ones dropped without being written
+/// are given up.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct PendingSave {
+    persistence: Arc<Persistence>,
+    path: PathBuf,
+    turn: u64,
+    contents: Contents,
+}
+
+impl PendingSave {
+    /// Creates the save of `contents`, the configuration at revision `revision` encoded for the
+    /// file `path` of `persistence`.
+    pub fn new(
+        persistence: Arc<Persistence>,
+        path: PathBuf,
+        revision: u64,
+        contents: ChoicesResult<Vec<u8>>,
+    ) -> Self {
+        Self::with_contents(persistence, path, Contents::Whole { revision, contents })
+    }
+
+    /// Creates the save of `part`, the configuration nested at `names` within the one saved to
+    /// the file `path` of `persistence`, replacing its part of the file.
+    pub fn part(
+        persistence: Arc<Persistence>,
+        path: PathBuf,
+        names: Vec<&'static str>,
+        part: ChoicesResult<Part>,
+    ) -> Self {
+        Self::with_contents(persistence, path, Contents::Part { names, part })
+    }
+
+    fn with_contents(persistence: Arc<Persistence>, path: PathBuf, contents: Contents) -> Self {
+        Self {
+            turn: persistence.take_turn(),
+            persistence,
+            path,
+            contents,
+        }
+    }
+
+    /// Writes the file once the saves created before are written, unless a later revision has
+    /// been saved meanwhile, and records the error if it fails.
+    pub fn write(self) {
+        let revision = match &self.contents {
+            Contents::Whole { revision, .. } => Some(*revision),
+            Contents::Part { .. } => None,
+        };
+        let state = self.persistence.wait_turn(self.turn);
+        if matches!(revision, Some(revision) if state.saved >= revision) {
+            return;
+        }
+        drop(state);
+        let result = match &self.contents {
+            Contents::Whole { contents, .. } => contents
+                .as_ref()
+                .map_err(Clone::clone)
+                .and_then(|contents| write_atomic(&self.path, contents)),
+            Contents::Part { names, part } => part
+                .as_ref()
+                .map_err(Clone::clone)
+                .and_then(|part| splice(&self.path, names, part))
+                .and_then(|contents| write_atomic(&self.path, &contents)),
+        };
+        let mut state = self.persistence.state();
+        match result {
+            Ok(()) => {
+                if let Some(revision) = revision {
+                    state.saved = revision;
+                }
+                state.error = None;
+            }
+            Err(err) => state.error = Some(err),
+        }
+    }
+
+    /// Writes the file in the background, on the blocking threads of the current tokio runtime
+    /// if any, or else on a new thread.
+    pub fn spawn(self) {
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(move || self.write());
+            }
+            Err(_) => {
+                std::thread::spawn(move || self.write());
+            }
+        }
+    }
+}
+
+impl Drop for PendingSave {
+    fn drop(&mut self) {
+        self.persistence.end_turn(self.turn);
+    }
+}
+
+/// Returns the contents of the file `path`, holding the configuration which `part` is nested at
+/// `names` within, with `part` replacing the values of the nested configuration. Missing files
+/// are considered empty.
+fn splice(path: &Path, names: &[&str], part: &Part) -> ChoicesResult<Vec<u8>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(io_error(err, path)),
+    };
+    match part {
+        Part::Text(lines) => {
+            let prefix = format!("{}.", names.join("."));
+            let mut spliced = String::new();
+            for line in split_escaped(std::str::from_utf8(&bytes)?, "\n", usize::MAX) {
+                if !line.is_empty() && !line.starts_with(prefix.as_str()) {
+                    spliced.push_str(line);
+                    spliced.push('\n');
+                }
+            }
+            spliced.push_str(lines);
+            Ok(spliced.into_bytes())
+        }
+        #[cfg(feature = "json")]
+        Part::Json(values) => {
+            let format = FileFormat::of(path);
+            let mut file = if bytes.is_empty() {
+                serde_json::Value::Object(Default::default())
+            } else {
+                decode_json(format, &bytes)?
+            };
+            let mut member = &mut file;
+            for name in names {
+                if !member.is_object() {
+                    *member = serde_json::Value::Object(Default::default());
+                }
+                member = member
+                    .as_object_mut()
+                    .expect("member is an object")
+                    .entry(*name)
+                    .or_insert(serde_json::Value::Null);
+            }
+            *member = values.clone();
+            encode_json(format, file)
+        }
+    }
+}
+
+/// Returns `value`, the text of a field, with its line breaks escaped for a text file.
+#[doc(hidden)]
+pub fn escape_value(value: &str) -> String {
+    escape(value, LINE_SEPARATORS)
+}
+
+/// Parses a text file made of `name=value` lines, whose line breaks are escaped, preparing each
+/// of them through `prepare`, as `patch::prepare_text()`.
+#[doc(hidden)]
+pub fn prepare_text<T>(
+    bytes: &Bytes,
+    mut prepare: impl FnMut(&str, &Bytes) -> ChoicesResult<Patch<T>>,
+) -> ChoicesResult<Vec<Patch<T>>> {
+    split_escaped(std::str::from_utf8(bytes)?, "\n", usize::MAX)
+        .into_iter()
+        .map(|line| match split_escaped(line, "\r", 2)[..] {
+            // Files may have CRLF line breaks, as long as the carriage returns aren't escaped.
+            [stripped, ""] => stripped,
+            _ => line,
+        })
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            patch::prepare_line(&unescaped_part(bytes, line, LINE_SEPARATORS), &mut prepare)
+        })
+        .collect()
+}
+
+/// Returns the error of a failed file operation, replied with `500 Internal Server Error`.
+fn io_error(err: io::Error, path: &Path) -> ChoicesError {
+    ChoicesError::custom(io::Error::new(
+        err.kind(),
+        format!("{}: {}", path.display(), err),
+    ))
+    .with_status(500)
+}
+
+/// Returns the error for a file whose format isn't enabled.
+#[doc(hidden)]
+pub fn unsupported(format: FileFormat) -> ChoicesError {
+    ChoicesError::ParseError(format!(
+        "you must enable the choices feature `{}` to use {} files",
+        format, format
+    ))
+}
+
+/// Reads the file `path`.
+#[doc(hidden)]
+pub fn read(path: &Path) -> ChoicesResult<Bytes> {
+    fs::read(path)
+        .map(Bytes::from)
+        .map_err(|err| io_error(err, path))
+}
+
+/// Writes `contents` to the file `path` atomically, through a temporary file in the same
+/// directory renamed over it.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> ChoicesResult<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io_error(io::ErrorKind::InvalidInput.into(), path))?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let write = || {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    };
+    write().map_err(|err| {
+        let _ = fs::remove_file(&temp_path);
+        io_error(err, path)
+    })
+}
+
+/// Encodes the json object `values` in the format `format`.
+#[cfg(feature = "json")]
+#[doc(hidden)]
+pub fn encode_json(format: FileFormat, values: serde_json::Value) -> ChoicesResult<Vec<u8>> {
+    match format {
+        FileFormat::Json => serde_json::to_vec_pretty(&values)
+            .map_err(|err| ChoicesError::ParseError(err.to_string())),
+        #[cfg(feature = "toml")]
+        FileFormat::Toml => toml::to_string(&without_nulls(values))
+            .map(String::into_bytes)
+            .map_err(|err| ChoicesError::ParseError(err.to_string())),
+        format => Err(unsupported(format)),
+    }
+}
+
+/// Decodes `bytes`, in the format `format`, into a json object.
+#[cfg(feature = "json")]
+#[doc(hidden)]
+pub fn decode_json(format: FileFormat, bytes: &[u8]) -> ChoicesResult<serde_json::Value> {
+    match format {
+        FileFormat::Json => {
+            serde_json::from_slice(bytes).map_err(|err| ChoicesError::ParseError(err.to_string()))
+        }
+        #[cfg(feature = "toml")]
+        FileFormat::Toml => toml::from_str(std::str::from_utf8(bytes)?)
+            .map_err(|err| ChoicesError::ParseError(err.to_string())),
+        format => Err(unsupported(format)),
+    }
+}
+
+/// Removes the null members of the objects in `value`, which TOML can't represent.
+#[cfg(feature = "toml")]
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(members) => members
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(name, value)| (name, without_nulls(value)))
+            .collect(),
+        value => value,
+    }
+}
+
+/// Returns the save of the configuration accessed through `choices`, encoded by `pending` while
+/// it's read, if it changed since it was last saved.
+fn pending_save<T, A>(choices: &A, pending: fn(&T) -> Option<PendingSave>) -> Option<PendingSave>
+where
+    A: ChoicesAccess<T>,
+{
+    match choices.read_access() {
+        Ok(config) => pending(&config),
+        Err(_) => None,
+    }
+}
+
+/// Saves the configuration accessed through `choices` in the background if it changed since it
+/// was last saved: it's encoded by `pending` while it's read, and the file is written once it's
+/// released.
+#[doc(hidden)]
+pub fn save_after<T, A>(choices: &A, pending: fn(&T) -> Option<PendingSave>)
+where
+    A: ChoicesAccess<T>,
+{
+    if let Some(pending) = pending_save(choices, pending) {
+        pending.spawn();
+    }
+}
+
+/// Returns `filter`, saving the configuration accessed through `choices` after each request, as
+/// `save_after()`, before replying. The file is written on the blocking threads of the runtime.
+/// Failed saves don't change the replies, since the changes are kept.
+#[doc(hidden)]
+pub fn after_requests<T, A, F, R>(
+    filter: F,
+    choices: A,
+    pending: fn(&T) -> Option<PendingSave>,
+) -> impl Filter<Extract = (R,), Error = Rejection> + Clone
+where
+    A: ChoicesAccess<T>,
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply + Send,
+{
+    filter.then(move |reply: R| {
+        let pending = pending_save(&choices, pending);
+        async move {
+            if let Some(pending) = pending {
+                // The save records its own errors, and panics only if the runtime shuts down.
+                let _ = tokio::task::spawn_blocking(move || pending.write()).await;
+            }
+            reply
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        assert_eq!(FileFormat::of(Path::new("a/config.json")), FileFormat::Json);
+        assert_eq!(FileFormat::of(Path::new("config.TOML")), FileFormat::Toml);
+        assert_eq!(FileFormat::of(Path::new("config.conf")), FileFormat::Text);
+        assert_eq!(FileFormat::of(Path::new("config")), FileFormat::Text);
+    }
+
+    #[test]
+    fn atomic_write() {
+        let dir = std::env::temp_dir().join(format!("choices-persist-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.txt");
+        write_atomic(&path, b"a=1\n").unwrap();
+        write_atomic(&path, b"a=2\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"a=2\n");
+        // No temporary file is left behind.
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(write_atomic(&dir.join("missing/config.txt"), b"").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pending_saves() {
+        let dir = std::env::temp_dir().join(format!("choices-pending-{}", std::process::id()));
+        let path = dir.join("config.txt");
+        let persistence = Arc::new(Persistence::new(None));
+        assert_eq!(persistence.pending(1), None);
+        persistence.persist_to(&path);
+        assert_eq!(persistence.pending(0), None);
+        assert_eq!(persistence.pending(1), Some(path.clone()));
+        let save = |revision: u64, contents: &[u8]| {
+            PendingSave::new(
+                persistence.clone(),
+                path.clone(),
+                revision,
+                Ok(contents.to_vec()),
+            )
+        };
+
+        // Failed saves are recorded and retried at the next change.
+        save(1, b"a=1\n").write();
+        assert!(persistence.flush().is_err());
+        assert_eq!(persistence.pending(1), Some(path.clone()));
+        fs::create_dir_all(&dir).unwrap();
+        save(2, b"a=2\n").spawn();
+        assert_eq!(persistence.flush(), Ok(()));
+        assert_eq!(persistence.pending(2), None);
+
+        // Earlier revisions don't overwrite later ones.
+        save(1, b"a=1\n").write();
+        assert_eq!(fs::read(&path).unwrap(), b"a=2\n");
+
+        // Saves are written in the order they're created, and the dropped ones are given up.
+        let first = save(3, b"a=3\n");
+        let dropped = save(4, b"a=4\n");
+        save(5, b"a=5\n").spawn();
+        drop(dropped);
+        first.write();
+        persistence.flush().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"a=5\n");
+        persistence.disable();
+        assert_eq!(persistence.pending(6), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parts() {
+        let dir = std::env::temp_dir().join(format!("choices-parts-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.txt");
+        let persistence = Arc::new(Persistence::new(Some(path.to_str().unwrap())));
+        let nested = Persistence::new(None);
+        nested.nest_in(&persistence, "db");
+        let files = nested.outer_files();
+        assert_eq!(files.len(), 1);
+        assert!(Arc::ptr_eq(&files[0].0, &persistence));
+        assert_eq!((&files[0].1, &files[0].2), (&path, &vec!["db"]));
+
+        // Parts replace the lines of the nested configuration and keep the others.
+        fs::write(&path, "a=1\ndb.port=1\ndb.host=x\\\ny\n").unwrap();
+        let part = Part::Text("db.port=2\n".to_string());
+        PendingSave::part(persistence.clone(), path.clone(), vec!["db"], Ok(part)).write();
+        persistence.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a=1\ndb.port=2\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn escaped_lines() {
+        let value = escape_value("a\r\nb\\n");
+        assert_eq!(value, "a\\\r\\\nb\\\\n");
+        let file = Bytes::from(format!("s={}\r\n\nt=1\n", value));
+        let patches = prepare_text(&file, |name, value| {
+            let line = format!("{}={}", name, std::str::from_utf8(value)?);
+            Ok(Box::new(move |lines: &mut Vec<String>| lines.push(line)) as Patch<_>)
+        })
+        .unwrap();
+        let mut lines = vec![];
+        for patch in patches {
+            patch(&mut lines);
+        }
+        assert_eq!(lines, vec!["s=a\r\nb\\n", "t=1"]);
+    }
+}
@@ -0,0 +1,85 @@
+//! Revisions of configuration objects, used for optimistic concurrency control.
+//!
+//! Each configuration object has a revision, incremented every time one of its fields is set,
+//! and each field records the revision at which it was last set. Revisions are exposed over
+//! HTTP as `ETag`s and checked against the `If-Match` header of modifying requests.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Revisions of a configuration object and of its fields.
+#[derive(Debug)]
+pub struct Revisions {
+    config: AtomicU64,
+    fields: Vec<AtomicU64>,
+}
+
+impl Revisions {
+    /// Creates the revisions of a configuration with `fields` fields, all starting at 0.
+    pub fn new(fields: usize) -> Self {
+        Self {
+            config: AtomicU64::new(0),
+            fields: (0..fields).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Returns the revision of the configuration.
+    pub fn config(&self) -> u64 {
+        self.config.load(Ordering::SeqCst)
+    }
+
+    /// Returns the revision at which the field `index` was last set.
+    pub fn field(&self, index: usize) -> u64 {
+        self.fields[index].load(Ordering::SeqCst)
+    }
+
+    /// Records that the field `index` has been set, returning the new revision.
+    pub fn bump(&self, index: usize) -> u64 {
+        let revision = self.config.fetch_add(1, Ordering::SeqCst) + 1;
+        self.fields[index].store(revision, Ordering::SeqCst);
+        revision
+    }
+}
+
+/// Returns the `ETag` of `revision`.
+pub fn etag(revision: u64) -> String {
+    format!("\"{}\"", revision)
+}
+
+/// Returns true if the `If-Match` header `if_match` matches `revision`, or if it's absent.
+///
+/// Tags are compared strongly, so weak tags never match, while `*` matches any revision.
+pub fn if_match(if_match: Option<&str>, revision: u64) -> bool {
+    match if_match {
+        None => true,
+        Some(tags) => tags
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag == etag(revision)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bump() {
+        let revisions = Revisions::new(2);
+        assert_eq!(revisions.bump(1), 1);
+        assert_eq!(revisions.bump(0), 2);
+        assert_eq!(revisions.config(), 2);
+        assert_eq!(revisions.field(0), 2);
+        assert_eq!(revisions.field(1), 1);
+    }
+
+    #[test]
+    fn match_tags() {
+        assert!(if_match(None, 3));
+        assert!(if_match(Some("\"3\""), 3));
+        assert!(!if_match(Some("W/\"3\""), 3));
+        assert!(if_match(Some("\"1\", \"3\""), 3));
+        assert!(if_match(Some("*"), 3));
+        assert!(!if_match(Some("\"2\""), 3));
+        assert!(!if_match(Some("3"), 3));
+    }
+}
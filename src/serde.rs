@@ -1,8 +1,10 @@
 //! Serializes and deserializes fields' value.
 
 use crate::bytes::Bytes;
-use crate::ChoicesResult;
+use crate::{ChoicesError, ChoicesResult};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt::Display;
+use std::hash::Hash;
 use std::str;
 
 /// Traits to serialize values into character sequences.
@@ -91,6 +93,294 @@ impl<'a, T: Display + ChoicesInput<'a>> ChoicesInput<'a> for Option<T> {
     }
 }
 
+/// Character escaping the separators, and itself, inside the elements of collections, maps and
+/// delimited values, so that elements containing them are read back as they were written.
+const ESCAPE: char = '\\';
+
+/// Returns `element` with the escape character and the occurrences of `separators` preceded by
+/// the escape character.
+#[doc(hidden)]
+pub fn escape(element: &str, separators: &[&str]) -> String {
+    let mut escaped = String::with_capacity(element.len());
+    let mut rest = element;
+    while let Some(c) = rest.chars().next() {
+        let len = match separator_len(rest, separators) {
+            0 if c == ESCAPE => {
+                escaped.push(ESCAPE);
+                c.len_utf8()
+            }
+            0 => c.len_utf8(),
+            len => {
+                escaped.push(ESCAPE);
+                len
+            }
+        };
+        escaped.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    escaped
+}
+
+/// Removes from `element` the escape characters added by `escape()` with `separators`. Escape
+/// characters followed by anything else are kept.
+#[doc(hidden)]
+pub fn unescape(element: &str, separators: &[&str]) -> String {
+    let mut unescaped = String::with_capacity(element.len());
+    let mut rest = element;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        let len = if c == ESCAPE {
+            escaped_len(rest, separators)
+        } else {
+            0
+        };
+        if len == 0 {
+            unescaped.push(c);
+        } else {
+            unescaped.push_str(&rest[..len]);
+            rest = &rest[len..];
+        }
+    }
+    unescaped
+}
+
+/// Splits `chars` into `limit` parts at most at the occurrences of `separator` which aren't
+/// escaped. The last part contains the remainder of `chars`, and the parts are still escaped.
+pub(crate) fn split_escaped<'a>(chars: &'a str, separator: &str, limit: usize) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while let Some(c) = chars[i..].chars().next() {
+        let rest = &chars[i..];
+        if parts.len() + 1 < limit && rest.starts_with(separator) {
+            parts.push(&chars[start..i]);
+            i += separator.len();
+            start = i;
+        } else if c == ESCAPE {
+            i += 1 + escaped_len(&rest[1..], &[separator]);
+        } else {
+            i += c.len_utf8();
+        }
+    }
+    parts.push(&chars[start..]);
+    parts
+}
+
+/// Returns the length of the escape character or of the separator among `separators` at the
+/// start of `chars`, which follows an escape character, or 0 if there's none.
+fn escaped_len(chars: &str, separators: &[&str]) -> usize {
+    if chars.starts_with(ESCAPE) {
+        ESCAPE.len_utf8()
+    } else {
+        separator_len(chars, separators)
+    }
+}
+
+/// Returns the length of the separator among `separators` at the start of `chars`, or 0 if
+/// there's none.
+fn separator_len(chars: &str, separators: &[&str]) -> usize {
+    separators
+        .iter()
+        .find(|separator| !separator.is_empty() && chars.starts_with(**separator))
+        .map_or(0, |separator| separator.len())
+}
+
+/// Returns `part`, a slice of the text of `bytes`, without the escapes of `separators`.
+pub(crate) fn unescaped_part(bytes: &Bytes, part: &str, separators: &[&str]) -> Bytes {
+    if part.contains(ESCAPE) {
+        Bytes::from(unescape(part, separators))
+    } else {
+        bytes.slice_ref(part.as_bytes())
+    }
+}
+
+/// Separator between the elements of a collection.
+const COLLECTION_SEPARATOR: &str = ",";
+
+/// Implements `ChoicesInput` and `ChoicesOutput` for collections, whose elements are separated
+/// by commas. Commas inside the elements are escaped by a backslash.
+macro_rules! collection_choices_impl {
+    ($ty:ident $(, $bound:path)*) => {
+        impl<T: ChoicesOutput> ChoicesOutput for $ty<T> {
+            fn body_string(&self) -> String {
+                self.iter()
+                    .map(|item| escape(&item.body_string(), &[COLLECTION_SEPARATOR]))
+                    .collect::<Vec<_>>()
+                    .join(COLLECTION_SEPARATOR)
+            }
+        }
+
+        impl<T: for<'a> ChoicesInput<'a> $(+ $bound)*> ChoicesInput<'_> for $ty<T> {
+            fn from_chars(bytes: &Bytes) -> ChoicesResult<Self> {
+                if bytes.is_empty() {
+                    Ok(Self::default())
+                } else {
+                    let chars = str::from_utf8(bytes)?;
+                    split_escaped(chars, COLLECTION_SEPARATOR, usize::MAX)
+                        .into_iter()
+                        .map(|item| {
+                            T::from_chars(&unescaped_part(bytes, item, &[COLLECTION_SEPARATOR]))
+                        })
+                        .collect()
+                }
+            }
+        }
+    };
+}
+
+collection_choices_impl! {Vec}
+collection_choices_impl! {VecDeque}
+collection_choices_impl! {HashSet, Eq, Hash}
+
+/// Separator between the key and the value of an entry of a map.
+const ENTRY_SEPARATOR: &str = "=";
+
+/// Separators escaped inside the keys and the values of maps.
+const MAP_SEPARATORS: &[&str] = &[COLLECTION_SEPARATOR, ENTRY_SEPARATOR];
+
+/// Implements `ChoicesInput` and `ChoicesOutput` for maps, whose entries are represented as
+/// `key=value` and separated by commas. Commas and equal signs inside the keys and the values are
+/// escaped by a backslash.
+macro_rules! map_choices_impl {
+    ($ty:ident $(, $bound:path)*) => {
+        impl<K: ChoicesOutput, V: ChoicesOutput> ChoicesOutput for $ty<K, V> {
+            fn body_string(&self) -> String {
+                self.iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "{}{}{}",
+                            escape(&key.body_string(), MAP_SEPARATORS),
+                            ENTRY_SEPARATOR,
+                            escape(&value.body_string(), MAP_SEPARATORS)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(COLLECTION_SEPARATOR)
+            }
+        }
+
+        impl<K, V> ChoicesInput<'_> for $ty<K, V>
+        where
+            K: for<'a> ChoicesInput<'a> $(+ $bound)*,
+            V: for<'a> ChoicesInput<'a>,
+        {
+            fn from_chars(bytes: &Bytes) -> ChoicesResult<Self> {
+                if bytes.is_empty() {
+                    return Ok(Self::default());
+                }
+                let chars = str::from_utf8(bytes)?;
+                split_escaped(chars, COLLECTION_SEPARATOR, usize::MAX)
+                    .into_iter()
+                    .map(|entry| match split_escaped(entry, ENTRY_SEPARATOR, 2)[..] {
+                        [key, value] => Ok((
+                            K::from_chars(&unescaped_part(bytes, key, MAP_SEPARATORS))?,
+                            V::from_chars(&unescaped_part(bytes, value, MAP_SEPARATORS))?,
+                        )),
+                        _ => Err(ChoicesError::ParseError(format!(
+                            "expected `key=value`, found `{}`",
+                            entry
+                        ))),
+                    })
+                    .collect()
+            }
+        }
+    };
+}
+
+map_choices_impl! {HashMap, Eq, Hash}
+map_choices_impl! {BTreeMap, Ord}
+
+/// Splits `chars` into exactly `count` fields separated by `delimiter`, removing the escapes of
+/// the delimiters inside the fields.
+///
+/// The last field contains the remainder of `chars`, unescaped delimiters included. `None`
+/// stands for no fields at all.
+#[doc(hidden)]
+pub fn split_fields(
+    chars: Option<&str>,
+    count: usize,
+    delimiter: &str,
+) -> ChoicesResult<Vec<Bytes>> {
+    let parts: Vec<Bytes> = match chars {
+        Some("") if count == 0 => Vec::new(),
+        Some(chars) => split_escaped(chars, delimiter, count.max(1))
+            .into_iter()
+            .map(|part| Bytes::from(unescape(part, &[delimiter])))
+            .collect(),
+        None => Vec::new(),
+    };
+    if parts.len() == count {
+        Ok(parts)
+    } else {
+        Err(ChoicesError::ParseError(format!(
+            "expected {} values separated by `{}`",
+            count, delimiter
+        )))
+    }
+}
+
+/// Returns the variant name and the fields of a delimited enum value `chars`, which are separated
+/// by the first `delimiter` which isn't escaped.
+#[doc(hidden)]
+pub fn split_variant<'a>(chars: &'a str, delimiter: &str) -> (&'a str, Option<&'a str>) {
+    match split_escaped(chars, delimiter, 2)[..] {
+        [variant, fields] => (variant, Some(fields)),
+        _ => (chars, None),
+    }
+}
+
+/// Trait for types whose values are restricted to a fixed set of named variants.
+///
+/// The variants are listed in the configuration's index, next to the fields of such type.
+pub trait ChoicesVariants {
+    /// Names of all the allowed variants.
+    const VARIANTS: &'static [&'static str];
+}
+
+impl<T: ChoicesVariants> ChoicesVariants for Option<T> {
+    const VARIANTS: &'static [&'static str] = T::VARIANTS;
+}
+
+/// Retrieves the variants of `T` if it implements `ChoicesVariants`.
+///
+/// Must be used as `(&VariantsOf::<T>::new()).variants()` with both `HasVariants` and
+/// `NoVariants` in scope.
+#[doc(hidden)]
+pub struct VariantsOf<T>(std::marker::PhantomData<T>);
+
+impl<T> VariantsOf<T> {
+    #[doc(hidden)]
+    pub fn new() -> Self {
+        Self(std::marker::PhantomData)
+    }
+}
+
+impl<T> Default for VariantsOf<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[doc(hidden)]
+pub trait HasVariants {
+    fn variants(&self) -> Option<&'static [&'static str]>;
+}
+
+impl<T: ChoicesVariants> HasVariants for VariantsOf<T> {
+    fn variants(&self) -> Option<&'static [&'static str]> {
+        Some(T::VARIANTS)
+    }
+}
+
+#[doc(hidden)]
+pub trait NoVariants {
+    fn variants(&self) -> Option<&'static [&'static str]> {
+        None
+    }
+}
+
+impl<T> NoVariants for &VariantsOf<T> {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,4 +405,91 @@ mod tests {
         let buf = BytesMut::with_capacity(16);
         assert_eq!(Option::<u16>::from_chars(&buf.freeze()), Ok(None));
     }
+
+    #[test]
+    #[allow(clippy::needless_borrow)]
+    fn variants_of() {
+        struct Mode;
+
+        impl ChoicesVariants for Mode {
+            const VARIANTS: &'static [&'static str] = &["fast", "safe"];
+        }
+
+        assert_eq!(
+            (&VariantsOf::<Mode>::new()).variants(),
+            Some(&["fast", "safe"][..])
+        );
+        assert_eq!(
+            (&VariantsOf::<Option<Mode>>::new()).variants(),
+            Some(&["fast", "safe"][..])
+        );
+        assert_eq!((&VariantsOf::<u16>::new()).variants(), None);
+    }
+
+    #[test]
+    fn vec_from_chars() {
+        assert_eq!(
+            Vec::<u8>::from_chars(&Bytes::from("1,2,3")),
+            Ok(vec![1, 2, 3])
+        );
+        assert_eq!(Vec::<u8>::from_chars(&Bytes::new()), Ok(vec![]));
+        assert!(Vec::<u8>::from_chars(&Bytes::from("1,,3")).is_err());
+        assert_eq!(vec![1, 2, 3].body_string(), "1,2,3");
+    }
+
+    #[test]
+    fn map_from_chars() {
+        let map = BTreeMap::<String, u8>::from_chars(&Bytes::from("a=1,b=2")).unwrap();
+        assert_eq!(map.body_string(), "a=1,b=2");
+        assert!(BTreeMap::<String, u8>::from_chars(&Bytes::from("a")).is_err());
+    }
+
+    #[test]
+    fn escaped_separators() {
+        let hosts = vec!["a,b".to_string(), r"c\d".to_string(), "e".to_string()];
+        assert_eq!(hosts.body_string(), r"a\,b,c\\d,e");
+        assert_eq!(
+            Vec::<String>::from_chars(&Bytes::from(hosts.body_string())),
+            Ok(hosts)
+        );
+        // Backslashes which don't escape anything are kept.
+        assert_eq!(
+            Vec::<String>::from_chars(&Bytes::from(r"c:\dir,e")),
+            Ok(vec![r"c:\dir".to_string(), "e".to_string()])
+        );
+
+        let mut map = BTreeMap::new();
+        map.insert("a=b".to_string(), "c,d".to_string());
+        assert_eq!(map.body_string(), r"a\=b=c\,d");
+        assert_eq!(
+            BTreeMap::from_chars(&Bytes::from(map.body_string())),
+            Ok(map)
+        );
+
+        // Nested separators are escaped once per level.
+        let nested = vec![escape("a.b", &["."]), "c".to_string()];
+        assert_eq!(nested.body_string(), r"a\\.b,c");
+        assert_eq!(
+            Vec::<String>::from_chars(&Bytes::from(nested.body_string())),
+            Ok(nested)
+        );
+        assert_eq!(
+            split_fields(Some(r"a\.b.c"), 2, "."),
+            Ok(vec![Bytes::from("a.b"), Bytes::from("c")])
+        );
+        assert_eq!(split_variant(r"rect\,a,b", ","), (r"rect\,a", Some("b")));
+    }
+
+    #[test]
+    fn split_fields_count() {
+        assert_eq!(
+            split_fields(Some("1.2.3"), 2, "."),
+            Ok(vec![Bytes::from("1"), Bytes::from("2.3")])
+        );
+        assert_eq!(split_fields(Some(""), 1, "."), Ok(vec![Bytes::new()]));
+        assert_eq!(split_fields(None, 0, "."), Ok(vec![]));
+        assert!(split_fields(Some("1"), 2, ".").is_err());
+        assert!(split_fields(None, 1, ".").is_err());
+        assert!(split_fields(Some("1"), 0, ".").is_err());
+    }
 }
@@ -0,0 +1,247 @@
+//! Control of the configuration http server.
+//!
+//! Servers started by `Choices::spawn()` run in a background task and are stopped gracefully
+//! through their `ServerHandle`: requests in flight are completed before the server ends.
+//!
+//! On Unix, servers can also listen on a Unix domain socket instead of a TCP port.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+#[cfg(unix)]
+use std::{io, path::Path};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use warp::filters::BoxedFilter;
+use warp::Reply;
+
+/// Filter of a whole configuration, whose replies are boxed so that it has the same type for
+/// every configuration.
+#[doc(hidden)]
+pub type ConfigFilter = BoxedFilter<(Box<dyn Reply>,)>;
+
+/// Handle of a configuration http server running in the background.
+///
+/// Dropping the handle doesn't stop the server.
+#[derive(Debug)]
+pub struct ServerHandle {
+    addr: SocketAddr,
+    shutdown: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+impl ServerHandle {
+    /// Binds the server of `filter` to `addr` and spawns it on the current tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address can't be bound or if called outside of a tokio runtime.
+    pub(crate) fn spawn(filter: ConfigFilter, addr: impl Into<SocketAddr>) -> Self {
+        let shutdown = Arc::new(Notify::new());
+        let signal = {
+            let shutdown = shutdown.clone();
+            async move { shutdown.notified().await }
+        };
+        let addr: SocketAddr = addr.into();
+        let (addr, server) = warp::serve(filter).bind_with_graceful_shutdown(addr, signal);
+        Self {
+            addr,
+            shutdown,
+            task: tokio::spawn(server),
+        }
+    }
+
+    /// Returns the address the server is bound to, which tells the actual port when binding to
+    /// port 0.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Asks the server to stop accepting connections and to end once the requests in flight are
+    /// completed. Use `join()` to wait for its end.
+    pub fn shutdown(&self) {
+        // The permit is stored if the server isn't waiting for it yet.
+        self.shutdown.notify_one();
+    }
+
+    /// Waits until the server ends.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the panic of the server, if any.
+    pub async fn join(self) {
+        if let Err(err) = self.task.await {
+            if err.is_panic() {
+                std::panic::resume_unwind(err.into_panic());
+            }
+        }
+    }
+}
+
+/// Serves `filter` on the Unix domain socket at `path`, whose file gets the permissions `mode`.
+///
+/// A stale socket file at `path`, on which no server is listening, is replaced. Fails with the
+/// error of accepting a connection, unless it's only caused by that connection.
+#[cfg(unix)]
+pub(crate) async fn serve_unix(filter: ConfigFilter, path: &Path, mode: u32) -> io::Result<()> {
+    use std::task::Poll;
+
+    remove_stale_socket(path)?;
+    let listener = bind_unix(path, mode)?;
+    let error = Arc::new(std::sync::Mutex::new(None));
+    let incoming = {
+        let error = error.clone();
+        futures_util::stream::poll_fn(move |cx| loop {
+            match futures_util::ready!(listener.poll_accept(cx)) {
+                Ok((stream, _)) => return Poll::Ready(Some(Ok::<_, io::Error>(stream))),
+                Err(err) if is_connection_error(&err) => continue,
+                // Ending the stream stops the server.
+                Err(err) => {
+                    *error.lock().unwrap_or_else(|err| err.into_inner()) = Some(err);
+                    return Poll::Ready(None);
+                }
+            }
+        })
+    };
+    warp::serve(filter).run_incoming(incoming).await;
+    let error = error.lock().unwrap_or_else(|err| err.into_inner()).take();
+    match error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Binds the Unix domain socket at `path` with the permissions `mode`.
+///
+/// The socket is bound in a directory only the user of the process can access, and moved to
+/// `path` once it has its permissions, so that it can't be reached with the default ones.
+#[cfg(unix)]
+fn bind_unix(path: &Path, mode: u32) -> io::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} isn't a file path", path.display()),
+        )
+    })?;
+    let dir = path.with_file_name(format!(
+        ".{}.{}",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let private_path = dir.join(name);
+    let result = tokio::net::UnixListener::bind(&private_path).and_then(|listener| {
+        std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(mode))?;
+        std::fs::rename(&private_path, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+/// Tells if `err`, raised accepting a connection, concerns only that connection, so that the
+/// server can keep accepting the next ones.
+#[cfg(unix)]
+fn is_connection_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+    )
+}
+
+/// Removes the socket file at `path` if no server is listening on it.
+///
+/// Fails if `path` is a file other than a socket, or if a server is listening on it.
+#[cfg(unix)]
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("a server is listening on {}", path.display()),
+                ))
+            } else {
+                std::fs::remove_file(path)
+            }
+        }
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and isn't a socket", path.display()),
+        )),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the filter of a configuration with its replies boxed.
+#[doc(hidden)]
+pub fn boxed_filter<R: Reply + 'static>(filter: BoxedFilter<(R,)>) -> ConfigFilter {
+    use warp::Filter;
+    filter
+        .map(|reply: R| Box::new(reply) as Box<dyn Reply>)
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Filter;
+
+    #[tokio::test]
+    async fn shutdown() {
+        let filter = boxed_filter(warp::any().map(|| "ok").boxed());
+        let handle = ServerHandle::spawn(filter, ([127, 0, 0, 1], 0));
+        assert_ne!(handle.addr().port(), 0);
+        handle.shutdown();
+        handle.join().await;
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn stale_sockets() {
+        let dir = std::env::temp_dir().join(format!("choices-stale-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.sock");
+
+        assert!(remove_stale_socket(&path).is_ok());
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert_eq!(
+            remove_stale_socket(&path).unwrap_err().kind(),
+            io::ErrorKind::AddrInUse
+        );
+        drop(listener);
+        assert!(remove_stale_socket(&path).is_ok());
+        assert!(!path.exists());
+
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(
+            remove_stale_socket(&path).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn private_binds() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("choices-bind-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.sock");
+
+        let _listener = bind_unix(&path, 0o600).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(std::os::unix::net::UnixStream::connect(&path).is_ok());
+        // The private directory is removed once the socket is moved.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,354 @@
+//! Signature of the requests modifying a configuration.
+//!
+//! Configurations with `#[choices(signature = expr)]` accept the requests modifying them only if
+//! they're signed with the shared key of the `Signature` value returned by `expr`. A request is
+//! signed by sending the headers:
+//!
+//! - `X-Choices-Timestamp`: the Unix time of the request, in seconds;
+//! - `X-Choices-Nonce`: a value never used before with the same key;
+//! - `X-Choices-Signature`: the hex-encoded HMAC-SHA256 of the method, the path, the timestamp,
+//!   the nonce and the body of the request, each but the body followed by a newline.
+//!
+//! Requests whose signature is wrong, whose timestamp is too far from the time of the server or
+//! whose nonce was already used are replied with `401 Unauthorized`.
+
+use crate::error::{ChoicesError, ChoicesResult};
+use crate::negotiate::{error_reply, Format};
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use warp::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use warp::path::FullPath;
+use warp::{Filter, Rejection, Reply};
+
+/// Header carrying the Unix time of a signed request, in seconds.
+pub const TIMESTAMP_HEADER: &str = "x-choices-timestamp";
+/// Header carrying the nonce of a signed request.
+pub const NONCE_HEADER: &str = "x-choices-nonce";
+/// Header carrying the signature of a signed request.
+pub const SIGNATURE_HEADER: &str = "x-choices-signature";
+/// Maximum difference between the timestamp of a request and the time of the server, unless set
+/// with `Signature::max_age()`.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(300);
+/// Maximum length of a nonce.
+pub const MAX_NONCE_LEN: usize = 128;
+
+/// HMAC-SHA256 signature of requests with a shared key.
+///
+/// The nonces of the verified requests are remembered as long as their timestamp is valid, so
+/// that the requests can't be replayed.
+pub struct Signature {
+    key: Vec<u8>,
+    max_age: Duration,
+    nonces: Mutex<HashMap<String, u64>>,
+}
+
+impl Signature {
+    /// Returns the signature with the shared key `key`.
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key: key.into(),
+            max_age: DEFAULT_MAX_AGE,
+            nonces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the maximum difference between the timestamp of a request and the time of the
+    /// server.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Returns the hex-encoded signature of the request of method `method` to the path `path`,
+    /// with the timestamp `timestamp`, the nonce `nonce` and the body `body`.
+    pub fn sign(
+        &self,
+        method: &str,
+        path: &str,
+        timestamp: u64,
+        nonce: &str,
+        body: &[u8],
+    ) -> String {
+        self.mac(method, path, timestamp, nonce, body)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Returns the headers signing the request of method `method` to the path `path`, with the
+    /// nonce `nonce` and the body `body`, sent now.
+    pub fn headers(
+        &self,
+        method: &str,
+        path: &str,
+        nonce: &str,
+        body: &[u8],
+    ) -> [(&'static str, String); 3] {
+        let timestamp = now();
+        [
+            (TIMESTAMP_HEADER, timestamp.to_string()),
+            (NONCE_HEADER, nonce.to_string()),
+            (
+                SIGNATURE_HEADER,
+                self.sign(method, path, timestamp, nonce, body),
+            ),
+        ]
+    }
+
+    /// Verifies the signature of the request of method `method` to the path `path`, with the
+    /// headers `headers` and the body `body`.
+    ///
+    /// The nonce of the request is remembered only if the request is valid.
+    pub fn verify(
+        &self,
+        method: &Method,
+        path: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> ChoicesResult<()> {
+        let timestamp: u64 = header_value(headers, TIMESTAMP_HEADER)?
+            .parse()
+            .map_err(|_| ChoicesError::Unauthorized("malformed timestamp".to_string()))?;
+        let nonce = header_value(headers, NONCE_HEADER)?;
+        if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+            return Err(ChoicesError::Unauthorized("malformed nonce".to_string()));
+        }
+        let signature = decode_hex(header_value(headers, SIGNATURE_HEADER)?)
+            .ok_or_else(|| ChoicesError::Unauthorized("malformed signature".to_string()))?;
+        self.mac(method.as_str(), path, timestamp, nonce, body)
+            .verify_slice(&signature)
+            .map_err(|_| ChoicesError::Unauthorized("invalid signature".to_string()))?;
+
+        let now = now();
+        let max_age = self.max_age.as_secs();
+        if timestamp.abs_diff(now) > max_age {
+            return Err(ChoicesError::Unauthorized("stale timestamp".to_string()));
+        }
+        let mut nonces = self.nonces.lock().unwrap_or_else(|err| err.into_inner());
+        // Nonces with a stale timestamp can be forgotten, since their requests are rejected
+        // anyway.
+        nonces.retain(|_, timestamp| timestamp.abs_diff(now) <= max_age);
+        if nonces.contains_key(nonce) {
+            return Err(ChoicesError::Unauthorized("reused nonce".to_string()));
+        }
+        nonces.insert(nonce.to_string(), timestamp);
+        Ok(())
+    }
+
+    /// Returns the HMAC of the request, not finalized.
+    fn mac(
+        &self,
+        method: &str,
+        path: &str,
+        timestamp: u64,
+        nonce: &str,
+        body: &[u8],
+    ) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}\n{}\n{}\n{}\n", method, path, timestamp, nonce).as_bytes());
+        mac.update(body);
+        mac
+    }
+}
+
+/// Returns the current Unix time, in seconds.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
+/// Returns the value of the header `name` of `headers`.
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> ChoicesResult<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| ChoicesError::Unauthorized(format!("missing header `{}`", name)))
+}
+
+/// Decodes the hex string `hex`.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Rejection of a request whose signature is invalid.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct InvalidSignature {
+    err: ChoicesError,
+    format: Format,
+}
+
+impl warp::reject::Reject for InvalidSignature {}
+
+/// Returns the filter verifying the signature of a request through `signature`, if any, and
+/// extracting its body. Requests with an invalid signature are rejected, and must be recovered
+/// with `recover`. `json` tells the format of the configuration.
+#[doc(hidden)]
+pub fn body(
+    signature: Option<Arc<Signature>>,
+    json: bool,
+) -> impl Filter<Extract = (Bytes,), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .and(warp::header::headers_cloned())
+        .and(crate::negotiate::accept(json))
+        .and(warp::body::bytes())
+        .and_then(
+            move |method: Method,
+                  path: FullPath,
+                  headers: HeaderMap,
+                  format: Format,
+                  body: Bytes| {
+                let signature = signature.clone();
+                async move {
+                    match signature {
+                        Some(signature) => signature
+                            .verify(&method, path.as_str(), &headers, &body)
+                            .map(|_| body)
+                            .map_err(|err| warp::reject::custom(InvalidSignature { err, format })),
+                        None => Ok(body),
+                    }
+                }
+            },
+        )
+}
+
+/// Returns the filter verifying the signature of a request without body through `signature`, if
+/// any. Requests with an invalid signature are rejected, and must be recovered with `recover`.
+/// `json` tells the format of the configuration.
+#[doc(hidden)]
+pub fn verify(
+    signature: Option<Arc<Signature>>,
+    json: bool,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .and(warp::header::headers_cloned())
+        .and(crate::negotiate::accept(json))
+        .and_then(
+            move |method: Method, path: FullPath, headers: HeaderMap, format: Format| {
+                let signature = signature.clone();
+                async move {
+                    match signature {
+                        Some(signature) => signature
+                            .verify(&method, path.as_str(), &headers, &[])
+                            .map_err(|err| warp::reject::custom(InvalidSignature { err, format })),
+                        None => Ok(()),
+                    }
+                }
+            },
+        )
+        .untuple_one()
+}
+
+/// Replies `401 Unauthorized` to requests whose signature is invalid.
+#[doc(hidden)]
+pub async fn recover(rejection: Rejection) -> Result<impl Reply, Rejection> {
+    match rejection.find::<InvalidSignature>() {
+        Some(InvalidSignature { err, format }) => {
+            let mut response =
+                error_reply(*format, err, None, None, StatusCode::UNAUTHORIZED).into_response();
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("HMAC realm=\"choices\""),
+            );
+            Ok(response)
+        }
+        None => Err(rejection),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(signature: &Signature, timestamp: u64, nonce: &str, body: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let sign = signature.sign("PUT", "/config/debug", timestamp, nonce, body);
+        headers.insert(TIMESTAMP_HEADER, HeaderValue::from(timestamp));
+        headers.insert(NONCE_HEADER, HeaderValue::from_str(nonce).unwrap());
+        headers.insert(SIGNATURE_HEADER, HeaderValue::from_str(&sign).unwrap());
+        headers
+    }
+
+    fn verify(signature: &Signature, headers: &HeaderMap, body: &[u8]) -> ChoicesResult<()> {
+        signature.verify(&Method::PUT, "/config/debug", headers, body)
+    }
+
+    #[test]
+    fn sign() {
+        // Reference value computed with `openssl dgst -sha256 -hmac key`.
+        assert_eq!(
+            Signature::new("key").sign("PUT", "/config/debug", 1700000000, "n", b"true"),
+            "09e3d0d0256b4d67db23ca51416cfb8c6dcd91d62f9cfbd82ba0904315231d78"
+        );
+    }
+
+    #[test]
+    fn verify_requests() {
+        let signature = Signature::new("key");
+        let valid = headers(&signature, now(), "a", b"true");
+        assert_eq!(verify(&signature, &valid, b"true"), Ok(()));
+        assert_eq!(
+            verify(&signature, &valid, b"true"),
+            Err(ChoicesError::Unauthorized("reused nonce".to_string()))
+        );
+
+        let tampered = headers(&signature, now(), "b", b"true");
+        assert_eq!(
+            verify(&signature, &tampered, b"false"),
+            Err(ChoicesError::Unauthorized("invalid signature".to_string()))
+        );
+        // The nonce of an invalid request isn't used up.
+        assert_eq!(verify(&signature, &tampered, b"true"), Ok(()));
+
+        let other_key = headers(&Signature::new("other"), now(), "c", b"true");
+        assert!(verify(&signature, &other_key, b"true").is_err());
+
+        let stale = headers(&signature, now() - 301, "d", b"true");
+        assert_eq!(
+            verify(&signature, &stale, b"true"),
+            Err(ChoicesError::Unauthorized("stale timestamp".to_string()))
+        );
+        let future = headers(&signature, now() + 301, "e", b"true");
+        assert!(verify(&signature, &future, b"true").is_err());
+
+        assert_eq!(
+            verify(&signature, &HeaderMap::new(), b"true"),
+            Err(ChoicesError::Unauthorized(
+                "missing header `x-choices-timestamp`".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn max_age() {
+        let signature = Signature::new("key").max_age(Duration::from_secs(10));
+        let stale = headers(&signature, now() - 11, "a", b"");
+        assert!(verify(&signature, &stale, b"").is_err());
+        let recent = headers(&signature, now() - 9, "a", b"");
+        assert!(verify(&signature, &recent, b"").is_ok());
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(decode_hex("00ff1A"), Some(vec![0, 255, 26]));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("é0"), None);
+    }
+}
@@ -0,0 +1,96 @@
+//! State of configuration objects, such as their revisions.
+//!
+//! Every struct deriving `Choices` has a field of type `State`, where the derived code keeps the
+//! revisions of the object, the channels of its events and the file where it's saved. The state
+//! belongs to the object: it's moved and dropped along with it, and every nested configuration
+//! has its own.
+
+use crate::events::Events;
+use crate::persist::Persistence;
+use crate::revision::Revisions;
+use crate::watch::Watches;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+/// State of a configuration object, created empty and filled by the derived code as needed.
+///
+/// Clones are empty, so that every configuration object has its own state.
+#[derive(Default)]
+pub struct State {
+    revisions: OnceLock<Arc<Revisions>>,
+    watches: OnceLock<Arc<Watches>>,
+    events: OnceLock<Arc<Events>>,
+    persistence: OnceLock<Arc<Persistence>>,
+}
+
+impl State {
+    /// Creates an empty state.
+    pub const fn new() -> Self {
+        Self {
+            revisions: OnceLock::new(),
+            watches: OnceLock::new(),
+            events: OnceLock::new(),
+            persistence: OnceLock::new(),
+        }
+    }
+
+    /// Returns the revisions of the configuration, which has `fields` fields.
+    #[doc(hidden)]
+    pub fn revisions(&self, fields: usize) -> Arc<Revisions> {
+        self.revisions
+            .get_or_init(|| Arc::new(Revisions::new(fields)))
+            .clone()
+    }
+
+    /// Returns the watch channels of the fields of the configuration, which has `fields` fields.
+    #[doc(hidden)]
+    pub fn watches(&self, fields: usize) -> Arc<Watches> {
+        self.watches
+            .get_or_init(|| Arc::new(Watches::new(fields)))
+            .clone()
+    }
+
+    /// Returns the channel of the events of the configuration.
+    #[doc(hidden)]
+    pub fn events(&self) -> Arc<Events> {
+        self.events.get_or_init(Default::default).clone()
+    }
+
+    /// Returns the persistence of the configuration, to the file `path` at first.
+    #[doc(hidden)]
+    pub fn persistence(&self, path: Option<&str>) -> Arc<Persistence> {
+        self.persistence
+            .get_or_init(|| Arc::new(Persistence::new(path)))
+            .clone()
+    }
+}
+
+impl Clone for State {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("State").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owned() {
+        let state = State::new();
+        state.revisions(2).bump(1);
+        // The state is moved along with its object.
+        let moved = Box::new(state);
+        assert_eq!(moved.revisions(2).config(), 1);
+        assert_eq!(moved.revisions(2).field(1), 1);
+        // Clones and new objects have their own states.
+        assert_eq!(moved.clone().revisions(2).config(), 0);
+        assert_eq!(State::new().revisions(2).config(), 0);
+    }
+}
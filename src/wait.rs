@@ -0,0 +1,218 @@
+//! Long polling of configuration fields.
+//!
+//! A GET request of a field can wait for the field to be set past a given revision, using the
+//! query `?wait=<revision>&timeout=<duration>`.
+
+use crate::error::{ChoicesError, ChoicesResult};
+use crate::events::Events;
+use crate::negotiate::{error_reply, Format};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use warp::{http::StatusCode, Rejection, Reply};
+
+/// Time a request waits for a change when no timeout is given.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Maximum time a request can wait for a change.
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Parameters of a request waiting for a field to change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Wait {
+    /// The request waits until the field is set at a revision greater than this one.
+    pub revision: u64,
+    /// Maximum time the request waits.
+    pub timeout: Duration,
+}
+
+impl Wait {
+    /// Reads the parameters from the parameters `query` of a request, returning `None` if the
+    /// request doesn't wait.
+    pub fn from_query(query: &HashMap<String, String>) -> ChoicesResult<Option<Self>> {
+        let revision = match query.get("wait") {
+            Some(revision) => revision.parse()?,
+            None => return Ok(None),
+        };
+        let timeout = match query.get("timeout") {
+            Some(timeout) => parse_duration(timeout)?.min(MAX_TIMEOUT),
+            None => DEFAULT_TIMEOUT,
+        };
+        Ok(Some(Self { revision, timeout }))
+    }
+
+    /// Waits until the field `field`, whose current revision is returned by `revision`, is set
+    /// past the awaited revision or the timeout elapses.
+    ///
+    /// `events` is the channel of the configuration the field belongs to.
+    pub async fn changed(&self, events: &Events, field: &str, revision: impl Fn() -> u64) {
+        // Subscribe before checking the revision, so that no change can be missed.
+        let mut receiver = events.subscribe();
+        if revision() > self.revision {
+            return;
+        }
+        let _ = tokio::time::timeout(self.timeout, async {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        if event.field == field && event.revision > self.revision {
+                            return;
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => {
+                        if revision() > self.revision {
+                            return;
+                        }
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        })
+        .await;
+    }
+}
+
+/// Rejection of a request whose wait parameters are invalid, replied in the format `format`.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct InvalidWait {
+    err: ChoicesError,
+    format: Format,
+}
+
+impl warp::reject::Reject for InvalidWait {}
+
+/// Waits as requested by the parameters `query` of a GET request of the field `field`, whose
+/// current revision is returned by `revision`. Invalid parameters are replied in the format
+/// `format`.
+#[doc(hidden)]
+pub async fn wait_for_query(
+    query: HashMap<String, String>,
+    format: Format,
+    events: &Events,
+    field: &str,
+    revision: impl Fn() -> u64,
+) -> Result<(), Rejection> {
+    match Wait::from_query(&query) {
+        Ok(Some(wait)) => {
+            wait.changed(events, field, revision).await;
+            Ok(())
+        }
+        Ok(None) => Ok(()),
+        Err(err) => Err(warp::reject::custom(InvalidWait { err, format })),
+    }
+}
+
+/// Replies `400 Bad Request` to requests whose wait parameters are invalid.
+#[doc(hidden)]
+pub async fn recover(rejection: Rejection) -> Result<impl Reply, Rejection> {
+    match rejection.find::<InvalidWait>() {
+        Some(InvalidWait { err, format }) => Ok(error_reply(
+            *format,
+            err,
+            None,
+            None,
+            StatusCode::BAD_REQUEST,
+        )),
+        None => Err(rejection),
+    }
+}
+
+/// Parses a duration made of a number followed by the unit `ms`, `s` or `m`. Numbers without
+/// unit are seconds.
+pub fn parse_duration(duration: &str) -> ChoicesResult<Duration> {
+    let (value, unit) = duration.split_at(
+        duration
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(duration.len()),
+    );
+    let value: u64 = value.parse()?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "" | "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value.saturating_mul(60))),
+        _ => Err(ChoicesError::ParseError(format!(
+            "invalid duration `{}`",
+            duration
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Event;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("2h").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn query() {
+        let query = |pairs: &[(&str, &str)]| {
+            Wait::from_query(
+                &pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            )
+        };
+        assert_eq!(query(&[]), Ok(None));
+        assert_eq!(
+            query(&[("wait", "3")]),
+            Ok(Some(Wait {
+                revision: 3,
+                timeout: DEFAULT_TIMEOUT
+            }))
+        );
+        assert_eq!(
+            query(&[("wait", "3"), ("timeout", "1h")])
+                .unwrap_err()
+                .to_string(),
+            "ParseError: invalid duration `1h`"
+        );
+        assert_eq!(
+            query(&[("wait", "3"), ("timeout", "60m")]),
+            Ok(Some(Wait {
+                revision: 3,
+                timeout: MAX_TIMEOUT
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn wake_on_change() {
+        let events: &'static Events = Box::leak(Box::new(Events::new()));
+        let wait = Wait {
+            revision: 1,
+            timeout: Duration::from_secs(30),
+        };
+        let waiter = tokio::spawn(async move { wait.changed(events, "port", || 1).await });
+        while !events.is_observed() {
+            tokio::task::yield_now().await;
+        }
+        events.send(Event {
+            field: "host".to_string(),
+            value: "remote".to_string(),
+            revision: 2,
+        });
+        events.send(Event {
+            field: "port".to_string(),
+            value: "80".to_string(),
+            revision: 3,
+        });
+        waiter.await.unwrap();
+
+        // Without changes, the request waits until the timeout.
+        let wait = Wait {
+            revision: 1,
+            timeout: Duration::from_millis(10),
+        };
+        wait.changed(events, "port", || 1).await;
+    }
+}
@@ -0,0 +1,112 @@
+//! Subscriptions to the values of single configuration fields.
+//!
+//! Each field of each configuration object has a `tokio::sync::watch` channel, created by the
+//! first call of the generated `subscribe_<field>()`, to which the new values are sent every time
+//! the field is set.
+
+use std::any::Any;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::watch;
+
+/// Watch channels of the fields of a configuration object.
+pub struct Watches {
+    fields: Vec<FieldWatch>,
+}
+
+impl Watches {
+    /// Creates the channels of a configuration with `fields` fields, without subscribers.
+    pub fn new(fields: usize) -> Self {
+        Self {
+            fields: (0..fields).map(|_| FieldWatch::new()).collect(),
+        }
+    }
+
+    /// Returns the channel of the field `index`.
+    pub fn field(&self, index: usize) -> &FieldWatch {
+        &self.fields[index]
+    }
+}
+
+/// Watch channel of a configuration field.
+#[derive(Default)]
+pub struct FieldWatch {
+    channel: OnceLock<Channel>,
+}
+
+/// Function sending the value of the field in a configuration to the subscribers.
+type Notify = Box<dyn Fn(&dyn Any) + Send + Sync>;
+
+struct Channel {
+    sender: Arc<dyn Any + Send + Sync>,
+    notify: Notify,
+}
+
+impl FieldWatch {
+    /// Creates a watch without subscribers.
+    pub const fn new() -> Self {
+        Self {
+            channel: OnceLock::new(),
+        }
+    }
+
+    /// Sends the value of the field in `config` to the subscribers, if there has ever been any.
+    pub fn notify(&self, config: &dyn Any) {
+        if let Some(channel) = self.channel.get() {
+            (channel.notify)(config);
+        }
+    }
+
+    /// Returns a receiver of the values of the field returned by `field`, whose current value is
+    /// the one in `config`.
+    pub fn subscribe<C, T>(&self, config: &C, field: fn(&C) -> &T) -> watch::Receiver<T>
+    where
+        C: 'static,
+        T: Clone + Send + Sync + 'static,
+    {
+        let channel = self.channel.get_or_init(|| {
+            let sender = Arc::new(watch::channel(field(config).clone()).0);
+            let notify_sender = sender.clone();
+            Channel {
+                sender,
+                notify: Box::new(move |config| {
+                    if let Some(config) = config.downcast_ref::<C>() {
+                        notify_sender.send_replace(field(config).clone());
+                    }
+                }),
+            }
+        });
+        channel
+            .sender
+            .clone()
+            .downcast::<watch::Sender<T>>()
+            .expect("the type of a field can't change")
+            .subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Config {
+        port: u16,
+    }
+
+    #[test]
+    fn notify() {
+        let watch = FieldWatch::new();
+        let mut config = Config { port: 80 };
+        // Nothing is sent before the first subscription.
+        watch.notify(&config);
+        let mut receiver = watch.subscribe(&config, |config: &Config| &config.port);
+        assert_eq!(*receiver.borrow_and_update(), 80);
+        config.port = 8080;
+        watch.notify(&config);
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(*receiver.borrow_and_update(), 8080);
+        assert_eq!(
+            *watch.subscribe(&config, |config| &config.port).borrow(),
+            8080
+        );
+    }
+}
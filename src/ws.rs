@@ -0,0 +1,250 @@
+//! Interactive access to the configuration through WebSocket.
+//!
+//! Clients send json requests, each answered by a json reply with the same `id`:
+//!
+//! - `{"id": 1, "op": "get", "field": "port"}` replies `{"id": 1, "ok": true, "value": 8080}`
+//! - `{"id": 2, "op": "set", "field": "db.host", "value": "remote"}` replies
+//!   `{"id": 2, "ok": true}`
+//! - `{"id": 3, "op": "subscribe"}` starts the notifications of the changes of the fields, and
+//!   `{"id": 4, "op": "unsubscribe"}` stops them
+//!
+//! Failed requests reply `{"id": 2, "ok": false, "error": <error>}`, where the error is the json
+//! object of the failed HTTP requests, e.g. `{"error": "validation_error", "field": "db.host",
+//! "message": "<message>"}`.
+//! Notifications have the form
+//! `{"event": "change", "field": "db.host", "value": "remote", "revision": 3}`.
+//!
+//! Values are json values if the configuration uses json, otherwise strings.
+
+use crate::error::ChoicesError;
+use crate::events::{Event, Subscription};
+use crate::negotiate::{error_json, INTERNAL_ERROR};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Map, Value};
+use std::fmt::Display;
+use warp::ws::{Message, WebSocket};
+
+/// Serves the requests received from `socket`.
+///
+/// `get` returns the value of a field, `set` sets it and `subscribe` subscribes to the changes of
+/// the configuration; their errors are the json objects of `error()` and `internal_error()`.
+#[doc(hidden)]
+pub async fn serve<G, S, U>(socket: WebSocket, json: bool, get: G, set: S, subscribe: U)
+where
+    G: Fn(&str) -> Result<Value, Value> + Send + Sync,
+    S: Fn(&str, Value) -> Result<(), Value> + Send + Sync,
+    U: Fn() -> Result<Subscription, Value> + Send + Sync,
+{
+    let (mut sender, mut receiver) = socket.split();
+    let mut subscription: Option<Subscription> = None;
+    loop {
+        let reply = tokio::select! {
+            message = receiver.next() => match message {
+                Some(Ok(message)) if message.is_close() => break,
+                Some(Ok(message)) => match message.to_str() {
+                    Ok(request) => handle(request, &get, &set, &mut subscription, &subscribe),
+                    // Pings and binary messages aren't requests.
+                    Err(_) => continue,
+                },
+                _ => break,
+            },
+            Some(event) = next_event(&mut subscription) => event_message(event, json),
+        };
+        if sender.send(Message::text(reply.to_string())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Returns the reply to `request`.
+fn handle<G, S, U>(
+    request: &str,
+    get: &G,
+    set: &S,
+    subscription: &mut Option<Subscription>,
+    subscribe: &U,
+) -> Value
+where
+    G: Fn(&str) -> Result<Value, Value>,
+    S: Fn(&str, Value) -> Result<(), Value>,
+    U: Fn() -> Result<Subscription, Value>,
+{
+    let request: Value = match serde_json::from_str(request) {
+        Ok(request) => request,
+        Err(err) => return reply(Value::Null, Err(parse_error(err.to_string()))),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let field = request.get("field").and_then(Value::as_str);
+    let result = match (request.get("op").and_then(Value::as_str), field) {
+        (Some("get"), Some(field)) => get(field).map(Some),
+        (Some("set"), Some(field)) => match request.get("value") {
+            Some(value) => set(field, value.clone()).map(|_| None),
+            None => Err(parse_error("missing `value`")),
+        },
+        (Some("subscribe"), _) => subscribe().map(|new_subscription| {
+            *subscription = Some(new_subscription);
+            None
+        }),
+        (Some("unsubscribe"), _) => {
+            *subscription = None;
+            Ok(None)
+        }
+        (Some("get" | "set"), None) => Err(parse_error("missing `field`")),
+        _ => Err(parse_error("unknown `op`")),
+    };
+    reply(id, result)
+}
+
+/// Returns the reply with the given `id` for the `result` of a request.
+fn reply(id: Value, result: Result<Option<Value>, Value>) -> Value {
+    match result {
+        Ok(Some(value)) => json!({ "id": id, "ok": true, "value": value }),
+        Ok(None) => json!({ "id": id, "ok": true }),
+        Err(err) => json!({ "id": id, "ok": false, "error": err }),
+    }
+}
+
+fn parse_error(message: impl Into<String>) -> Value {
+    error(&ChoicesError::ParseError(message.into()), None)
+}
+
+/// Returns the json object describing the error `err` of a request of the field `field`, as the
+/// bodies of the failed HTTP requests.
+#[doc(hidden)]
+pub fn error(err: &ChoicesError, field: Option<&str>) -> Value {
+    error_json(err.code(), err.to_string(), err.field().or(field), None)
+}
+
+/// Returns the json object describing an error which isn't caused by the request, such as a
+/// configuration which can't be accessed.
+#[doc(hidden)]
+pub fn internal_error(message: impl Display) -> Value {
+    error_json(INTERNAL_ERROR, message.to_string(), None, None)
+}
+
+/// Waits for the next event of `subscription`, or forever if there's none.
+async fn next_event(subscription: &mut Option<Subscription>) -> Option<Event> {
+    match subscription {
+        Some(subscription) => subscription.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Returns the notification of `event`, whose value is json if `json` is true.
+fn event_message(event: Event, json: bool) -> Value {
+    let value = if json {
+        serde_json::from_str(&event.value).unwrap_or(Value::String(event.value))
+    } else {
+        Value::String(event.value)
+    };
+    json!({
+        "event": "change",
+        "field": event.field,
+        "value": value,
+        "revision": event.revision,
+    })
+}
+
+/// Returns the value of the field `name` in the json object `values`, where fields of nested
+/// configurations are named `nested.name`.
+#[doc(hidden)]
+pub fn select(mut values: Value, name: &str) -> Option<Value> {
+    for name in name.split('.') {
+        values = values.as_object_mut()?.remove(name)?;
+    }
+    Some(values)
+}
+
+/// Returns the json object setting the field `name` to `value`, where fields of nested
+/// configurations are named `nested.name`.
+#[doc(hidden)]
+pub fn nest(name: &str, value: Value) -> Value {
+    name.rsplit('.').fold(value, |value, name| {
+        let mut object = Map::new();
+        object.insert(name.to_string(), value);
+        Value::Object(object)
+    })
+}
+
+/// Returns the text of a value of a configuration which doesn't use json.
+#[doc(hidden)]
+pub fn text_value(value: Value) -> Result<bytes::Bytes, Value> {
+    match value {
+        Value::String(value) => Ok(value.into()),
+        _ => Err(parse_error("expected a string")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_names() {
+        let values = json!({ "db": { "host": "localhost" }, "port": 80 });
+        assert_eq!(select(values.clone(), "port"), Some(json!(80)));
+        assert_eq!(select(values.clone(), "db.host"), Some(json!("localhost")));
+        assert_eq!(select(values.clone(), "db.port"), None);
+        assert_eq!(select(values, "port.x"), None);
+        assert_eq!(nest("port", json!(80)), json!({ "port": 80 }));
+        assert_eq!(
+            nest("db.host", json!("remote")),
+            json!({ "db": { "host": "remote" } })
+        );
+    }
+
+    #[test]
+    fn requests() {
+        let get = |field: &str| match field {
+            "port" => Ok(json!(80)),
+            _ => Err(json!("unknown")),
+        };
+        let set = |_: &str, value: Value| match value {
+            Value::Number(_) => Ok(()),
+            _ => Err(json!("invalid")),
+        };
+        let mut subscription = None;
+        let mut handle = |request| {
+            handle(request, &get, &set, &mut subscription, &|| {
+                Ok(Subscription::new(vec![]))
+            })
+        };
+        assert_eq!(
+            handle(r#"{"id": 1, "op": "get", "field": "port"}"#),
+            json!({ "id": 1, "ok": true, "value": 80 })
+        );
+        assert_eq!(
+            handle(r#"{"id": 2, "op": "get", "field": "host"}"#),
+            json!({ "id": 2, "ok": false, "error": "unknown" })
+        );
+        assert_eq!(
+            handle(r#"{"id": 3, "op": "set", "field": "port", "value": 8080}"#),
+            json!({ "id": 3, "ok": true })
+        );
+        assert_eq!(
+            handle(r#"{"id": 4, "op": "set", "field": "port", "value": "x"}"#),
+            json!({ "id": 4, "ok": false, "error": "invalid" })
+        );
+        assert_eq!(
+            handle(r#"{"id": 5, "op": "set", "field": "port"}"#),
+            json!({
+                "id": 5,
+                "ok": false,
+                "error": {"error": "parse_error", "message": "ParseError: missing `value`"},
+            })
+        );
+        assert_eq!(
+            handle(r#"{"id": 6, "op": "subscribe"}"#),
+            json!({ "id": 6, "ok": true })
+        );
+        assert_eq!(
+            handle(r#"{"id": 7, "op": "delete"}"#),
+            json!({
+                "id": 7,
+                "ok": false,
+                "error": {"error": "parse_error", "message": "ParseError: unknown `op`"},
+            })
+        );
+        assert_eq!(handle("x")["ok"], json!(false));
+    }
+}
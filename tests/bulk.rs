@@ -0,0 +1,156 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct DbConfig {
+    host: String,
+    port: u16,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Config {
+    debug: bool,
+    hosts: Vec<String>,
+    #[choices(hide_get)]
+    password: String,
+    #[choices(skip)]
+    _internal: u8,
+    #[choices(nested)]
+    db: DbConfig,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonDbConfig {
+    host: String,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonConfig {
+    debug: bool,
+    id: Option<i32>,
+    #[choices(hide_get)]
+    password: String,
+    #[choices(nested)]
+    db: JsonDbConfig,
+    state: choices::State,
+}
+
+fn new_config() -> Config {
+    Config {
+        debug: true,
+        hosts: vec!["a".to_string(), "b".to_string()],
+        password: "secret".to_string(),
+        _internal: 0,
+        db: DbConfig {
+            host: "localhost".to_string(),
+            port: 5432,
+            state: Default::default(),
+        },
+        state: Default::default(),
+    }
+}
+
+fn new_json_config() -> JsonConfig {
+    JsonConfig {
+        debug: true,
+        id: None,
+        password: "secret".to_string(),
+        db: JsonDbConfig {
+            host: "localhost".to_string(),
+            state: Default::default(),
+        },
+        state: Default::default(),
+    }
+}
+
+async fn get_all_text_impl<F>(port: u16, server_future: F)
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let rt = Runtime::new().unwrap();
+    rt.spawn(server_future);
+
+    check_get_text!(
+        port,
+        "config/_all",
+        "debug=true\nhosts=a,b\ndb.host=localhost\ndb.port=5432\n"
+    );
+    check_get_text!(port, "config/db/_all", "host=localhost\nport=5432\n");
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn get_all_text() {
+    let port = get_free_port!();
+    get_all_text_impl(port, async move {
+        lazy_static! {
+            static ref CONFIG: Config = new_config();
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn get_all_text_mutable() {
+    let port = get_free_port!();
+    get_all_text_impl(port, async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(new_config()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    })
+    .await;
+}
+
+async fn get_all_json_impl<F>(port: u16, server_future: F)
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let rt = Runtime::new().unwrap();
+    rt.spawn(server_future);
+
+    check_get_json!(
+        port,
+        "config/_all",
+        "{\"db\":{\"host\":\"localhost\"},\"debug\":true,\"id\":null}"
+    );
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn get_all_json() {
+    let port = get_free_port!();
+    get_all_json_impl(port, async move {
+        lazy_static! {
+            static ref CONFIG: JsonConfig = new_json_config();
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn get_all_json_mutable() {
+    let port = get_free_port!();
+    get_all_json_impl(port, async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<JsonConfig>> = Arc::new(Mutex::new(new_json_config()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    })
+    .await;
+}
@@ -0,0 +1,162 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct Config {
+    #[choices(item_validator = check_host, validator = check_not_empty, on_set = (|v: &Vec<String>| self.updates = v.len()))]
+    hosts: Vec<String>,
+    queue: VecDeque<u8>,
+    ports: HashSet<u16>,
+    updates: usize,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonConfig {
+    hosts: Vec<String>,
+    state: choices::State,
+}
+
+fn check_host(host: &str) -> choices::ChoicesResult<()> {
+    if host.contains(' ') {
+        Err(choices::ChoicesError::ValidationError(
+            "hosts can't contain spaces".to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_not_empty(hosts: &[String]) -> choices::ChoicesResult<()> {
+    if hosts.is_empty() {
+        Err(choices::ChoicesError::ValidationError(
+            "at least one host is required".to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn new_config() -> Config {
+    Config {
+        hosts: vec!["a".to_string(), "b".to_string()],
+        queue: VecDeque::from(vec![1, 2]),
+        ports: [80].into_iter().collect(),
+        updates: 0,
+        state: Default::default(),
+    }
+}
+
+#[test]
+fn collection_setters() {
+    let mut config = new_config();
+    config.push_hosts("c").unwrap();
+    assert_eq!(config.hosts, vec!["a", "b", "c"]);
+    assert_eq!(config.updates, 3);
+    config.set_hosts_item(0, "d").unwrap();
+    assert_eq!(config.hosts, vec!["d", "b", "c"]);
+    config.remove_hosts_item(1).unwrap();
+    assert_eq!(config.hosts, vec!["d", "c"]);
+    assert_eq!(config.updates, 2);
+
+    assert!(matches!(
+        config.set_hosts_item(5, "e"),
+        Err(choices::ChoicesError::NotFound(_))
+    ));
+    assert!(config.push_hosts("e f").is_err());
+    assert!(config.set_hosts(vec!["e f".to_string()]).is_err());
+    config.remove_hosts_item(0).unwrap();
+    assert!(config.remove_hosts_item(0).is_err());
+    assert_eq!(config.hosts, vec!["c"]);
+    assert_eq!(config.updates, 1);
+
+    config.push_ports(80u16).unwrap();
+    config.set_ports_item(80, 443u16).unwrap();
+    assert_eq!(config.ports, [443].into_iter().collect());
+}
+
+#[tokio::test]
+async fn collection_text() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(new_config()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    check_get_field_text!(port, hosts, "a,b");
+    check_get_text!(port, "config/hosts/1", "b");
+    check_get_text!(port, "config/queue/0", "1");
+    check_get_text!(port, "config/ports/80", "80");
+    check_get_text!(port, "config/ports/%38%30", "80");
+
+    let response = send(reqwest::Method::GET, port, "config/hosts/2", &[], "").await;
+    assert_eq!(response.status, 404);
+    let response = send(reqwest::Method::GET, port, "config/hosts/x", &[], "").await;
+    assert_eq!(response.status, 404);
+
+    let response = send(reqwest::Method::POST, port, "config/hosts", &[], "c").await;
+    assert_eq!(response.status, 200);
+    let response = send(reqwest::Method::PUT, port, "config/hosts/0", &[], "d").await;
+    assert_eq!(response.status, 200);
+    let response = send(reqwest::Method::DELETE, port, "config/hosts/1", &[], "").await;
+    assert_eq!(response.status, 200);
+    check_get_field_text!(port, hosts, "d,c");
+    check_get_field_text!(port, updates, "2");
+
+    let response = send(reqwest::Method::PUT, port, "config/hosts/5", &[], "e").await;
+    assert_eq!(response.status, 404);
+    let response = send(reqwest::Method::POST, port, "config/hosts", &[], "e f").await;
+    assert_eq!(response.status, 400);
+
+    let response = send(reqwest::Method::PUT, port, "config/hosts", &[], "x").await;
+    assert_eq!(response.status, 200);
+    let response = send(reqwest::Method::DELETE, port, "config/hosts/0", &[], "").await;
+    assert_eq!(response.status, 400);
+    check_get_field_text!(port, hosts, "x");
+
+    let response = send(reqwest::Method::POST, port, "config/queue", &[], "3").await;
+    assert_eq!(response.status, 200);
+    check_get_field_text!(port, queue, "1,2,3");
+
+    let response = send(reqwest::Method::DELETE, port, "config/ports/80", &[], "").await;
+    assert_eq!(response.status, 200);
+    let response = send(reqwest::Method::DELETE, port, "config/ports/80", &[], "").await;
+    assert_eq!(response.status, 404);
+    check_get_field_text!(port, ports, "");
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn collection_json() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<JsonConfig>> = Arc::new(Mutex::new(JsonConfig {
+                hosts: vec!["a".to_string()],
+                state: Default::default(),
+            }));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    check_get_json!(port, "config/hosts/0", "\"a\"");
+    let response = send(reqwest::Method::POST, port, "config/hosts", &[], "\"b\"").await;
+    assert_eq!(response.status, 200);
+    let response = send(reqwest::Method::PUT, port, "config/hosts/0", &[], "\"c\"").await;
+    assert_eq!(response.status, 200);
+    check_get_field_json!(port, hosts, "[\"c\",\"b\"]");
+
+    rt.shutdown_background();
+}
@@ -0,0 +1,129 @@
+use choices::auth::Bearer;
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use util::*;
+
+const ORIGIN: &str = "https://admin.example.com";
+
+#[derive(Choices, Default)]
+#[choices(auth = Bearer::new("secret"), cors = choices::cors::origins([ORIGIN]))]
+struct Config {
+    debug: bool,
+    state: choices::State,
+}
+
+#[derive(Choices)]
+#[choices(cors = choices::cors::any_origin())]
+struct ReadConfig {
+    debug: bool,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+    static ref READ_CONFIG: ReadConfig = ReadConfig {
+        debug: true,
+        state: Default::default()
+    };
+}
+
+#[tokio::test]
+async fn allowed_origins() {
+    use reqwest::Method;
+
+    let port = serve!(CONFIG);
+
+    // Preflight requests are answered without credentials.
+    let Reply {
+        status, headers, ..
+    } = send(
+        Method::OPTIONS,
+        port,
+        "config/debug",
+        &[
+            ("origin", ORIGIN),
+            ("access-control-request-method", "PUT"),
+            ("access-control-request-headers", "authorization, if-match"),
+        ],
+        "",
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(headers["access-control-allow-origin"], ORIGIN);
+    let methods = headers["access-control-allow-methods"].to_str().unwrap();
+    assert!(methods.contains("PUT") && methods.contains("PATCH"));
+
+    let Reply {
+        status, headers, ..
+    } = send(Method::GET, port, "config/debug", &[("origin", ORIGIN)], "").await;
+    assert_eq!(status, 200);
+    assert_eq!(headers["access-control-allow-origin"], ORIGIN);
+    assert!(headers["access-control-expose-headers"]
+        .to_str()
+        .unwrap()
+        .contains("etag"));
+
+    // Replies of the authentication carry the CORS headers as well.
+    let Reply {
+        status, headers, ..
+    } = send(
+        Method::PUT,
+        port,
+        "config/debug",
+        &[("origin", ORIGIN)],
+        "true",
+    )
+    .await;
+    assert_eq!(status, 401);
+    assert_eq!(headers["access-control-allow-origin"], ORIGIN);
+    let Reply { status, .. } = send(
+        Method::PUT,
+        port,
+        "config/debug",
+        &[("origin", ORIGIN), ("authorization", "Bearer secret")],
+        "true",
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(CONFIG.lock().unwrap().debug);
+
+    let other = "https://evil.example.com";
+    let Reply { status, .. } = send(
+        Method::OPTIONS,
+        port,
+        "config/debug",
+        &[("origin", other), ("access-control-request-method", "PUT")],
+        "",
+    )
+    .await;
+    assert_eq!(status, 403);
+    let Reply {
+        status, headers, ..
+    } = send(Method::GET, port, "config/debug", &[("origin", other)], "").await;
+    assert_eq!(status, 403);
+    assert!(!headers.contains_key("access-control-allow-origin"));
+}
+
+#[tokio::test]
+async fn any_origin() {
+    use reqwest::Method;
+
+    let port = serve!(READ_CONFIG);
+
+    let Reply {
+        status, headers, ..
+    } = send(
+        Method::GET,
+        port,
+        "config",
+        &[("origin", "http://localhost:3000")],
+        "",
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "http://localhost:3000"
+    );
+}
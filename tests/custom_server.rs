@@ -23,7 +23,11 @@ where
 async fn get_all() {
     let port = get_free_port!();
     get_all_impl(port, async move {
-        let routes = text::SimpleBoolConfig { debug: true }
+        static CONFIG: text::SimpleBoolConfig = text::SimpleBoolConfig {
+            debug: true,
+            state: choices::State::new(),
+        };
+        let routes = CONFIG
             .filter()
             .or(choices::warp::path("hello").map(|| "Hello!"));
         choices::warp::serve(routes)
@@ -39,7 +43,10 @@ async fn get_all_mutable() {
     get_all_impl(port, async move {
         lazy_static! {
             static ref CONFIG: Arc<Mutex<text::SimpleBoolConfig>> =
-                Arc::new(Mutex::new(text::SimpleBoolConfig { debug: true }));
+                Arc::new(Mutex::new(text::SimpleBoolConfig {
+                    debug: true,
+                    state: Default::default()
+                }));
         }
         let routes = text::SimpleBoolConfig::filter_mutable(CONFIG.clone())
             .or(choices::warp::path("hello").map(|| "Hello!"));
@@ -0,0 +1,121 @@
+use choices::{Choices, ChoicesError};
+use util::check_port;
+
+#[derive(Choices, Default)]
+struct Config {
+    debug: bool,
+    hosts: Vec<String>,
+    #[choices(skip)]
+    secret: String,
+    #[choices(nested)]
+    server: Server,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Server {
+    #[choices(validator = check_port)]
+    port: u16,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(env_prefix = "ENV_PREFIXED")]
+struct PrefixedConfig {
+    retries: u8,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct EnvDefaultConfig {
+    retries: u8,
+    state: choices::State,
+}
+
+// Configurations without a default value can still be loaded through `load_env()`.
+#[derive(Choices)]
+struct NoDefaultConfig {
+    retries: u8,
+    state: choices::State,
+}
+
+// Every test uses its own prefix, since the environment is shared by the tests running in
+// parallel.
+
+#[test]
+fn load_env() {
+    std::env::set_var("ENV_LOAD_DEBUG", "true");
+    std::env::set_var("ENV_LOAD_HOSTS", "a,b");
+    std::env::set_var("ENV_LOAD_SECRET", "password");
+    std::env::set_var("ENV_LOAD_SERVER_PORT", "9000");
+    let mut config = Config::default();
+    config.load_env("ENV_LOAD").unwrap();
+    assert!(config.debug);
+    assert_eq!(config.hosts, vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(config.secret, "");
+    assert_eq!(config.server.port, 9000);
+}
+
+#[test]
+fn missing_variables() {
+    std::env::set_var("ENV_MISSING_DEBUG", "true");
+    let mut config = Config {
+        server: Server {
+            port: 80,
+            state: Default::default(),
+        },
+        ..Config::default()
+    };
+    config.load_env("ENV_MISSING").unwrap();
+    assert!(config.debug);
+    assert_eq!(config.server.port, 80);
+}
+
+#[test]
+fn all_errors() {
+    std::env::set_var("ENV_ERRORS_DEBUG", "maybe");
+    std::env::set_var("ENV_ERRORS_HOSTS", "a");
+    std::env::set_var("ENV_ERRORS_SERVER_PORT", "0");
+    let mut config = Config::default();
+    let err = config.load_env("ENV_ERRORS").unwrap_err();
+    match err {
+        ChoicesError::MultipleErrors(errors) => {
+            let fields: Vec<_> = errors.iter().map(|err| err.field().unwrap()).collect();
+            assert_eq!(fields, vec!["debug", "server.port"]);
+            assert_eq!(errors[0].code(), "parse_bool_error");
+            assert_eq!(errors[1].code(), "validation_error");
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+    // Nothing is loaded if any variable is invalid.
+    assert!(config.hosts.is_empty());
+
+    std::env::set_var("ENV_ERRORS_DEBUG", "true");
+    let err = config.load_env("ENV_ERRORS").unwrap_err();
+    assert_eq!(err.code(), "validation_error");
+    assert_eq!(err.field(), Some("server.port"));
+}
+
+#[test]
+fn from_env() {
+    std::env::set_var("ENV_PREFIXED_RETRIES", "5");
+    assert_eq!(PrefixedConfig::env_prefix(), "ENV_PREFIXED");
+    assert_eq!(PrefixedConfig::from_env().unwrap().retries, 5);
+
+    // Without `#[choices(env_prefix)]` the prefix is the name of the type.
+    std::env::set_var("ENV_DEFAULT_CONFIG_RETRIES", "7");
+    assert_eq!(EnvDefaultConfig::env_prefix(), "ENV_DEFAULT_CONFIG");
+    assert_eq!(EnvDefaultConfig::from_env().unwrap().retries, 7);
+}
+
+#[test]
+fn empty_prefix() {
+    let mut config = NoDefaultConfig {
+        retries: 1,
+        state: Default::default(),
+    };
+    assert_eq!(config.load_env("").unwrap_err().code(), "validation_error");
+    std::env::set_var("ENV_NO_DEFAULT_RETRIES", "2");
+    config.load_env("ENV_NO_DEFAULT").unwrap();
+    assert_eq!(config.retries, 2);
+}
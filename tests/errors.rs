@@ -0,0 +1,271 @@
+use choices::serde_json::{self, json, Value};
+use choices::{Choices, ChoicesError, ChoicesResult};
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct Config {
+    #[choices(validator = check_port)]
+    port: u16,
+    hosts: Vec<String>,
+    #[choices(validator = check_name)]
+    name: String,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonDbConfig {
+    #[choices(validator = check_port)]
+    port: u16,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonConfig {
+    #[choices(validator = check_port)]
+    port: u16,
+    #[choices(nested)]
+    db: JsonDbConfig,
+    state: choices::State,
+}
+
+fn check_name(name: &str) -> ChoicesResult<()> {
+    match name {
+        "taken" => Err(ChoicesError::ValidationError("name is taken".to_string()).with_status(409)),
+        "." => Err(ChoicesError::custom(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "reserved name",
+        ))
+        .with_status(422)),
+        _ => Ok(()),
+    }
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+    static ref JSON_CONFIG: Arc<Mutex<JsonConfig>> = Arc::new(Mutex::new(JsonConfig::default()));
+}
+
+fn json_error(response: Reply) -> (u16, Value) {
+    assert_eq!(response.header("content-type"), Some(CONTENT_TYPE_JSON));
+    (
+        response.status,
+        serde_json::from_str(&response.body).unwrap(),
+    )
+}
+
+fn text_error(response: Reply) -> (u16, String) {
+    assert_eq!(response.header("content-type"), Some(CONTENT_TYPE_TEXT));
+    (response.status, response.body)
+}
+
+#[tokio::test]
+async fn errors_json() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(JSON_CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)));
+
+    assert_eq!(
+        json_error(send(reqwest::Method::PUT, port, "config/port", &[], "0").await),
+        (
+            400,
+            json!({
+                "error": "validation_error",
+                "field": "port",
+                "message": "ValidationError: port must be positive",
+                "input": 0,
+            })
+        )
+    );
+    assert_eq!(
+        json_error(send(reqwest::Method::PUT, port, "config/port", &[], "eighty").await).1["error"],
+        "parse_error"
+    );
+    // The input is a string if it isn't valid json.
+    assert_eq!(
+        json_error(send(reqwest::Method::PUT, port, "config/port", &[], "eighty").await).1["input"],
+        "eighty"
+    );
+    assert_eq!(
+        json_error(
+            send(
+                reqwest::Method::PUT,
+                port,
+                "config/port",
+                &[("If-Match", "\"7\"")],
+                "80"
+            )
+            .await
+        ),
+        (
+            412,
+            json!({
+                "error": "precondition_failed",
+                "field": "port",
+                "message": "PreconditionFailed: the current revision is 0",
+            })
+        )
+    );
+    assert_eq!(
+        json_error(
+            send(
+                reqwest::Method::PATCH,
+                port,
+                "config",
+                &[],
+                r#"{"port": 0}"#
+            )
+            .await
+        ),
+        (
+            400,
+            json!({
+                "error": "validation_error",
+                "field": "port",
+                "message": "ValidationError: port must be positive",
+                "input": {"port": 0},
+            })
+        )
+    );
+
+    // Errors of nested configurations are raised by their dotted fields.
+    assert_eq!(
+        json_error(
+            send(
+                reqwest::Method::PATCH,
+                port,
+                "config",
+                &[],
+                r#"{"db": {"port": 0}}"#
+            )
+            .await
+        )
+        .1["field"],
+        "db.port"
+    );
+
+    // Errors are replied as text if requested.
+    assert_eq!(
+        text_error(
+            send(
+                reqwest::Method::PUT,
+                port,
+                "config/port",
+                &[("Accept", "text/plain")],
+                "0"
+            )
+            .await
+        ),
+        (400, "ValidationError: port must be positive".to_string())
+    );
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn errors_text() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)));
+
+    assert_eq!(
+        text_error(send(reqwest::Method::PUT, port, "config/port", &[], "0").await),
+        (400, "ValidationError: port must be positive".to_string())
+    );
+
+    // Errors follow the format of the body, unless another one is accepted.
+    assert_eq!(
+        json_error(
+            send(
+                reqwest::Method::PUT,
+                port,
+                "config/port",
+                &[("Content-Type", CONTENT_TYPE_JSON)],
+                "-1"
+            )
+            .await
+        ),
+        (
+            400,
+            json!({
+                "error": "parse_error",
+                "field": "port",
+                "message": "ParseError: invalid value: integer `-1`, expected u16",
+                "input": -1,
+            })
+        )
+    );
+    let accept_json = [("Accept", CONTENT_TYPE_JSON)];
+    assert_eq!(
+        json_error(send(reqwest::Method::PUT, port, "config/port", &accept_json, "x").await),
+        (
+            400,
+            json!({
+                "error": "parse_int_error",
+                "field": "port",
+                "message": "ParseIntError: invalid digit found in string",
+                "input": "x",
+            })
+        )
+    );
+    assert_eq!(
+        json_error(
+            send(
+                reqwest::Method::GET,
+                port,
+                "config/hosts/3",
+                &accept_json,
+                ""
+            )
+            .await
+        ),
+        (
+            404,
+            json!({
+                "error": "not_found",
+                "field": "hosts",
+                "message": "NotFound: no such element in `hosts`",
+            })
+        )
+    );
+    assert_eq!(
+        json_error(
+            send(
+                reqwest::Method::DELETE,
+                port,
+                "config/hosts/3",
+                &accept_json,
+                ""
+            )
+            .await
+        )
+        .1["error"],
+        "not_found"
+    );
+
+    // Validators can choose the status of their errors.
+    assert_eq!(
+        text_error(send(reqwest::Method::PUT, port, "config/name", &[], "taken").await),
+        (409, "ValidationError: name is taken".to_string())
+    );
+    assert_eq!(
+        json_error(send(reqwest::Method::PUT, port, "config/name", &accept_json, ".").await),
+        (
+            422,
+            json!({
+                "error": "custom_error",
+                "field": "name",
+                "message": "CustomError: reserved name",
+                "input": ".",
+            })
+        )
+    );
+
+    rt.shutdown_background();
+}
@@ -0,0 +1,131 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct DbConfig {
+    port: u16,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Config {
+    debug: bool,
+    #[choices(hide_get)]
+    password: String,
+    #[choices(nested)]
+    db: DbConfig,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonConfig {
+    hosts: Vec<String>,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+    static ref JSON_CONFIG: Arc<Mutex<JsonConfig>> = Arc::new(Mutex::new(JsonConfig::default()));
+}
+
+/// Reads the next event from the stream `response`.
+async fn next_event(response: &mut reqwest::Response, buffer: &mut String) -> String {
+    loop {
+        if let Some(end) = buffer.find("\n\n") {
+            let event = buffer[..end + 2].to_string();
+            buffer.replace_range(..end + 2, "");
+            if !event.starts_with(':') {
+                return event;
+            }
+        } else {
+            let chunk = response.chunk().await.unwrap().expect("stream closed");
+            buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+}
+
+#[tokio::test]
+async fn events_text() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)));
+
+    let mut events = retry_await!(reqwest::get(format!(
+        "http://127.0.0.1:{}/config/_events",
+        port
+    )))
+    .unwrap();
+    assert_eq!(events.status(), 200);
+    let mut buffer = String::new();
+
+    assert_eq!(
+        send(reqwest::Method::PUT, port, "config/debug", &[], "true")
+            .await
+            .status,
+        200
+    );
+    assert_eq!(
+        next_event(&mut events, &mut buffer).await,
+        "event:debug\ndata:true\nid:1\n\n"
+    );
+
+    // Hidden fields don't send events, nested ones are prefixed.
+    assert_eq!(
+        send(reqwest::Method::PUT, port, "config/password", &[], "secret")
+            .await
+            .status,
+        200
+    );
+    assert_eq!(
+        send(reqwest::Method::PUT, port, "config/db/port", &[], "80")
+            .await
+            .status,
+        200
+    );
+    assert_eq!(
+        next_event(&mut events, &mut buffer).await,
+        "event:db.port\ndata:80\nid:1\n\n"
+    );
+
+    // Setters called from Rust send events too.
+    CONFIG.lock().unwrap().set_debug(false).unwrap();
+    assert_eq!(
+        next_event(&mut events, &mut buffer).await,
+        "event:debug\ndata:false\nid:3\n\n"
+    );
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn events_json() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(JSON_CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)));
+
+    let mut events = retry_await!(reqwest::get(format!(
+        "http://127.0.0.1:{}/config/_events",
+        port
+    )))
+    .unwrap();
+    let mut buffer = String::new();
+
+    assert_eq!(
+        send(reqwest::Method::POST, port, "config/hosts", &[], "\"a\"")
+            .await
+            .status,
+        200
+    );
+    assert_eq!(
+        next_event(&mut events, &mut buffer).await,
+        "event:hosts\ndata:[\"a\"]\nid:1\n\n"
+    );
+
+    rt.shutdown_background();
+}
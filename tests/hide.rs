@@ -12,6 +12,7 @@ struct Config {
     b: i32,
     #[choices(hide_get, hide_put)]
     c: i32,
+    state: choices::State,
 }
 
 #[tokio::test]
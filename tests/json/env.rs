@@ -0,0 +1,38 @@
+use choices::Choices;
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct EnvConfig {
+    debug: bool,
+    name: String,
+    hosts: Vec<String>,
+    #[choices(nested)]
+    server: EnvServer,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct EnvServer {
+    port: u16,
+    state: choices::State,
+}
+
+#[test]
+fn load_env_json() {
+    std::env::set_var("JSON_ENV_DEBUG", "true");
+    std::env::set_var("JSON_ENV_HOSTS", r#"["a", "b"]"#);
+    std::env::set_var("JSON_ENV_SERVER_PORT", "9000");
+    // Values rejected by the type of the field are taken as strings.
+    std::env::set_var("JSON_ENV_NAME", "123");
+    let mut config = EnvConfig::default();
+    config.load_env("JSON_ENV").unwrap();
+    assert!(config.debug);
+    assert_eq!(config.name, "123");
+    assert_eq!(config.hosts, vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(config.server.port, 9000);
+
+    std::env::set_var("JSON_ENV_HOSTS", "a,b");
+    let err = config.load_env("JSON_ENV").unwrap_err();
+    assert_eq!(err.field(), Some("hosts"));
+}
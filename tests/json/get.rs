@@ -11,15 +11,18 @@ use util::*;
 #[choices(json)]
 struct SimpleBoolConfig {
     debug: bool,
+    state: choices::State,
 }
 
 #[tokio::test]
 async fn get_non_existing_field() {
     let port = get_free_port!();
     get_non_existing_field_impl(port, async move {
-        SimpleBoolConfig { debug: true }
-            .run((std::net::Ipv4Addr::LOCALHOST, port))
-            .await
+        static CONFIG: SimpleBoolConfig = SimpleBoolConfig {
+            debug: true,
+            state: choices::State::new(),
+        };
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
     })
     .await;
 }
@@ -30,7 +33,10 @@ async fn get_non_existing_field_mutable() {
     get_non_existing_field_impl(port, async move {
         lazy_static! {
             static ref CONFIG: Arc<Mutex<SimpleBoolConfig>> =
-                Arc::new(Mutex::new(SimpleBoolConfig { debug: true }));
+                Arc::new(Mutex::new(SimpleBoolConfig {
+                    debug: true,
+                    state: Default::default()
+                }));
         }
         CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
     })
@@ -110,6 +116,7 @@ async fn get_string_field() {
             static ref CONFIG: StringConfig = {
                 StringConfig {
                     string: "blabla".to_string(),
+                    state: Default::default(),
                 }
             };
         }
@@ -126,6 +133,7 @@ async fn get_string_field_mutable() {
             static ref CONFIG: Arc<Mutex<StringConfig>> = {
                 Arc::new(Mutex::new(StringConfig {
                     string: "blabla".to_string(),
+                    state: Default::default(),
                 }))
             };
         }
@@ -152,12 +160,12 @@ where
 async fn get_option_field() {
     let port = get_free_port!();
     get_option_field_impl(port, async move {
-        OptionConfig {
+        static CONFIG: OptionConfig = OptionConfig {
             character: Some('a'),
             empty: None,
-        }
-        .run((std::net::Ipv4Addr::LOCALHOST, port))
-        .await
+            state: choices::State::new(),
+        };
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
     })
     .await;
 }
@@ -171,6 +179,7 @@ async fn get_option_field_mutable() {
                 Arc::new(Mutex::new(OptionConfig {
                     character: Some('a'),
                     empty: None,
+                    state: Default::default(),
                 }))
             };
         }
@@ -1,5 +1,6 @@
-use choices::Choices;
+use choices::{Choices, ChoicesValue};
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
 use std::future::Future;
@@ -15,6 +16,7 @@ struct Config {
     delay: f64,
     score: Option<i32>,
     map: HashMap<u8, i32>,
+    state: choices::State,
 }
 
 async fn get_list_impl<F>(port: u16, server_future: F)
@@ -65,3 +67,48 @@ async fn get_list_mutable() {
     })
     .await;
 }
+
+#[derive(ChoicesValue, Serialize, Deserialize)]
+enum Mode {
+    Fast,
+    Safe,
+}
+
+#[derive(Choices)]
+#[choices(json)]
+struct VariantsConfig {
+    mode: Mode,
+    fallback: Option<Mode>,
+    state: choices::State,
+}
+
+#[tokio::test]
+async fn get_list_variants() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: VariantsConfig = VariantsConfig {
+                mode: Mode::Fast,
+                fallback: None,
+                state: Default::default(),
+            };
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    check_get!(
+        port,
+        "config",
+        json!([
+            {"name": "mode", "type": "Mode", "variants": ["Fast", "Safe"]},
+            {"name": "fallback", "type": "Option<Mode>", "variants": ["Fast", "Safe"]}
+        ])
+        .to_string(),
+        util::CONTENT_TYPE_JSON
+    );
+    check_get_field_json!(port, mode, json!("Fast").to_string());
+
+    rt.shutdown_background();
+}
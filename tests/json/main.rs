@@ -1,5 +1,7 @@
+mod env;
 mod get;
 mod list;
+mod persist;
 mod put;
 mod skip;
 mod user_type;
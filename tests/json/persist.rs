@@ -0,0 +1,107 @@
+use choices::Choices;
+use serde_json::json;
+use std::path::PathBuf;
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct PersistConfig {
+    debug: bool,
+    name: Option<String>,
+    hosts: Vec<String>,
+    #[choices(nested)]
+    server: PersistServer,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct PersistServer {
+    port: u16,
+    state: choices::State,
+}
+
+/// Returns the path of the file `name` in a fresh directory for the test `test`.
+fn file(test: &str, name: &str) -> PathBuf {
+    let dir = PathBuf::from("target/persist-test").join(test);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+fn config() -> PersistConfig {
+    PersistConfig {
+        debug: true,
+        name: None,
+        hosts: vec!["a".to_string()],
+        server: PersistServer {
+            port: 80,
+            state: Default::default(),
+        },
+        state: Default::default(),
+    }
+}
+
+#[test]
+fn save_and_load_json() {
+    let path = file("save_and_load_json", "config.json");
+    config().save(&path).unwrap();
+    let contents: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(
+        contents,
+        json!({"debug": true, "name": null, "hosts": ["a"], "server": {"port": 80}})
+    );
+
+    let mut loaded = PersistConfig::default();
+    loaded.load(&path).unwrap();
+    assert!(loaded.debug);
+    assert_eq!(loaded.hosts, vec!["a".to_string()]);
+    assert_eq!(loaded.server.port, 80);
+
+    std::fs::write(&path, r#"{"debug": false, "server": {"port": "x"}}"#).unwrap();
+    assert!(loaded.load(&path).is_err());
+    assert!(loaded.debug);
+}
+
+#[test]
+fn nested_setters_json() {
+    let path = file("nested_setters_json", "config.json");
+    let mut config = config();
+    config.save(&path).unwrap();
+    let persistence = config.persistence();
+    persistence.persist_to(&path);
+    config.server.set_port(8080u16).unwrap();
+    assert_eq!(persistence.flush(), Ok(()));
+    let contents: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(
+        contents,
+        json!({"debug": true, "name": null, "hosts": ["a"], "server": {"port": 8080}})
+    );
+}
+
+#[test]
+fn save_and_load_toml() {
+    let path = file("save_and_load_toml", "config.toml");
+    let result = config().save(&path);
+    #[cfg(not(feature = "toml"))]
+    assert!(result.is_err());
+
+    #[cfg(feature = "toml")]
+    {
+        result.unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("debug = true"));
+        assert!(contents.contains("[server]"));
+
+        let mut loaded = PersistConfig {
+            name: Some("old".to_string()),
+            ..PersistConfig::default()
+        };
+        loaded.load(&path).unwrap();
+        assert!(loaded.debug);
+        // Missing values are left unchanged.
+        assert_eq!(loaded.name, Some("old".to_string()));
+        assert_eq!(loaded.server.port, 80);
+    }
+}
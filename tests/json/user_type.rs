@@ -1,4 +1,4 @@
-use choices::Choices;
+use choices::{Choices, ChoicesValue};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -16,6 +16,7 @@ struct UserType<T: Default, U: Default> {
 #[choices(json)]
 struct Config {
     field: UserType<u8, u8>,
+    state: choices::State,
 }
 
 #[tokio::test]
@@ -47,3 +48,47 @@ async fn user_type() {
 
     rt.shutdown_background();
 }
+
+#[derive(ChoicesValue, Default, Serialize, Deserialize)]
+#[choices(serde_json)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Choices, Default)]
+struct TextConfig {
+    point: Point,
+    state: choices::State,
+}
+
+#[tokio::test]
+async fn serde_json_value() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<TextConfig>> = Arc::new(Mutex::new(TextConfig::default()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    check_get_field_text!(port, point, json!({"x": 0, "y": 0}).to_string());
+    check_put_field_text!(
+        port,
+        point,
+        json!({"x": 1, "y": 2}).to_string(),
+        200,
+        json!({"x": 1, "y": 2}).to_string()
+    );
+    check_put_field_text!(
+        port,
+        point,
+        "wrong",
+        400,
+        json!({"x": 1, "y": 2}).to_string()
+    );
+
+    rt.shutdown_background();
+}
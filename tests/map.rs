@@ -0,0 +1,173 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct Config {
+    #[choices(item_validator = check_limit)]
+    limits: BTreeMap<String, u32>,
+    ports: HashMap<u16, bool>,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonConfig {
+    limits: BTreeMap<String, u32>,
+    state: choices::State,
+}
+
+fn check_limit(limit: &u32) -> choices::ChoicesResult<()> {
+    if *limit > 1000 {
+        Err(choices::ChoicesError::ValidationError(
+            "limit too high".to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn new_limits() -> BTreeMap<String, u32> {
+    [("acme".to_string(), 10), ("globex".to_string(), 20)]
+        .into_iter()
+        .collect()
+}
+
+#[test]
+fn map_setters() {
+    let mut config = Config {
+        limits: new_limits(),
+        ..Default::default()
+    };
+    config.set_limits_entry("initech", 30u32).unwrap();
+    config.set_limits_entry("acme", 15u32).unwrap();
+    config.remove_limits_entry("globex").unwrap();
+    assert_eq!(
+        config.limits,
+        [("acme".to_string(), 15), ("initech".to_string(), 30)]
+            .into_iter()
+            .collect()
+    );
+    assert!(config.set_limits_entry("acme", 2000u32).is_err());
+    assert!(matches!(
+        config.remove_limits_entry("globex"),
+        Err(choices::ChoicesError::NotFound(_))
+    ));
+    assert_eq!(config.limits["acme"], 15);
+}
+
+#[tokio::test]
+async fn map_text() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+                limits: new_limits(),
+                ports: [(80, true)].into_iter().collect(),
+                state: Default::default(),
+            }));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    check_get_field_text!(port, limits, "acme,globex");
+    check_get_text!(port, "config/limits/acme", "10");
+    check_get_text!(port, "config/ports/80", "true");
+    let response = send(reqwest::Method::GET, port, "config/limits/initech", &[], "").await;
+    assert_eq!(response.status, 404);
+    let response = send(reqwest::Method::GET, port, "config/ports/x", &[], "").await;
+    assert_eq!(response.status, 404);
+
+    let response = send(
+        reqwest::Method::PUT,
+        port,
+        "config/limits/initech",
+        &[],
+        "30",
+    )
+    .await;
+    assert_eq!(response.status, 200);
+    let response = send(reqwest::Method::PUT, port, "config/limits/acme", &[], "11").await;
+    assert_eq!(response.status, 200);
+    let response = send(
+        reqwest::Method::PUT,
+        port,
+        "config/limits/acme",
+        &[],
+        "2000",
+    )
+    .await;
+    assert_eq!(response.status, 400);
+    let response = send(reqwest::Method::PUT, port, "config/limits/acme", &[], "x").await;
+    assert_eq!(response.status, 400);
+    let response = send(
+        reqwest::Method::DELETE,
+        port,
+        "config/limits/globex",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(response.status, 200);
+    let response = send(
+        reqwest::Method::DELETE,
+        port,
+        "config/limits/globex",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(response.status, 404);
+    check_get_field_text!(port, limits, "acme,initech");
+    check_get_text!(port, "config/limits/acme", "11");
+
+    // Keys are percent-decoded.
+    let response = send(
+        reqwest::Method::PUT,
+        port,
+        "config/limits/big%20corp",
+        &[],
+        "40",
+    )
+    .await;
+    assert_eq!(response.status, 200);
+    check_get_text!(port, "config/limits/big%20corp", "40");
+    check_get_field_text!(port, limits, "acme,big corp,initech");
+
+    let response = send(reqwest::Method::PUT, port, "config/limits", &[], "a=1,b=2").await;
+    assert_eq!(response.status, 200);
+    check_get_field_text!(port, limits, "a,b");
+    let response = send(reqwest::Method::PUT, port, "config/limits", &[], "a=5000").await;
+    assert_eq!(response.status, 400);
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn map_json() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<JsonConfig>> = Arc::new(Mutex::new(JsonConfig {
+                limits: new_limits(),
+                state: Default::default(),
+            }));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    check_get_field_json!(port, limits, "[\"acme\",\"globex\"]");
+    check_get_json!(port, "config/limits/globex", "20");
+    let response = send(reqwest::Method::PUT, port, "config/limits/acme", &[], "12").await;
+    assert_eq!(response.status, 200);
+    check_get_json!(port, "config/limits/acme", "12");
+
+    rt.shutdown_background();
+}
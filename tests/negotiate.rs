@@ -0,0 +1,188 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct DbConfig {
+    host: String,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Config {
+    port: u16,
+    hosts: Vec<String>,
+    #[choices(nested)]
+    db: DbConfig,
+    state: choices::State,
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Debug)]
+enum Mode {
+    #[default]
+    Fast,
+    Safe,
+}
+
+#[derive(Choices, Default)]
+#[choices(json, message = "Options:")]
+struct JsonConfig {
+    debug: bool,
+    mode: Mode,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+    static ref JSON_CONFIG: Arc<Mutex<JsonConfig>> = Arc::new(Mutex::new(JsonConfig::default()));
+}
+
+/// Returns the status, the content type and the body of the reply `reply`.
+fn parts(reply: Reply) -> (u16, String, String) {
+    let content_type = reply.header("content-type").unwrap_or_default().to_string();
+    (reply.status, content_type, reply.body)
+}
+
+async fn get(port: u16, path: &str, accept: &str) -> (u16, String, String) {
+    parts(send(reqwest::Method::GET, port, path, &[("Accept", accept)], "").await)
+}
+
+fn ok(content_type: &str, body: &str) -> (u16, String, String) {
+    (200, content_type.to_string(), body.to_string())
+}
+
+#[tokio::test]
+async fn negotiate_text() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)));
+
+    // Replies to json bodies are json, unless another format is accepted.
+    let json = ("Content-Type", CONTENT_TYPE_JSON);
+    assert_eq!(
+        parts(send(reqwest::Method::PUT, port, "config/port", &[json], "8080").await),
+        ok(CONTENT_TYPE_JSON, "")
+    );
+    assert_eq!(
+        parts(
+            send(
+                reqwest::Method::POST,
+                port,
+                "config/hosts",
+                &[json],
+                "\"a\""
+            )
+            .await
+        ),
+        ok(CONTENT_TYPE_JSON, "")
+    );
+    assert_eq!(
+        parts(
+            send(
+                reqwest::Method::PATCH,
+                port,
+                "config",
+                &[json],
+                r#"{"db": {"host": "remote"}}"#
+            )
+            .await
+        ),
+        ok(CONTENT_TYPE_JSON, "")
+    );
+
+    assert_eq!(
+        get(port, "config/port", "*/*").await,
+        ok(CONTENT_TYPE_TEXT, "8080")
+    );
+    assert_eq!(
+        get(port, "config/port", CONTENT_TYPE_JSON).await,
+        ok(CONTENT_TYPE_JSON, "8080")
+    );
+    assert_eq!(
+        get(port, "config/hosts/0", CONTENT_TYPE_JSON).await,
+        ok(CONTENT_TYPE_JSON, "\"a\"")
+    );
+    assert_eq!(
+        get(port, "config/db/host", CONTENT_TYPE_JSON).await,
+        ok(CONTENT_TYPE_JSON, "\"remote\"")
+    );
+    assert_eq!(
+        get(port, "config/_all", CONTENT_TYPE_JSON).await,
+        ok(
+            CONTENT_TYPE_JSON,
+            r#"{"db":{"host":"remote"},"hosts":["a"],"port":8080}"#
+        )
+    );
+    assert_eq!(
+        get(port, "config", CONTENT_TYPE_JSON).await.2,
+        r#"[{"name":"port","type":"u16"},{"name":"hosts","type":"Vec<String>"},{"name":"db","type":"DbConfig"}]"#
+    );
+
+    // Json bodies are parsed as json.
+    assert_eq!(
+        parts(send(reqwest::Method::PUT, port, "config/port", &[json], "\"x\"").await).0,
+        400
+    );
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn negotiate_json() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(JSON_CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)));
+
+    let text = ("Content-Type", "text/plain");
+    assert_eq!(
+        parts(send(reqwest::Method::PUT, port, "config/debug", &[text], "true").await),
+        ok(CONTENT_TYPE_TEXT, "")
+    );
+    // Types without a text representation are written as json.
+    assert_eq!(
+        parts(
+            send(
+                reqwest::Method::PUT,
+                port,
+                "config/mode",
+                &[text],
+                "\"Safe\""
+            )
+            .await
+        ),
+        ok(CONTENT_TYPE_TEXT, "")
+    );
+
+    assert_eq!(
+        get(port, "config/debug", "*/*").await,
+        ok(CONTENT_TYPE_JSON, "true")
+    );
+    assert_eq!(
+        get(port, "config/debug", "text/plain").await,
+        ok(CONTENT_TYPE_TEXT, "true")
+    );
+    assert_eq!(
+        get(port, "config/mode", "text/plain").await,
+        ok(CONTENT_TYPE_TEXT, "\"Safe\"")
+    );
+    assert_eq!(
+        get(port, "config/_all", "text/plain").await,
+        ok(CONTENT_TYPE_TEXT, "debug=true\nmode=\"Safe\"\n")
+    );
+    // The message is part of the index as text.
+    assert_eq!(
+        get(port, "config", "text/plain").await,
+        ok(
+            CONTENT_TYPE_TEXT,
+            "Options:\n  - debug: bool\n  - mode: Mode\n"
+        )
+    );
+    assert_eq!(JSON_CONFIG.lock().unwrap().mode, Mode::Safe);
+
+    rt.shutdown_background();
+}
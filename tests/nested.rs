@@ -0,0 +1,157 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct DbConfig {
+    host: String,
+    #[choices(validator = check_port)]
+    port: u16,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Config {
+    debug: bool,
+    #[choices(nested)]
+    db: DbConfig,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(rw_lock)]
+struct RwConfig {
+    #[choices(nested)]
+    db: DbConfig,
+    state: choices::State,
+}
+
+fn new_config() -> Config {
+    Config {
+        debug: true,
+        db: DbConfig {
+            host: "localhost".to_string(),
+            port: 5432,
+            state: Default::default(),
+        },
+        state: Default::default(),
+    }
+}
+
+async fn get_nested_impl<F>(port: u16, server_future: F)
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let rt = Runtime::new().unwrap();
+    rt.spawn(server_future);
+
+    check_get_text!(
+        port,
+        "config",
+        "Available configuration options:\n  - debug: bool\n  - db: DbConfig\n"
+    );
+    check_get_text!(
+        port,
+        "config/db",
+        "Available configuration options:\n  - host: String\n  - port: u16\n"
+    );
+    check_get_text!(port, "config/db/host", "localhost");
+    check_get_text!(port, "config/db/port", "5432");
+
+    let response = retry_await!(reqwest::get(&format!(
+        "http://127.0.0.1:{}/config/db/fake",
+        port
+    )))
+    .unwrap();
+    assert_eq!(response.status(), 404);
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn get_nested() {
+    let port = get_free_port!();
+    get_nested_impl(port, async move {
+        lazy_static! {
+            static ref CONFIG: Config = new_config();
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn get_nested_mutable() {
+    let port = get_free_port!();
+    get_nested_impl(port, async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(new_config()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn put_nested() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(new_config()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    let response = retry_await!(reqwest::Client::builder()
+        .build()
+        .unwrap()
+        .put(format!("http://127.0.0.1:{}/config/db/host", port))
+        .body("remote")
+        .send())
+    .unwrap();
+    assert_eq!(response.status(), 200);
+    check_get_text!(port, "config/db/host", "remote");
+
+    let response = retry_await!(reqwest::Client::builder()
+        .build()
+        .unwrap()
+        .put(format!("http://127.0.0.1:{}/config/db/port", port))
+        .body("0")
+        .send())
+    .unwrap();
+    assert_eq!(response.status(), 400);
+    check_get_text!(port, "config/db/port", "5432");
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn put_nested_rw_lock() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<RwLock<RwConfig>> = Arc::new(RwLock::new(RwConfig::default()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    let response = retry_await!(reqwest::Client::builder()
+        .build()
+        .unwrap()
+        .put(format!("http://127.0.0.1:{}/config/db/port", port))
+        .body("80")
+        .send())
+    .unwrap();
+    assert_eq!(response.status(), 200);
+    check_get_text!(port, "config/db/port", "80");
+
+    rt.shutdown_background();
+}
@@ -9,6 +9,7 @@ struct Config {
     #[choices(on_set = (|v| self.total += v))]
     value: i32,
     total: i32,
+    state: choices::State,
 }
 
 #[test]
@@ -0,0 +1,132 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct DbConfig {
+    host: String,
+    #[choices(validator = check_port)]
+    port: u16,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Config {
+    #[choices(on_set = (|_: &bool| self.updates += 1))]
+    debug: bool,
+    #[choices(hide_put)]
+    id: u32,
+    updates: u32,
+    #[choices(nested)]
+    db: DbConfig,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonConfig {
+    debug: bool,
+    #[choices(validator = check_port)]
+    port: u16,
+    #[choices(nested)]
+    db: JsonDbConfig,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonDbConfig {
+    host: String,
+    state: choices::State,
+}
+
+async fn patch(port: u16, body: &'static str) -> reqwest::Response {
+    retry_await!(reqwest::Client::builder()
+        .build()
+        .unwrap()
+        .patch(format!("http://127.0.0.1:{}/config", port))
+        .body(body)
+        .send())
+    .unwrap()
+}
+
+#[tokio::test]
+async fn patch_text() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    let response = patch(port, "debug=true\ndb.host=remote\ndb.port=80\n").await;
+    assert_eq!(response.status(), 200);
+    check_get_text!(
+        port,
+        "config/_all",
+        "debug=true\nid=0\nupdates=1\ndb.host=remote\ndb.port=80\n"
+    );
+
+    // Nothing is applied if any value is invalid.
+    let response = patch(port, "debug=false\ndb.port=0").await;
+    assert_eq!(response.status(), 400);
+    let response = patch(port, "debug=false\ndb.port=x").await;
+    assert_eq!(response.status(), 400);
+    let response = patch(port, "debug=false\nid=3").await;
+    assert_eq!(response.status(), 400);
+    let response = patch(port, "debug=false\nfake=3").await;
+    assert_eq!(response.status(), 400);
+    let response = patch(port, "debug").await;
+    assert_eq!(response.status(), 400);
+    check_get_text!(
+        port,
+        "config/_all",
+        "debug=true\nid=0\nupdates=1\ndb.host=remote\ndb.port=80\n"
+    );
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn patch_json() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<JsonConfig>> = Arc::new(Mutex::new(JsonConfig::default()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    let response = patch(
+        port,
+        "{\"debug\":true,\"port\":80,\"db\":{\"host\":\"remote\"}}",
+    )
+    .await;
+    assert_eq!(response.status(), 200);
+    check_get_json!(
+        port,
+        "config/_all",
+        "{\"db\":{\"host\":\"remote\"},\"debug\":true,\"port\":80}"
+    );
+
+    let response = patch(port, "{\"debug\":false,\"port\":0}").await;
+    assert_eq!(response.status(), 400);
+    let response = patch(port, "{\"debug\":false,\"db\":{\"fake\":1}}").await;
+    assert_eq!(response.status(), 400);
+    let response = patch(port, "[1]").await;
+    assert_eq!(response.status(), 400);
+    check_get_json!(
+        port,
+        "config/_all",
+        "{\"db\":{\"host\":\"remote\"},\"debug\":true,\"port\":80}"
+    );
+
+    rt.shutdown_background();
+}
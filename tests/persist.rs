@@ -0,0 +1,270 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use util::*;
+
+#[derive(Choices, Default)]
+struct Config {
+    debug: bool,
+    hosts: Vec<String>,
+    motd: String,
+    #[choices(skip)]
+    secret: String,
+    #[choices(nested)]
+    server: Server,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Server {
+    #[choices(validator = check_port)]
+    port: u16,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(persist = "target/persist-test/saved.conf")]
+struct SavedConfig {
+    debug: bool,
+    hosts: Vec<String>,
+    #[choices(nested)]
+    server: Server,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref SAVED_CONFIG: Arc<Mutex<SavedConfig>> = Arc::new(Mutex::new(SavedConfig::default()));
+    static ref UNSAVED_CONFIG: Arc<Mutex<SavedConfig>> =
+        Arc::new(Mutex::new(SavedConfig::default()));
+}
+
+/// Returns the path of the file `name` in a fresh directory for the test `test`.
+fn file(test: &str, name: &str) -> PathBuf {
+    let dir = PathBuf::from("target/persist-test").join(test);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn save_and_load() {
+    let path = file("save_and_load", "config.conf");
+    let config = Config {
+        debug: true,
+        hosts: vec!["a".to_string(), "b".to_string()],
+        motd: "hello".to_string(),
+        secret: "password".to_string(),
+        server: Server {
+            port: 80,
+            state: Default::default(),
+        },
+        state: Default::default(),
+    };
+    config.save(&path).unwrap();
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("debug=true"));
+    assert!(contents.contains("server.port=80"));
+    assert!(!contents.contains("password"));
+
+    let mut loaded = Config::default();
+    loaded.load(&path).unwrap();
+    assert!(loaded.debug);
+    assert_eq!(loaded.hosts, vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(loaded.secret, "");
+    assert_eq!(loaded.server.port, 80);
+}
+
+#[test]
+fn load_errors() {
+    let path = file("load_errors", "config.conf");
+    let mut config = Config::default();
+    assert!(config.load(&path).is_err());
+
+    // Invalid files are rejected as a whole.
+    std::fs::write(&path, "debug=true\nserver.port=0\n").unwrap();
+    assert_eq!(config.load(&path).unwrap_err().code(), "validation_error");
+    assert!(!config.debug);
+    std::fs::write(&path, "debug=true\nunknown=1\n").unwrap();
+    assert!(config.load(&path).is_err());
+    assert!(!config.debug);
+
+    // Fields missing from the file are left unchanged.
+    config.server.port = 8080;
+    std::fs::write(&path, "debug=true\n").unwrap();
+    config.load(&path).unwrap();
+    assert!(config.debug);
+    assert_eq!(config.server.port, 8080);
+
+    #[cfg(not(feature = "json"))]
+    assert!(config.save(path.with_extension("json")).is_err());
+}
+
+#[test]
+fn multi_line_values() {
+    let path = file("multi_line_values", "config.conf");
+    let mut config = Config {
+        hosts: vec!["a\nb".to_string(), "c,d".to_string()],
+        motd: "hello\r\nworld\\n\n".to_string(),
+        ..Config::default()
+    };
+    config.server.port = 80;
+    config.save(&path).unwrap();
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .contains("motd=hello\\\r\\\nworld\\\\n\\\n"));
+
+    let mut loaded = Config::default();
+    loaded.load(&path).unwrap();
+    assert_eq!(loaded.hosts, config.hosts);
+    assert_eq!(loaded.motd, config.motd);
+    assert_eq!(loaded.server.port, 80);
+}
+
+#[test]
+fn nested_setters() {
+    let path = file("nested_setters", "config.conf");
+    let mut config = Config::default();
+    let persistence = config.persistence();
+    persistence.persist_to(&path);
+    config.set_debug(true).unwrap();
+    config.server.set_port(80u16).unwrap();
+    assert_eq!(persistence.flush(), Ok(()));
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("debug=true"));
+    assert!(contents.contains("server.port=80"));
+
+    // Only the part of the nested configuration is replaced.
+    config.debug = false;
+    config.server.set_port(8080u16).unwrap();
+    assert_eq!(persistence.flush(), Ok(()));
+    let mut loaded = Config::default();
+    loaded.load(&path).unwrap();
+    assert!(loaded.debug);
+    assert_eq!(loaded.server.port, 8080);
+    assert_eq!(config.server.persistence().path(), None);
+
+    // Invalid values are neither set nor saved.
+    assert!(config.server.set_port(0u16).is_err());
+    assert_eq!(persistence.flush(), Ok(()));
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .contains("server.port=8080"));
+}
+
+#[tokio::test]
+async fn persist_changes() {
+    use reqwest::Method;
+
+    let path = PathBuf::from("target/persist-test/saved.conf");
+    let _ = std::fs::remove_file(&path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    assert_eq!(
+        SAVED_CONFIG.lock().unwrap().persistence().path(),
+        Some(path.clone())
+    );
+
+    // Restoring a missing file leaves the configuration unchanged.
+    SAVED_CONFIG.lock().unwrap().restore().unwrap();
+    assert!(!path.exists());
+
+    let port = serve!(SAVED_CONFIG);
+    assert_eq!(
+        send(Method::GET, port, "config/debug", &[], "")
+            .await
+            .status,
+        200
+    );
+    assert!(!path.exists());
+
+    assert_eq!(
+        send(Method::PUT, port, "config/debug", &[], "true")
+            .await
+            .status,
+        200
+    );
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .contains("debug=true"));
+    assert_eq!(
+        send(Method::POST, port, "config/hosts", &[], "a")
+            .await
+            .status,
+        200
+    );
+    assert_eq!(
+        send(Method::PUT, port, "config/server/port", &[], "80")
+            .await
+            .status,
+        200
+    );
+    assert_eq!(
+        send(Method::PATCH, port, "config", &[], "debug=false")
+            .await
+            .status,
+        200
+    );
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("debug=false"));
+    assert!(contents.contains("server.port=80"));
+
+    // Changes through the setters are saved as well.
+    SAVED_CONFIG
+        .lock()
+        .unwrap()
+        .set_hosts(vec!["b".to_string()])
+        .unwrap();
+    let persistence = SAVED_CONFIG.lock().unwrap().persistence();
+    assert_eq!(persistence.flush(), Ok(()));
+
+    let mut restored = SavedConfig::default();
+    restored.restore().unwrap();
+    assert!(!restored.debug);
+    assert_eq!(restored.hosts, vec!["b".to_string()]);
+    assert_eq!(restored.server.port, 80);
+}
+
+#[tokio::test]
+async fn failed_saves() {
+    use reqwest::Method;
+
+    let dir = PathBuf::from("target/persist-test/failed_saves");
+    let _ = std::fs::remove_dir_all(&dir);
+    let path = dir.join("saved.conf");
+    let persistence = UNSAVED_CONFIG.lock().unwrap().persistence();
+    persistence.persist_to(&path);
+
+    // Changes which can't be saved are kept, and saved again at the next change.
+    let port = serve!(UNSAVED_CONFIG);
+    assert_eq!(
+        send(Method::PUT, port, "config/debug", &[], "true")
+            .await
+            .status,
+        200
+    );
+    assert!(UNSAVED_CONFIG.lock().unwrap().debug);
+    assert!(persistence.error().is_some());
+    UNSAVED_CONFIG
+        .lock()
+        .unwrap()
+        .set_hosts(vec!["a".to_string()])
+        .unwrap();
+    assert!(persistence.flush().is_err());
+
+    std::fs::create_dir_all(&dir).unwrap();
+    assert_eq!(
+        send(Method::PUT, port, "config/server/port", &[], "80")
+            .await
+            .status,
+        200
+    );
+    assert_eq!(persistence.error(), None);
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("debug=true"));
+    assert!(contents.contains("server.port=80"));
+    // Other configuration objects of the same type have their own persistence.
+    assert_eq!(
+        SavedConfig::default().persistence().path(),
+        Some(PathBuf::from("target/persist-test/saved.conf"))
+    );
+}
@@ -0,0 +1,252 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct DbConfig {
+    host: String,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Config {
+    debug: bool,
+    hosts: Vec<String>,
+    #[choices(nested)]
+    db: DbConfig,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonConfig {
+    port: u16,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct ReplicatedConfig {
+    #[choices(nested)]
+    primary: DbConfig,
+    #[choices(nested)]
+    replica: DbConfig,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref REPLICATED_CONFIG: Arc<Mutex<ReplicatedConfig>> =
+        Arc::new(Mutex::new(ReplicatedConfig::default()));
+    static ref OTHER_REPLICATED_CONFIG: Arc<Mutex<ReplicatedConfig>> =
+        Arc::new(Mutex::new(ReplicatedConfig::default()));
+}
+
+fn etag(response: &Reply) -> String {
+    response.header("ETag").unwrap().to_string()
+}
+
+#[tokio::test]
+async fn revision_text() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    let get = reqwest::Method::GET;
+    let put = reqwest::Method::PUT;
+    let response = send(get.clone(), port, "config/debug", &[], "").await;
+    let debug_etag = etag(&response);
+
+    // Setting a field changes its revision.
+    let response = send(
+        put.clone(),
+        port,
+        "config/debug",
+        &[("If-Match", debug_etag.as_str())],
+        "true",
+    )
+    .await;
+    assert_eq!(response.status, 200);
+    let response = send(get.clone(), port, "config/debug", &[], "").await;
+    assert_ne!(etag(&response), debug_etag);
+    assert_eq!(response.body, "true");
+
+    // A stale revision is rejected.
+    let response = send(
+        put.clone(),
+        port,
+        "config/debug",
+        &[("If-Match", debug_etag.as_str())],
+        "false",
+    )
+    .await;
+    assert_eq!(response.status, 412);
+    check_get_field_text!(port, debug, "true");
+    let response = send(
+        put.clone(),
+        port,
+        "config/debug",
+        &[("If-Match", "*")],
+        "false",
+    )
+    .await;
+    assert_eq!(response.status, 200);
+    let response = send(put.clone(), port, "config/debug", &[], "true").await;
+    assert_eq!(response.status, 200);
+
+    // Elements are conditioned to the revision of their collection.
+    let response = send(get.clone(), port, "config/hosts", &[], "").await;
+    let hosts_etag = etag(&response);
+    let response = send(
+        reqwest::Method::POST,
+        port,
+        "config/hosts",
+        &[("If-Match", hosts_etag.as_str())],
+        "a",
+    )
+    .await;
+    assert_eq!(response.status, 200);
+    let response = send(
+        put.clone(),
+        port,
+        "config/hosts/0",
+        &[("If-Match", hosts_etag.as_str())],
+        "b",
+    )
+    .await;
+    assert_eq!(response.status, 412);
+    let response = send(
+        reqwest::Method::DELETE,
+        port,
+        "config/hosts/0",
+        &[("If-Match", hosts_etag.as_str())],
+        "",
+    )
+    .await;
+    assert_eq!(response.status, 412);
+    check_get_field_text!(port, hosts, "a");
+
+    // The revision of the configuration includes the nested ones.
+    let response = send(get.clone(), port, "config/_all", &[], "").await;
+    let all_etag = etag(&response);
+    let response = send(put.clone(), port, "config/db/host", &[], "remote").await;
+    assert_eq!(response.status, 200);
+    let response = send(
+        reqwest::Method::PATCH,
+        port,
+        "config",
+        &[("If-Match", all_etag.as_str())],
+        "debug=false",
+    )
+    .await;
+    assert_eq!(response.status, 412);
+    let response = send(get, port, "config/_all", &[], "").await;
+    let all_etag = etag(&response);
+    let response = send(
+        reqwest::Method::PATCH,
+        port,
+        "config",
+        &[("If-Match", all_etag.as_str())],
+        "debug=false",
+    )
+    .await;
+    assert_eq!(response.status, 200);
+    check_get_field_text!(port, debug, "false");
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn revision_json() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<JsonConfig>> = Arc::new(Mutex::new(JsonConfig::default()));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    let response = send(reqwest::Method::GET, port, "config/port", &[], "").await;
+    let port_etag = etag(&response);
+    let response = send(
+        reqwest::Method::PUT,
+        port,
+        "config/port",
+        &[("If-Match", port_etag.as_str())],
+        "80",
+    )
+    .await;
+    assert_eq!(response.status, 200);
+    let response = send(
+        reqwest::Method::PUT,
+        port,
+        "config/port",
+        &[("If-Match", port_etag.as_str())],
+        "81",
+    )
+    .await;
+    assert_eq!(response.status, 412);
+    check_get_field_json!(port, port, "80");
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn revision_per_instance() {
+    use reqwest::Method;
+
+    let port = serve!(REPLICATED_CONFIG);
+    let other_port = serve!(OTHER_REPLICATED_CONFIG);
+
+    // Configurations nested twice have their own revisions.
+    let response = send(Method::GET, port, "config/primary/host", &[], "").await;
+    let primary_etag = etag(&response);
+    let response = send(Method::PUT, port, "config/replica/host", &[], "replica").await;
+    assert_eq!(response.status, 200);
+    let response = send(
+        Method::PUT,
+        port,
+        "config/primary/host",
+        &[("If-Match", primary_etag.as_str())],
+        "primary",
+    )
+    .await;
+    assert_eq!(response.status, 200);
+
+    // Each change is counted once in the revision of the configuration.
+    let response = send(Method::GET, port, "config/_all", &[], "").await;
+    assert_eq!(etag(&response), "\"2\"");
+
+    // Other configurations of the same type have their own revisions.
+    let response = send(Method::GET, other_port, "config/_all", &[], "").await;
+    assert_eq!(etag(&response), "\"0\"");
+}
+
+#[tokio::test]
+async fn revision_after_move() {
+    use reqwest::Method;
+
+    // The revisions are moved along with the configuration.
+    let mut config = ReplicatedConfig::default();
+    config.primary.set_host("primary").unwrap();
+    let config: &'static Arc<Mutex<ReplicatedConfig>> =
+        Box::leak(Box::new(Arc::new(Mutex::new(config))));
+    let port = serve!(config);
+    let response = send(Method::GET, port, "config/_all", &[], "").await;
+    assert_eq!(etag(&response), "\"1\"");
+
+    // New configurations start from the first revision.
+    let other: &'static Arc<Mutex<ReplicatedConfig>> =
+        Box::leak(Box::new(Arc::new(Mutex::new(ReplicatedConfig::default()))));
+    let other_port = serve!(other);
+    let response = send(Method::GET, other_port, "config/primary/host", &[], "").await;
+    assert_eq!(etag(&response), "\"0\"");
+}
@@ -0,0 +1,217 @@
+use choices::auth::{Identity, Tokens};
+use choices::serde_json::{self, json, Value};
+use choices::warp::http::HeaderMap;
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use util::*;
+
+#[derive(Choices, Default)]
+#[choices(auth = tokens(), identity = tokens())]
+struct Config {
+    #[choices(write_role = "ops")]
+    log_level: String,
+    #[choices(read_role = "admin", write_role = "admin")]
+    db_url: String,
+    debug: bool,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json, identity = role_header)]
+struct JsonConfig {
+    #[choices(read_role = "admin")]
+    secret: String,
+    debug: bool,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(identity = role_header)]
+struct VaultConfig {
+    #[choices(read_role = "admin", write_role = "admin")]
+    secret: String,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct NestedConfig {
+    debug: bool,
+    #[choices(nested)]
+    vault: VaultConfig,
+    state: choices::State,
+}
+
+fn tokens() -> Tokens {
+    Tokens::new()
+        .token("ops-token", ["ops"])
+        .token("admin-token", ["ops", "admin"])
+}
+
+fn role_header(headers: &HeaderMap) -> Identity {
+    Identity::new(
+        headers
+            .get("x-role")
+            .and_then(|role| role.to_str().ok())
+            .map(str::to_string),
+    )
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+    static ref JSON_CONFIG: Arc<Mutex<JsonConfig>> = Arc::new(Mutex::new(JsonConfig::default()));
+    static ref NESTED_CONFIG: Arc<Mutex<NestedConfig>> =
+        Arc::new(Mutex::new(NestedConfig::default()));
+}
+
+const OPS: &[(&str, &str)] = &[("authorization", "Bearer ops-token")];
+const ADMIN: &[(&str, &str)] = &[("authorization", "Bearer admin-token")];
+
+/// Returns the status and the body of the reply `reply`.
+fn parts(reply: Reply) -> (u16, String) {
+    (reply.status, reply.body)
+}
+
+#[tokio::test]
+async fn field_roles() {
+    use reqwest::Method;
+
+    let port = serve!(CONFIG);
+
+    let (status, index) = parts(send(Method::GET, port, "config", &[], "").await);
+    assert_eq!(status, 200);
+    assert!(index.contains("log_level"));
+    assert!(!index.contains("db_url"));
+    let (_, index) = parts(send(Method::GET, port, "config", ADMIN, "").await);
+    assert!(index.contains("db_url"));
+
+    assert_eq!(
+        parts(send(Method::GET, port, "config/db_url", OPS, "").await),
+        (403, "Forbidden: the role `admin` is required".to_string())
+    );
+    assert_eq!(
+        parts(send(Method::GET, port, "config/db_url", ADMIN, "").await).0,
+        200
+    );
+    assert_eq!(
+        parts(send(Method::GET, port, "config/log_level", &[], "").await).0,
+        200
+    );
+
+    assert_eq!(
+        parts(send(Method::PUT, port, "config/log_level", OPS, "debug").await).0,
+        200
+    );
+    assert_eq!(
+        parts(send(Method::PUT, port, "config/db_url", OPS, "db").await).0,
+        403
+    );
+    assert_eq!(
+        parts(send(Method::PUT, port, "config/db_url", ADMIN, "db").await).0,
+        200
+    );
+    assert_eq!(CONFIG.lock().unwrap().log_level, "debug");
+    assert_eq!(CONFIG.lock().unwrap().db_url, "db");
+
+    // Bulk resources require all the roles of the fields.
+    assert_eq!(
+        parts(send(Method::GET, port, "config/_all", OPS, "").await).0,
+        403
+    );
+    assert_eq!(
+        parts(send(Method::GET, port, "config/_all", ADMIN, "").await).0,
+        200
+    );
+    assert_eq!(
+        parts(send(Method::PATCH, port, "config", OPS, "debug=true").await).0,
+        403
+    );
+    assert_eq!(
+        parts(send(Method::PATCH, port, "config", ADMIN, "debug=true").await).0,
+        200
+    );
+    assert!(CONFIG.lock().unwrap().debug);
+}
+
+#[tokio::test]
+async fn identity_without_auth() {
+    use reqwest::Method;
+
+    let port = serve!(JSON_CONFIG);
+
+    let (_, index) = parts(send(Method::GET, port, "config", &[], "").await);
+    assert_eq!(
+        serde_json::from_str::<Value>(&index).unwrap(),
+        json!([{"name": "debug", "type": "bool"}])
+    );
+    let (_, index) = parts(send(Method::GET, port, "config", &[("x-role", "admin")], "").await);
+    assert_eq!(
+        serde_json::from_str::<Value>(&index).unwrap()[0]["name"],
+        "secret"
+    );
+
+    let (status, body) = parts(send(Method::GET, port, "config/secret", &[], "").await);
+    assert_eq!(status, 403);
+    assert_eq!(
+        serde_json::from_str::<Value>(&body).unwrap(),
+        json!({
+            "error": "forbidden",
+            "field": "secret",
+            "message": "Forbidden: the role `admin` is required"
+        })
+    );
+    assert_eq!(
+        parts(
+            send(
+                Method::GET,
+                port,
+                "config/secret",
+                &[("x-role", "admin")],
+                ""
+            )
+            .await
+        )
+        .0,
+        200
+    );
+}
+
+#[tokio::test]
+async fn nested_roles() {
+    use reqwest::Method;
+
+    let port = serve!(NESTED_CONFIG);
+    let admin: &[(&str, &str)] = &[("x-role", "admin")];
+
+    assert_eq!(
+        parts(send(Method::GET, port, "config/vault/secret", &[], "").await).0,
+        403
+    );
+
+    // Bulk resources require the roles of the nested configurations as well.
+    assert_eq!(
+        parts(send(Method::GET, port, "config/_all", &[], "").await),
+        (403, "Forbidden: the role `admin` is required".to_string())
+    );
+    assert_eq!(
+        parts(send(Method::GET, port, "config/_events", &[], "").await).0,
+        403
+    );
+    assert_eq!(
+        parts(send(Method::PATCH, port, "config", &[], "vault.secret=leaked").await).0,
+        403
+    );
+    assert_eq!(
+        parts(send(Method::PATCH, port, "config", &[], "debug=true").await).0,
+        403
+    );
+    assert_eq!(NESTED_CONFIG.lock().unwrap().vault.secret, "");
+
+    assert_eq!(
+        parts(send(Method::PATCH, port, "config", admin, "vault.secret=key").await).0,
+        200
+    );
+    let (status, values) = parts(send(Method::GET, port, "config/_all", admin, "").await);
+    assert_eq!(status, 200);
+    assert!(values.contains("vault.secret=key"));
+}
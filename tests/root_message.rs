@@ -7,7 +7,9 @@ use util::*;
 
 #[derive(Choices)]
 #[choices(message = "welcome")]
-struct Config {}
+struct Config {
+    state: choices::State,
+}
 
 async fn check_message_impl<F>(port: u16, server_future: F, expected: &str)
 where
@@ -27,7 +29,12 @@ async fn check_message() {
     let port = get_free_port!();
     check_message_impl(
         port,
-        async move { Config {}.run((std::net::Ipv4Addr::LOCALHOST, port)).await },
+        async move {
+            static CONFIG: Config = Config {
+                state: choices::State::new(),
+            };
+            CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+        },
         "welcome\n",
     )
     .await;
@@ -40,7 +47,9 @@ async fn check_message_mutable() {
         port,
         async move {
             lazy_static! {
-                static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {}));
+                static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+                    state: Default::default()
+                }));
             }
             CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
         },
@@ -51,7 +60,9 @@ async fn check_message_mutable() {
 
 #[derive(Choices)]
 #[choices(message = "")]
-struct ConfigMsgEmpty {}
+struct ConfigMsgEmpty {
+    state: choices::State,
+}
 
 #[tokio::test]
 async fn check_message_empty() {
@@ -59,9 +70,10 @@ async fn check_message_empty() {
     check_message_impl(
         port,
         async move {
-            ConfigMsgEmpty {}
-                .run((std::net::Ipv4Addr::LOCALHOST, port))
-                .await
+            static CONFIG: ConfigMsgEmpty = ConfigMsgEmpty {
+                state: choices::State::new(),
+            };
+            CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
         },
         "",
     )
@@ -9,6 +9,7 @@ use util::*;
 #[choices(path = "myconfig")]
 struct Config {
     debug: bool,
+    state: choices::State,
 }
 
 async fn get_field_impl<F>(port: u16, server_future: F)
@@ -28,9 +29,11 @@ where
 async fn get_field() {
     let port = get_free_port!();
     get_field_impl(port, async move {
-        Config { debug: true }
-            .run((std::net::Ipv4Addr::LOCALHOST, port))
-            .await
+        static CONFIG: Config = Config {
+            debug: true,
+            state: choices::State::new(),
+        };
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
     })
     .await;
 }
@@ -40,7 +43,10 @@ async fn get_field_mutable() {
     let port = get_free_port!();
     get_field_impl(port, async move {
         lazy_static! {
-            static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config { debug: true }));
+            static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+                debug: true,
+                state: Default::default()
+            }));
         }
         CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
     })
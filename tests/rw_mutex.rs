@@ -8,6 +8,7 @@ use util::*;
 #[choices(rw_lock)]
 struct Config {
     debug: bool,
+    state: choices::State,
 }
 
 #[tokio::test]
@@ -17,7 +18,10 @@ async fn get_field() {
     let rt = Runtime::new().unwrap();
     rt.spawn(async move {
         lazy_static! {
-            static ref CONFIG: Arc<RwLock<Config>> = Arc::new(RwLock::new(Config { debug: true }));
+            static ref CONFIG: Arc<RwLock<Config>> = Arc::new(RwLock::new(Config {
+                debug: true,
+                state: Default::default()
+            }));
         }
         CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
     });
@@ -34,7 +38,10 @@ async fn put_field() {
     let rt = Runtime::new().unwrap();
     rt.spawn(async move {
         lazy_static! {
-            static ref CONFIG: Arc<RwLock<Config>> = Arc::new(RwLock::new(Config { debug: true }));
+            static ref CONFIG: Arc<RwLock<Config>> = Arc::new(RwLock::new(Config {
+                debug: true,
+                state: Default::default()
+            }));
         }
         CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
     });
@@ -0,0 +1,75 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use util::*;
+
+#[derive(Choices)]
+struct Config {
+    debug: bool,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Config = Config {
+        debug: true,
+        state: Default::default()
+    };
+    static ref MUTABLE_CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        debug: false,
+        state: Default::default()
+    }));
+}
+
+#[tokio::test]
+async fn spawn_and_shutdown() {
+    let handle = CONFIG.spawn((std::net::Ipv4Addr::LOCALHOST, 0));
+    let port = handle.addr().port();
+    assert_ne!(port, 0);
+
+    check_get_field_text!(port, debug, "true");
+
+    handle.shutdown();
+    handle.join().await;
+    assert!(
+        reqwest::get(format!("http://127.0.0.1:{}/config/debug", port))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn run_with_shutdown() {
+    let port = get_free_port!();
+    let (sender, receiver) = tokio::sync::oneshot::channel::<()>();
+
+    let server = tokio::spawn(MUTABLE_CONFIG.run_with_shutdown(
+        (std::net::Ipv4Addr::LOCALHOST, port),
+        async move {
+            receiver.await.ok();
+        },
+    ));
+
+    check_put_field_text!(port, debug, "true", 200, "true");
+
+    sender.send(()).unwrap();
+    server.await.unwrap();
+    assert!(MUTABLE_CONFIG.lock().unwrap().debug);
+}
+
+#[tokio::test]
+async fn bind() {
+    let port = serve!(CONFIG);
+    // The server is ready once bound.
+    let response = reqwest::get(format!("http://127.0.0.1:{}/config/debug", port))
+        .await
+        .unwrap();
+    assert_eq!(response.text().await.unwrap(), "true");
+
+    let (addr, server) = MUTABLE_CONFIG.bind((std::net::Ipv4Addr::LOCALHOST, 0));
+    assert_ne!(addr.port(), 0);
+    tokio::spawn(server);
+    let response = reqwest::get(format!("http://{}/config", addr))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
@@ -0,0 +1,185 @@
+use choices::signature::Signature;
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use util::*;
+
+#[derive(Choices, Default)]
+#[choices(signature = Signature::new("key"))]
+struct Config {
+    debug: bool,
+    hosts: Vec<String>,
+    #[choices(nested)]
+    server: Server,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Server {
+    port: u16,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+}
+
+/// Sends a request signed with `key` and the nonce `nonce`, if given, returning the status, the
+/// `WWW-Authenticate` header and the body of the reply.
+async fn send_signed(
+    method: reqwest::Method,
+    port: u16,
+    path: &str,
+    signed: Option<(&str, &str)>,
+    body: &'static str,
+) -> (u16, Option<String>, String) {
+    let headers = signed.map_or(vec![], |(key, nonce)| {
+        Signature::new(key)
+            .headers(method.as_str(), path, nonce, body.as_bytes())
+            .to_vec()
+    });
+    let headers: Vec<_> = headers
+        .iter()
+        .map(|(name, value)| (*name, value.as_str()))
+        .collect();
+    let reply = send(method, port, path, &headers, body).await;
+    let challenge = reply.header("www-authenticate").map(str::to_string);
+    (reply.status, challenge, reply.body)
+}
+
+#[tokio::test]
+async fn signed_requests() {
+    use reqwest::Method;
+
+    let port = serve!(CONFIG);
+    let challenge = Some("HMAC realm=\"choices\"".to_string());
+
+    // Reads aren't signed.
+    assert_eq!(
+        send_signed(Method::GET, port, "/config/debug", None, "").await,
+        (200, None, "false".to_string())
+    );
+
+    assert_eq!(
+        send_signed(Method::PUT, port, "/config/debug", None, "true").await,
+        (
+            401,
+            challenge.clone(),
+            "Unauthorized: missing header `x-choices-timestamp`".to_string()
+        )
+    );
+    assert_eq!(
+        send_signed(
+            Method::PUT,
+            port,
+            "/config/debug",
+            Some(("other", "1")),
+            "true"
+        )
+        .await,
+        (
+            401,
+            challenge.clone(),
+            "Unauthorized: invalid signature".to_string()
+        )
+    );
+    assert!(!CONFIG.lock().unwrap().debug);
+
+    assert_eq!(
+        send_signed(
+            Method::PUT,
+            port,
+            "/config/debug",
+            Some(("key", "1")),
+            "true"
+        )
+        .await
+        .0,
+        200
+    );
+    assert!(CONFIG.lock().unwrap().debug);
+    assert_eq!(
+        send_signed(
+            Method::PUT,
+            port,
+            "/config/debug",
+            Some(("key", "1")),
+            "true"
+        )
+        .await,
+        (401, challenge, "Unauthorized: reused nonce".to_string())
+    );
+
+    // Elements, bulk updates and nested configurations are signed as well.
+    assert_eq!(
+        send_signed(Method::POST, port, "/config/hosts", None, "a")
+            .await
+            .0,
+        401
+    );
+    assert_eq!(
+        send_signed(Method::POST, port, "/config/hosts", Some(("key", "2")), "a")
+            .await
+            .0,
+        200
+    );
+    assert_eq!(
+        send_signed(Method::DELETE, port, "/config/hosts/0", None, "")
+            .await
+            .0,
+        401
+    );
+    assert_eq!(
+        send_signed(
+            Method::DELETE,
+            port,
+            "/config/hosts/0",
+            Some(("key", "3")),
+            ""
+        )
+        .await
+        .0,
+        200
+    );
+    assert_eq!(
+        send_signed(Method::PATCH, port, "/config", None, "debug=false")
+            .await
+            .0,
+        401
+    );
+    assert_eq!(
+        send_signed(
+            Method::PATCH,
+            port,
+            "/config",
+            Some(("key", "4")),
+            "debug=false"
+        )
+        .await
+        .0,
+        200
+    );
+    assert_eq!(
+        send_signed(Method::PUT, port, "/config/server/port", None, "80")
+            .await
+            .0,
+        401
+    );
+    assert_eq!(
+        send_signed(
+            Method::PUT,
+            port,
+            "/config/server/port",
+            Some(("key", "5")),
+            "80"
+        )
+        .await
+        .0,
+        200
+    );
+
+    let config = CONFIG.lock().unwrap();
+    assert!(!config.debug);
+    assert!(config.hosts.is_empty());
+    assert_eq!(config.server.port, 80);
+}
@@ -0,0 +1,118 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct DbConfig {
+    host: String,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Config {
+    port: u16,
+    #[choices(nested)]
+    db: DbConfig,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct ReplicatedConfig {
+    #[choices(nested)]
+    primary: DbConfig,
+    #[choices(nested)]
+    replica: DbConfig,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+}
+
+async fn put(port: u16, path: &str, body: &'static str) {
+    let response = retry_await!(reqwest::Client::builder()
+        .build()
+        .unwrap()
+        .put(format!("http://127.0.0.1:{}/{}", port, path))
+        .body(body)
+        .send())
+    .unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn subscribe() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)));
+
+    let mut port_receiver = CONFIG.lock().unwrap().subscribe_port();
+    let mut host_receiver = CONFIG.lock().unwrap().db.subscribe_host();
+    let mut subscription = CONFIG.lock().unwrap().subscribe();
+    assert_eq!(*port_receiver.borrow_and_update(), 0);
+
+    put(port, "config/port", "80").await;
+    port_receiver.changed().await.unwrap();
+    assert_eq!(*port_receiver.borrow_and_update(), 80);
+    let event = subscription.recv().await.unwrap();
+    assert_eq!((event.field.as_str(), event.value.as_str()), ("port", "80"));
+
+    CONFIG.lock().unwrap().db.set_host("remote").unwrap();
+    host_receiver.changed().await.unwrap();
+    assert_eq!(*host_receiver.borrow_and_update(), "remote");
+    assert!(!port_receiver.has_changed().unwrap());
+    let event = subscription.recv().await.unwrap();
+    assert_eq!(
+        (event.field.as_str(), event.value.as_str()),
+        ("db.host", "remote")
+    );
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn subscribe_per_instance() {
+    let mut config = ReplicatedConfig::default();
+    let mut other = ReplicatedConfig::default();
+    let mut subscription = config.subscribe();
+    let mut other_subscription = other.subscribe();
+
+    // Each change of a configuration nested twice is received once, under its own path.
+    config.primary.set_host("primary").unwrap();
+    config.replica.set_host("replica").unwrap();
+    let event = subscription.recv().await.unwrap();
+    assert_eq!(
+        (event.field.as_str(), event.value.as_str()),
+        ("primary.host", "primary")
+    );
+    let event = subscription.recv().await.unwrap();
+    assert_eq!(
+        (event.field.as_str(), event.value.as_str()),
+        ("replica.host", "replica")
+    );
+
+    // Other configurations of the same type have their own events.
+    other.primary.set_host("other").unwrap();
+    let event = other_subscription.recv().await.unwrap();
+    assert_eq!(
+        (event.field.as_str(), event.value.as_str()),
+        ("primary.host", "other")
+    );
+    let pending = tokio::time::timeout(Duration::from_millis(100), subscription.recv()).await;
+    assert!(pending.is_err());
+
+    // Fields are watched in their own configuration.
+    let mut replica_host = config.replica.subscribe_host();
+    let other_host = other.replica.subscribe_host();
+    assert_eq!(*replica_host.borrow_and_update(), "replica");
+    assert_eq!(*other_host.borrow(), "");
+    config.primary.set_host("remote").unwrap();
+    assert!(!replica_host.has_changed().unwrap());
+    other.replica.set_host("other").unwrap();
+    assert!(!replica_host.has_changed().unwrap());
+    assert_eq!(*other_host.borrow(), "other");
+}
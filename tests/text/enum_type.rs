@@ -0,0 +1,60 @@
+use choices::{Choices, ChoicesValue};
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(ChoicesValue, Default)]
+enum LogFormat {
+    #[default]
+    #[choices(rename = "json")]
+    Json,
+    #[choices(rename = "text")]
+    Text,
+}
+
+#[derive(ChoicesValue)]
+enum Strategy {
+    Fast,
+    Safe,
+}
+
+#[derive(Choices)]
+struct Config {
+    format: LogFormat,
+    strategy: Strategy,
+    state: choices::State,
+}
+
+#[tokio::test]
+async fn enum_type() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+                format: LogFormat::default(),
+                strategy: Strategy::Safe,
+                state: Default::default(),
+            }));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    check_get_text!(
+        port,
+        "config",
+        "Available configuration options:\n  - format: LogFormat [json, text]\n  \
+            - strategy: Strategy [Fast, Safe]\n"
+    );
+
+    check_get_field_text!(port, format, "json");
+    check_get_field_text!(port, strategy, "Safe");
+    check_put_field_text!(port, format, "text", 200, "text");
+    check_put_field_text!(port, format, "Text", 400, "text");
+    check_put_field_text!(port, strategy, "Fast", 200, "Fast");
+    check_put_field_text!(port, strategy, "slow", 400, "Fast");
+
+    rt.shutdown_background();
+}
@@ -11,6 +11,7 @@ struct Config {
     retries: u8,
     delay: f64,
     score: Option<i32>,
+    state: choices::State,
 }
 
 async fn get_list_impl<F>(port: u16, server_future: F)
@@ -36,14 +37,14 @@ where
 async fn get_list() {
     let port = get_free_port!();
     get_list_impl(port, async move {
-        Config {
+        static CONFIG: Config = Config {
             debug: true,
             retries: 3,
             delay: 0.1,
             score: Some(3),
-        }
-        .run((std::net::Ipv4Addr::LOCALHOST, port))
-        .await
+            state: choices::State::new(),
+        };
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
     })
     .await;
 }
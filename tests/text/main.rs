@@ -1,3 +1,4 @@
+mod enum_type;
 mod get;
 mod list;
 mod put;
@@ -8,6 +8,7 @@ use util::*;
 #[derive(Choices)]
 struct SimpleConfig {
     debug: bool,
+    state: choices::State,
 }
 
 #[tokio::test]
@@ -17,8 +18,10 @@ async fn put_non_existing_field() {
     let rt = Runtime::new().unwrap();
     rt.spawn(async move {
         lazy_static! {
-            static ref CONFIG: Arc<Mutex<SimpleConfig>> =
-                Arc::new(Mutex::new(SimpleConfig { debug: true }));
+            static ref CONFIG: Arc<Mutex<SimpleConfig>> = Arc::new(Mutex::new(SimpleConfig {
+                debug: true,
+                state: Default::default()
+            }));
         }
         CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
     });
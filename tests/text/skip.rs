@@ -8,6 +8,7 @@ use util::*;
 pub struct SkipConfig {
     #[choices(skip)]
     pub debug: bool,
+    pub state: choices::State,
 }
 
 #[tokio::test]
@@ -1,6 +1,8 @@
 use choices::bytes::Bytes;
-use choices::{Choices, ChoicesInput, ChoicesOutput, ChoicesResult};
+use choices::{Choices, ChoicesInput, ChoicesOutput, ChoicesResult, ChoicesValue};
 use lazy_static::lazy_static;
+use std::fmt;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use tokio::runtime::Runtime;
 use util::*;
@@ -26,6 +28,7 @@ impl<T: Default, U: Default> ChoicesOutput for UserType<T, U> {
 #[derive(Choices, Default)]
 struct Config {
     field: UserType<u8, u8>,
+    state: choices::State,
 }
 
 #[tokio::test]
@@ -53,3 +56,98 @@ async fn user_type() {
 
     rt.shutdown_background();
 }
+
+#[derive(ChoicesValue)]
+#[choices(delimiter = ".")]
+struct TimePoint {
+    years: u32,
+    seconds: u32,
+}
+
+#[derive(ChoicesValue)]
+#[choices(delimiter = ":")]
+struct Address(String, u16);
+
+#[derive(ChoicesValue)]
+#[choices(delimiter = ",")]
+enum Shape {
+    #[choices(rename = "point")]
+    Point,
+    #[choices(rename = "circle")]
+    Circle { radius: f32 },
+    #[choices(rename = "rect")]
+    Rect(u8, u8),
+}
+
+#[derive(ChoicesValue)]
+#[choices(from_str)]
+struct Percent(u8);
+
+impl FromStr for Percent {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Percent(s.trim_end_matches('%').parse()?))
+    }
+}
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+#[derive(Choices)]
+struct DerivedConfig {
+    time: TimePoint,
+    address: Address,
+    shape: Shape,
+    percent: Percent,
+    state: choices::State,
+}
+
+#[tokio::test]
+async fn derived_user_type() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(async move {
+        lazy_static! {
+            static ref CONFIG: Arc<Mutex<DerivedConfig>> = Arc::new(Mutex::new(DerivedConfig {
+                time: TimePoint {
+                    years: 1,
+                    seconds: 2
+                },
+                address: Address("localhost".to_string(), 80),
+                shape: Shape::Point,
+                percent: Percent(10),
+                state: Default::default(),
+            }));
+        }
+        CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)).await
+    });
+
+    check_get_field_text!(port, time, "1.2");
+    check_put_field_text!(port, time, "42.3", 200, "42.3");
+    check_put_field_text!(port, time, "42", 400, "42.3");
+    check_put_field_text!(port, time, "a.3", 400, "42.3");
+
+    check_get_field_text!(port, address, "localhost:80");
+    check_put_field_text!(port, address, "[::1]:8080", 400, "localhost:80");
+    check_put_field_text!(port, address, "remote:8080", 200, "remote:8080");
+    // Delimiters inside the fields are escaped.
+    check_put_field_text!(port, address, r"[\:\:1]:8080", 200, r"[\:\:1]:8080");
+
+    check_get_field_text!(port, shape, "point");
+    check_put_field_text!(port, shape, "circle,1.5", 200, "circle,1.5");
+    check_put_field_text!(port, shape, "rect,2,3", 200, "rect,2,3");
+    check_put_field_text!(port, shape, "rect,2", 400, "rect,2,3");
+    check_put_field_text!(port, shape, "square,2", 400, "rect,2,3");
+    check_put_field_text!(port, shape, "point", 200, "point");
+
+    check_get_field_text!(port, percent, "10%");
+    check_put_field_text!(port, percent, "50%", 200, "50%");
+    check_put_field_text!(port, percent, "half", 400, "50%");
+
+    rt.shutdown_background();
+}
@@ -0,0 +1,96 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use reqwest::{Certificate, Client, Identity};
+use std::sync::{Arc, Mutex};
+use util::*;
+
+#[derive(Choices)]
+struct Config {
+    debug: bool,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Config = Config {
+        debug: true,
+        state: Default::default()
+    };
+    static ref MUTABLE_CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        debug: false,
+        state: Default::default()
+    }));
+}
+
+const CA: &str = "tests/tls/ca.pem";
+const SERVER_CERT: &str = "tests/tls/server.pem";
+const SERVER_KEY: &str = "tests/tls/server.key";
+const CLIENT_CERT: &str = "tests/tls/client.pem";
+const CLIENT_KEY: &str = "tests/tls/client.key";
+
+fn ca() -> Certificate {
+    Certificate::from_pem(&std::fs::read(CA).unwrap()).unwrap()
+}
+
+fn client_identity() -> Identity {
+    Identity::from_pkcs8_pem(
+        &std::fs::read(CLIENT_CERT).unwrap(),
+        &std::fs::read(CLIENT_KEY).unwrap(),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn run_tls() {
+    let port = get_free_port!();
+    tokio::spawn(CONFIG.run_tls(
+        (std::net::Ipv4Addr::LOCALHOST, port),
+        SERVER_CERT,
+        SERVER_KEY,
+    ));
+
+    let client = Client::builder()
+        .add_root_certificate(ca())
+        .build()
+        .unwrap();
+    let response = retry_await!(client
+        .get(format!("https://localhost:{}/config/debug", port))
+        .send())
+    .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "true");
+
+    // Plain http isn't served.
+    assert!(
+        reqwest::get(format!("http://localhost:{}/config/debug", port))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn run_tls_with_client_auth() {
+    let port = get_free_port!();
+    tokio::spawn(MUTABLE_CONFIG.run_tls_with_client_auth(
+        (std::net::Ipv4Addr::LOCALHOST, port),
+        SERVER_CERT,
+        SERVER_KEY,
+        CA,
+    ));
+    let url = format!("https://localhost:{}/config/debug", port);
+
+    let client = Client::builder()
+        .add_root_certificate(ca())
+        .identity(client_identity())
+        .build()
+        .unwrap();
+    let response = retry_await!(client.put(&url).body("true").send()).unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(MUTABLE_CONFIG.lock().unwrap().debug);
+
+    // Clients without a certificate are rejected.
+    let client = Client::builder()
+        .add_root_certificate(ca())
+        .build()
+        .unwrap();
+    assert!(client.get(&url).send().await.is_err());
+}
@@ -0,0 +1,96 @@
+#![cfg(unix)]
+
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+#[derive(Choices)]
+struct Config {
+    debug: bool,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Config = Config {
+        debug: true,
+        state: Default::default()
+    };
+    static ref MUTABLE_CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config {
+        debug: false,
+        state: Default::default()
+    }));
+}
+
+fn socket_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("choices-unix-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+/// Sends a raw http request over the socket at `path` and returns the response.
+async fn request(path: &Path, method: &str, uri: &str, body: &str) -> String {
+    let mut stream = loop {
+        match UnixStream::connect(path).await {
+            Ok(stream) => break stream,
+            Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+        }
+    };
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        uri,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn run_unix() {
+    let path = socket_path("config.sock");
+    // Leave a stale socket file behind, as a crashed server would.
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+    tokio::spawn(CONFIG.run_unix(path.clone()));
+
+    let response = request(&path, "GET", "/config/debug", "").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\ntrue"));
+
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    // The socket is in use now.
+    let err = CONFIG.run_unix(path).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+}
+
+#[tokio::test]
+async fn run_unix_with_mode() {
+    let path = socket_path("mutable.sock");
+    tokio::spawn(MUTABLE_CONFIG.run_unix_with_mode(path.clone(), 0o660));
+
+    let response = request(&path, "PUT", "/config/debug", "true").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(MUTABLE_CONFIG.lock().unwrap().debug);
+
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o660);
+}
+
+#[tokio::test]
+async fn not_a_socket() {
+    let path = socket_path("file.sock");
+    std::fs::write(&path, "").unwrap();
+
+    let err = CONFIG.run_unix(&path).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert!(path.exists());
+}
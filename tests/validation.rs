@@ -8,6 +8,7 @@ use util::*;
 struct Config {
     #[choices(validator = check_value)]
     value: i32,
+    state: choices::State,
 }
 
 const GOOD_VALUE: i32 = 1;
@@ -59,3 +60,13 @@ async fn validation_for_put() {
 
     rt.shutdown_background();
 }
+
+#[tokio::test]
+async fn validation_for_put_on_bound_port() {
+    lazy_static! {
+        static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+    }
+    let port = serve!(CONFIG);
+
+    check_put_field_text!(port, value, format!("{}", BAD_VALUE), 400, 0.to_string());
+}
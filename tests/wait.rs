@@ -0,0 +1,82 @@
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use util::*;
+
+#[derive(Choices, Default)]
+struct Config {
+    debug: bool,
+    port: u16,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+}
+
+async fn get(port: u16, path: &str) -> reqwest::Response {
+    retry_await!(reqwest::get(format!("http://127.0.0.1:{}/{}", port, path))).unwrap()
+}
+
+/// Returns the revision in the `ETag` of `response`.
+fn revision(response: &reqwest::Response) -> u64 {
+    response.headers()["ETag"]
+        .to_str()
+        .unwrap()
+        .trim_matches('"')
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn wait_field() {
+    let port = get_free_port!();
+
+    let rt = Runtime::new().unwrap();
+    rt.spawn(CONFIG.run((std::net::Ipv4Addr::LOCALHOST, port)));
+
+    let response = get(port, "config/port").await;
+    let port_revision = revision(&response);
+
+    // Without changes, the request returns the current value when the timeout elapses.
+    let response = get(
+        port,
+        &format!("config/port?wait={}&timeout=100ms", port_revision),
+    )
+    .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(revision(&response), port_revision);
+    assert_eq!(response.text().await.unwrap(), "0");
+
+    // Waiters are woken by setters, but not by changes of other fields.
+    let waiter =
+        tokio::spawn(
+            async move { get(port, &format!("config/port?wait={}", port_revision)).await },
+        );
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    CONFIG.lock().unwrap().set_debug(true).unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(!waiter.is_finished());
+    CONFIG.lock().unwrap().set_port(80u16).unwrap();
+    let response = waiter.await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(revision(&response) > port_revision);
+    assert_eq!(response.text().await.unwrap(), "80");
+
+    // Past revisions return immediately.
+    let response = get(port, &format!("config/port?wait={}", port_revision)).await;
+    assert_eq!(response.text().await.unwrap(), "80");
+
+    // Invalid parameters are replied as the other errors.
+    let response = get(port, "config/port?wait=x").await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/plain; charset=utf-8"
+    );
+    let response = get(port, "config/port?wait=1&timeout=1h").await;
+    assert_eq!(response.status(), 400);
+
+    rt.shutdown_background();
+}
@@ -0,0 +1,177 @@
+use choices::serde_json::{self, json, Value};
+use choices::Choices;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+use util::check_port;
+
+#[derive(Choices, Default)]
+struct DbConfig {
+    #[choices(validator = check_port)]
+    port: u16,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+struct Config {
+    debug: bool,
+    #[choices(hide_get)]
+    password: String,
+    #[choices(nested)]
+    db: DbConfig,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonDbConfig {
+    hosts: Vec<String>,
+    state: choices::State,
+}
+
+#[derive(Choices, Default)]
+#[choices(json)]
+struct JsonConfig {
+    #[choices(validator = check_port)]
+    port: u16,
+    #[choices(nested)]
+    db: JsonDbConfig,
+    state: choices::State,
+}
+
+lazy_static! {
+    static ref CONFIG: Arc<Mutex<Config>> = Arc::new(Mutex::new(Config::default()));
+    static ref JSON_CONFIG: Arc<Mutex<JsonConfig>> = Arc::new(Mutex::new(JsonConfig::default()));
+}
+
+/// Sends `request` and returns the next message received.
+async fn request(client: &mut choices::warp::test::WsClient, request: Value) -> Value {
+    client.send_text(request.to_string()).await;
+    receive(client).await
+}
+
+async fn receive(client: &mut choices::warp::test::WsClient) -> Value {
+    let message = client.recv().await.unwrap();
+    serde_json::from_str(message.to_str().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn ws_text() {
+    let mut client = choices::warp::test::ws()
+        .path("/config/_ws")
+        .handshake(Config::filter_mutable(CONFIG.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        request(&mut client, json!({"id": 1, "op": "get", "field": "debug"})).await,
+        json!({"id": 1, "ok": true, "value": "false"})
+    );
+    assert_eq!(
+        request(&mut client, json!({"id": 2, "op": "subscribe"})).await,
+        json!({"id": 2, "ok": true})
+    );
+    assert_eq!(
+        request(
+            &mut client,
+            json!({"id": 3, "op": "set", "field": "db.port", "value": "80"})
+        )
+        .await,
+        json!({"id": 3, "ok": true})
+    );
+    assert_eq!(
+        receive(&mut client).await,
+        json!({"event": "change", "field": "db.port", "value": "80", "revision": 1})
+    );
+    assert_eq!(CONFIG.lock().unwrap().db.port, 80);
+
+    // Errors are the json objects of the HTTP requests.
+    assert_eq!(
+        request(
+            &mut client,
+            json!({"id": 4, "op": "set", "field": "db.port", "value": "0"})
+        )
+        .await,
+        json!({
+            "id": 4,
+            "ok": false,
+            "error": {
+                "error": "validation_error",
+                "field": "db.port",
+                "message": "ValidationError: port must be positive",
+            },
+        })
+    );
+    assert_eq!(
+        request(
+            &mut client,
+            json!({"id": 5, "op": "get", "field": "password"})
+        )
+        .await,
+        json!({
+            "id": 5,
+            "ok": false,
+            "error": {
+                "error": "parse_error",
+                "message": "ParseError: unknown field `password`",
+            },
+        })
+    );
+    assert_eq!(
+        request(
+            &mut client,
+            json!({"id": 6, "op": "set", "field": "debug", "value": true})
+        )
+        .await,
+        json!({
+            "id": 6,
+            "ok": false,
+            "error": {"error": "parse_error", "message": "ParseError: expected a string"},
+        })
+    );
+}
+
+#[tokio::test]
+async fn ws_json() {
+    let mut client = choices::warp::test::ws()
+        .path("/config/_ws")
+        .handshake(JsonConfig::filter_mutable(JSON_CONFIG.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        request(&mut client, json!({"id": "a", "op": "subscribe"})).await,
+        json!({"id": "a", "ok": true})
+    );
+    assert_eq!(
+        request(
+            &mut client,
+            json!({"id": "b", "op": "set", "field": "db.hosts", "value": ["a", "b"]})
+        )
+        .await,
+        json!({"id": "b", "ok": true})
+    );
+    assert_eq!(
+        receive(&mut client).await,
+        json!({"event": "change", "field": "db.hosts", "value": ["a", "b"], "revision": 1})
+    );
+    assert_eq!(
+        request(&mut client, json!({"id": "c", "op": "get", "field": "db"})).await,
+        json!({"id": "c", "ok": true, "value": {"hosts": ["a", "b"]}})
+    );
+    assert_eq!(
+        request(
+            &mut client,
+            json!({"id": "d", "op": "set", "field": "port", "value": 0})
+        )
+        .await,
+        json!({
+            "id": "d",
+            "ok": false,
+            "error": {
+                "error": "validation_error",
+                "field": "port",
+                "message": "ValidationError: port must be positive",
+            },
+        })
+    );
+}
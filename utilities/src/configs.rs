@@ -24,6 +24,7 @@ macro_rules! scalar_config {
             uintsize: usize,
             float: f32,
             double: f64,
+            state: choices::State,
         }
 
         impl ScalarConfig {
@@ -45,6 +46,7 @@ macro_rules! scalar_config {
                     uintsize: 6,
                     float: 5.5,
                     double: 3.2,
+                    state: Default::default(),
                 }
             }
         }
@@ -59,17 +61,20 @@ pub mod text {
     #[derive(Choices)]
     pub struct SimpleBoolConfig {
         pub debug: bool,
+        pub state: choices::State,
     }
 
     #[derive(Choices, Default)]
     pub struct StringConfig {
         pub string: String,
+        pub state: choices::State,
     }
 
     #[derive(Choices, Default)]
     pub struct OptionConfig {
         pub character: Option<char>,
         pub empty: Option<bool>,
+        pub state: choices::State,
     }
 }
 
@@ -82,6 +87,7 @@ pub mod json {
     #[choices(json)]
     pub struct StringConfig {
         pub string: String,
+        pub state: choices::State,
     }
 
     #[derive(Choices, Default)]
@@ -89,18 +95,21 @@ pub mod json {
     pub struct OptionConfig {
         pub character: Option<char>,
         pub empty: Option<bool>,
+        pub state: choices::State,
     }
 
     #[derive(Choices, Default)]
     #[choices(json)]
     pub struct VecConfig {
         pub vector: Vec<u8>,
+        pub state: choices::State,
     }
 
     impl VecConfig {
         pub fn new() -> Self {
             Self {
                 vector: vec![1, 2, 3],
+                state: Default::default(),
             }
         }
     }
@@ -6,9 +6,15 @@ pub use configs::*;
 pub mod constants;
 pub use constants::*;
 
+pub mod requests;
+pub use requests::*;
+
 pub mod test_cases;
 pub use test_cases::*;
 
+pub mod validators;
+pub use validators::*;
+
 /// Retries the given operation 50 times, waiting 1ms in between.
 /// `op` must return an awaitable future.
 #[macro_export]
@@ -37,6 +43,17 @@ macro_rules! get_free_port {
     }};
 }
 
+/// Binds the server of the configuration `config` to a free port of localhost, spawning it on
+/// the current tokio runtime, and returns the port.
+#[macro_export]
+macro_rules! serve {
+    ($config:expr) => {{
+        let (addr, server) = choices::Choices::bind(&*$config, (std::net::Ipv4Addr::LOCALHOST, 0));
+        tokio::spawn(server);
+        addr.port()
+    }};
+}
+
 #[macro_export]
 macro_rules! check_get {
     ($port:expr, $path:expr, $expected:expr, $content_type:expr) => {
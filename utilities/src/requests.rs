@@ -0,0 +1,50 @@
+//! Requests to the servers of the tests.
+
+use crate::retry_await;
+use reqwest::header::HeaderMap;
+use reqwest::Method;
+
+/// Reply to a request.
+#[derive(Debug)]
+pub struct Reply {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl Reply {
+    /// Returns the value of the header `name`, if any.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(name)
+            .map(|value| value.to_str().expect("invalid header"))
+    }
+}
+
+/// Sends a request with the method `method`, the headers `headers` and the body `body` to the
+/// path `path` of a server running on localhost on port `port`, retrying while the server
+/// isn't listening yet.
+pub async fn send(
+    method: Method,
+    port: u16,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &'static str,
+) -> Reply {
+    let url = format!("http://127.0.0.1:{}/{}", port, path.trim_start_matches('/'));
+    let response = retry_await!({
+        let mut request = reqwest::Client::new()
+            .request(method.clone(), &url)
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.send()
+    })
+    .unwrap();
+    Reply {
+        status: response.status().as_u16(),
+        headers: response.headers().clone(),
+        body: response.text().await.unwrap(),
+    }
+}
@@ -0,0 +1,14 @@
+//! Validators commonly used in the integration tests.
+
+use choices::{ChoicesError, ChoicesResult};
+
+/// Accepts only positive ports.
+pub fn check_port(port: &u16) -> ChoicesResult<()> {
+    if *port > 0 {
+        Ok(())
+    } else {
+        Err(ChoicesError::ValidationError(
+            "port must be positive".to_string(),
+        ))
+    }
+}